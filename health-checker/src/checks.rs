@@ -1,71 +1,426 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use log::{info, warn};
-use std::process::Command;
+use std::path::Path;
 
+use crate::config::{DockerDaemonConfig, SystemConfig};
+use crate::docker::{DaemonDiagnostics, DockerClient, LabeledContainer};
+use crate::severity::{escalate, severity};
 use crate::{HealthCheckResult, HealthStatus};
 
+mod disks;
+mod host;
+mod mounts;
+mod ports;
+
+pub use disks::DiskTrend;
+
+fn parse_api_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Run blocking filesystem work on its own thread; None if it does not finish within `timeout`.
+/// A call stuck in the kernel on a dead network mount cannot be cancelled, so the thread is left
+/// behind rather than tying up the runtime's blocking pool, which would also delay process exit.
+async fn run_in_worker<T: Send + 'static>(
+    timeout: std::time::Duration,
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<Option<T>> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    std::thread::Builder::new()
+        .name("fs-probe".to_string())
+        .spawn(move || {
+            let _ = sender.send(work());
+        })
+        .context("Cannot start worker thread")?;
+    match tokio::time::timeout(timeout, receiver).await {
+        Ok(result) => Ok(Some(result.map_err(|_| anyhow!("Worker thread panicked"))?)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn read_proc_file(proc_root: &Path, name: &str) -> Result<String> {
+    let path = proc_root.join(name);
+    std::fs::read_to_string(&path).with_context(|| format!("Cannot read {}", path.display()))
+}
+
+/// Memory figures from `/proc/meminfo`, in kB
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemInfo {
+    pub mem_total_kb: u64,
+    pub mem_available_kb: u64,
+    pub swap_total_kb: u64,
+    pub swap_free_kb: u64,
+}
+
+impl MemInfo {
+    /// Memory not available to new workloads without swapping, in percent
+    pub fn used_percent(&self) -> f64 {
+        if self.mem_total_kb == 0 {
+            return 0.0;
+        }
+        self.mem_total_kb.saturating_sub(self.mem_available_kb) as f64 / self.mem_total_kb as f64 * 100.0
+    }
+
+    /// None on hosts without swap
+    pub fn swap_used_percent(&self) -> Option<f64> {
+        (self.swap_total_kb > 0)
+            .then(|| self.swap_total_kb.saturating_sub(self.swap_free_kb) as f64 / self.swap_total_kb as f64 * 100.0)
+    }
+}
+
+pub fn parse_meminfo(content: &str) -> Result<MemInfo> {
+    let field = |name: &str| -> Result<u64> {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next()?.parse().ok())
+            .ok_or_else(|| anyhow!("meminfo has no {} field", name))
+    };
+    Ok(MemInfo {
+        mem_total_kb: field("MemTotal")?,
+        // Present since Linux 3.14
+        mem_available_kb: field("MemAvailable")?,
+        swap_total_kb: field("SwapTotal")?,
+        swap_free_kb: field("SwapFree")?,
+    })
+}
+
+/// Run queue averages from `/proc/loadavg`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadAvg {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+pub fn parse_loadavg(content: &str) -> Result<LoadAvg> {
+    let mut fields = content.split_whitespace().map(|field| field.parse::<f64>());
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(Ok(one)), Some(Ok(five)), Some(Ok(fifteen))) => Ok(LoadAvg { one, five, fifteen }),
+        _ => Err(anyhow!("Unexpected loadavg format: {}", content.trim())),
+    }
+}
+
+/// Cumulative CPU time in clock ticks, summed over all CPUs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CpuTimes {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+}
+
+/// Share of CPU time between two `/proc/stat` reads, in percent
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuShares {
+    pub iowait: f64,
+    pub steal: f64,
+}
+
+impl CpuTimes {
+    // guest and guest_nice are already included in user and nice
+    fn total(&self) -> u64 {
+        self.user + self.nice + self.system + self.idle + self.iowait + self.irq + self.softirq + self.steal
+    }
+
+    /// None when no CPU time passed since `earlier`
+    pub fn shares_since(&self, earlier: &CpuTimes) -> Option<CpuShares> {
+        let elapsed = self.total().checked_sub(earlier.total()).filter(|&ticks| ticks > 0)? as f64;
+        Some(CpuShares {
+            iowait: self.iowait.saturating_sub(earlier.iowait) as f64 / elapsed * 100.0,
+            steal: self.steal.saturating_sub(earlier.steal) as f64 / elapsed * 100.0,
+        })
+    }
+}
+
+/// The aggregate `cpu` line of `/proc/stat` and the number of online CPUs
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcStat {
+    pub cpu: CpuTimes,
+    pub cores: usize,
+}
+
+pub fn parse_proc_stat(content: &str) -> Result<ProcStat> {
+    let line = content
+        .lines()
+        .find(|line| line.split_whitespace().next() == Some("cpu"))
+        .ok_or_else(|| anyhow!("stat has no aggregate cpu line"))?;
+    let values = line
+        .split_whitespace()
+        .skip(1)
+        .map(|value| value.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Unexpected cpu line: {}", line))?;
+    if values.len() < 4 {
+        return Err(anyhow!("Unexpected cpu line: {}", line));
+    }
+    // Older kernels stop before steal; missing columns count as zero
+    let value = |index: usize| values.get(index).copied().unwrap_or(0);
+
+    let cores = content
+        .lines()
+        .filter_map(|line| line.split_whitespace().next()?.strip_prefix("cpu"))
+        .filter(|suffix| !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit()))
+        .count();
+
+    Ok(ProcStat {
+        cpu: CpuTimes {
+            user: value(0),
+            nice: value(1),
+            system: value(2),
+            idle: value(3),
+            iowait: value(4),
+            irq: value(5),
+            softirq: value(6),
+            steal: value(7),
+        },
+        cores,
+    })
+}
+
+/// `kB` value as GiB, as shown by free -h
+fn format_kib(kb: u64) -> String {
+    format!("{:.1} GiB", kb as f64 / 1024.0 / 1024.0)
+}
+
 /// Additional health check utilities and system checks
 pub struct SystemChecker;
 
 impl SystemChecker {
-    /// Check if Docker is running and accessible
-    pub fn check_docker_daemon() -> Result<(HealthStatus, String)> {
-        match Command::new("docker").arg("version").output() {
-            Ok(output) => {
-                if output.status.success() {
-                    let version_info = String::from_utf8_lossy(&output.stdout);
-                    let version_line = version_info
-                        .lines()
-                        .find(|line| line.contains("Version:"))
-                        .unwrap_or("Docker daemon running");
-                    Ok((HealthStatus::Healthy, version_line.to_string()))
-                } else {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    Ok((HealthStatus::Unhealthy, format!("Docker command failed: {}", error)))
-                }
+    /// Check the Docker daemon through the Engine API
+    pub async fn check_docker_daemon(docker: &DockerClient, thresholds: &DockerDaemonConfig) -> Result<(HealthStatus, String)> {
+        match docker.collect_daemon_diagnostics().await {
+            Ok(diagnostics) => {
+                // The data root can only be measured when the engine runs on this machine
+                let data_root_usage = diagnostics.docker_root_dir.as_deref()
+                    .filter(|_| docker.is_local())
+                    .and_then(|dir| Self::filesystem_usage_percent(Path::new(dir)).ok());
+                Ok(Self::evaluate_docker_daemon(&diagnostics, data_root_usage, thresholds))
             }
-            Err(e) => Ok((HealthStatus::Unhealthy, format!("Docker not found or not running: {}", e))),
+            Err(e) => Ok((HealthStatus::Unhealthy, format!("Docker daemon not accessible: {}", e))),
         }
     }
 
-    /// Check system resources (CPU, Memory, Disk)
-    pub fn check_system_resources() -> Result<(HealthStatus, String)> {
+    pub fn evaluate_docker_daemon(
+        diagnostics: &DaemonDiagnostics,
+        data_root_usage: Option<f64>,
+        thresholds: &DockerDaemonConfig,
+    ) -> (HealthStatus, String) {
         let mut status = HealthStatus::Healthy;
-        let mut details = Vec::new();
+        let mut issues = Vec::new();
+
+        // The client must speak an API version inside the server's supported range
+        let client = parse_api_version(&diagnostics.client_api_version);
+        let server = parse_api_version(&diagnostics.api_version);
+        let server_min = parse_api_version(&diagnostics.min_api_version);
+        if let (Some(client), Some(server_min)) = (client, server_min) {
+            if client < server_min {
+                issues.push(format!(
+                    "Client API {} older than server minimum {}",
+                    diagnostics.client_api_version, diagnostics.min_api_version
+                ));
+                escalate(&mut status, HealthStatus::Unhealthy);
+            }
+        }
+        if let (Some(client), Some(server)) = (client, server) {
+            if client > server {
+                issues.push(format!(
+                    "Client API {} newer than server API {}",
+                    diagnostics.client_api_version, diagnostics.api_version
+                ));
+                escalate(&mut status, HealthStatus::Degraded);
+            }
+        }
 
-        // Check available memory using `vm_stat` on macOS
-        if let Ok(output) = Command::new("vm_stat").output() {
-            if output.status.success() {
-                let _vm_info = String::from_utf8_lossy(&output.stdout);
-                details.push("Memory check completed".to_string());
-            } else {
-                details.push("Memory check failed".to_string());
-                status = HealthStatus::Degraded;
+        if let Some(usage) = data_root_usage {
+            if let Some(level) = severity(usage, &thresholds.data_root_used) {
+                let issue = match level {
+                    HealthStatus::Unhealthy => "Data root critically full",
+                    _ => "Data root filling up",
+                };
+                issues.push(format!("{}: {:.1}%", issue, usage));
+                escalate(&mut status, level);
             }
         }
 
-        // Check disk space for current directory
-        if let Ok(output) = Command::new("df").arg("-h").arg(".").output() {
-            if output.status.success() {
-                let df_info = String::from_utf8_lossy(&output.stdout);
-                if let Some(line) = df_info.lines().nth(1) {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 5 {
-                        let usage_percent = parts[4].trim_end_matches('%');
-                        if let Ok(usage) = usage_percent.parse::<u32>() {
-                            if usage > 90 {
-                                status = HealthStatus::Degraded;
-                                details.push(format!("Disk usage high: {}%", usage));
-                            } else {
-                                details.push(format!("Disk usage: {}%", usage));
-                            }
-                        }
-                    }
+        if let Some(level) = severity(diagnostics.dangling_images as f64, &thresholds.dangling_images) {
+            issues.push(format!("{} dangling images", diagnostics.dangling_images));
+            escalate(&mut status, level);
+        }
+
+        if let Some(level) = severity(diagnostics.dangling_volumes as f64, &thresholds.dangling_volumes) {
+            issues.push(format!("{} dangling volumes", diagnostics.dangling_volumes));
+            escalate(&mut status, level);
+        }
+
+        let build_cache_mib = diagnostics.build_cache.size_bytes / 1024 / 1024;
+        if let Some(level) = severity(build_cache_mib as f64, &thresholds.build_cache_mib) {
+            issues.push(format!("Build cache {}MB", build_cache_mib));
+            escalate(&mut status, level);
+        }
+
+        let mut details = format!(
+            "Docker {} (API {}, client {}), driver: {}, images: {} ({}MB), containers: {} ({}MB), volumes: {} ({}MB), build cache: {}MB",
+            diagnostics.server_version,
+            diagnostics.api_version,
+            diagnostics.client_api_version,
+            diagnostics.storage_driver,
+            diagnostics.images.count,
+            diagnostics.images.size_bytes / 1024 / 1024,
+            diagnostics.containers.count,
+            diagnostics.containers.size_bytes / 1024 / 1024,
+            diagnostics.volumes.count,
+            diagnostics.volumes.size_bytes / 1024 / 1024,
+            diagnostics.build_cache.size_bytes / 1024 / 1024,
+        );
+        if let Some(usage) = data_root_usage {
+            details.push_str(&format!(", data root: {:.1}%", usage));
+        }
+        details.push_str(&format!(
+            ", dangling: {} images/{} volumes, nvidia runtime: {}",
+            diagnostics.dangling_images,
+            diagnostics.dangling_volumes,
+            if diagnostics.nvidia_runtime_registered() { "yes" } else { "no" }
+        ));
+        if !issues.is_empty() {
+            details.push_str(&format!(" - Issues: {}", issues.join(", ")));
+        }
+
+        (status, details)
+    }
+
+    /// Percentage of blocks in use on the filesystem holding `path`
+    pub fn filesystem_usage_percent(path: &Path) -> Result<f64> {
+        Ok(disks::disk_usage(path)?.used_percent())
+    }
+
+    /// Check memory, CPU and disk usage of this host from procfs and statvfs
+    pub async fn check_system_resources(config: &SystemConfig, trend: Option<&mut DiskTrend>) -> Vec<HealthCheckResult> {
+        let proc_root = config.proc_root.as_path();
+        let mut results = Vec::new();
+
+        let start_time = std::time::Instant::now();
+        let memory = read_proc_file(proc_root, "meminfo")
+            .and_then(|content| parse_meminfo(&content))
+            .map(|memory| Self::evaluate_memory(&memory, config));
+        results.push(Self::resource_result("System Memory", memory, start_time));
+
+        let start_time = std::time::Instant::now();
+        let cpu = Self::sample_cpu(config)
+            .await
+            .map(|(load, earlier, later)| Self::evaluate_cpu(&load, &earlier, &later, config));
+        results.push(Self::resource_result("System CPU", cpu, start_time));
+
+        results.extend(Self::check_disks(config, trend).await);
+        results.extend(Self::check_network_mounts(config).await);
+        results
+    }
+
+    /// Load averages and two `/proc/stat` snapshots taken `cpu_sample_millis` apart
+    async fn sample_cpu(config: &SystemConfig) -> Result<(LoadAvg, ProcStat, ProcStat)> {
+        let proc_root = config.proc_root.as_path();
+        let load = parse_loadavg(&read_proc_file(proc_root, "loadavg")?)?;
+        let earlier = parse_proc_stat(&read_proc_file(proc_root, "stat")?)?;
+        tokio::time::sleep(std::time::Duration::from_millis(config.cpu_sample_millis)).await;
+        let later = parse_proc_stat(&read_proc_file(proc_root, "stat")?)?;
+        Ok((load, earlier, later))
+    }
+
+    fn resource_result(
+        service_name: &str,
+        evaluation: Result<(HealthStatus, String)>,
+        start_time: std::time::Instant,
+    ) -> HealthCheckResult {
+        let (status, details, error_message) = match evaluation {
+            Ok((status, details)) => (status, details, None),
+            Err(e) => (HealthStatus::Unknown, format!("{:#}", e), Some(e.to_string())),
+        };
+        HealthCheckResult {
+            service_name: service_name.to_string(),
+            status,
+            response_time_ms: start_time.elapsed().as_millis() as u64,
+            details,
+            timestamp: chrono::Utc::now(),
+            error_message,
+        }
+    }
+
+    pub fn evaluate_memory(memory: &MemInfo, config: &SystemConfig) -> (HealthStatus, String) {
+        let mut status = HealthStatus::Healthy;
+        let mut issues = Vec::new();
+
+        let used_percent = memory.used_percent();
+        if let Some(level) = severity(used_percent, &config.memory_used) {
+            issues.push(format!("Memory {:.1}% used", used_percent));
+            escalate(&mut status, level);
+        }
+
+        let mut details = format!(
+            "{} of {} available ({:.1}% used)",
+            format_kib(memory.mem_available_kb),
+            format_kib(memory.mem_total_kb),
+            used_percent
+        );
+        match memory.swap_used_percent() {
+            Some(swap_percent) => {
+                if let Some(level) = severity(swap_percent, &config.swap_used) {
+                    issues.push(format!("Swap {:.1}% used", swap_percent));
+                    escalate(&mut status, level);
                 }
+                details.push_str(&format!(
+                    ", swap: {} of {} used ({:.1}%)",
+                    format_kib(memory.swap_total_kb - memory.swap_free_kb),
+                    format_kib(memory.swap_total_kb),
+                    swap_percent
+                ));
             }
+            None => details.push_str(", no swap"),
         }
 
-        Ok((status, details.join(", ")))
+        if !issues.is_empty() {
+            details.push_str(&format!(" - Issues: {}", issues.join(", ")));
+        }
+        (status, details)
+    }
+
+    pub fn evaluate_cpu(load: &LoadAvg, earlier: &ProcStat, later: &ProcStat, config: &SystemConfig) -> (HealthStatus, String) {
+        let mut status = HealthStatus::Healthy;
+        let mut issues = Vec::new();
+
+        let load_per_core = load.five / later.cores.max(1) as f64;
+        if let Some(level) = severity(load_per_core, &config.load_per_core) {
+            issues.push(format!("Load {:.2} per core", load_per_core));
+            escalate(&mut status, level);
+        }
+
+        let mut details = format!(
+            "{} CPUs, load {:.2}/{:.2}/{:.2} ({:.2} per core over 5 min)",
+            later.cores, load.one, load.five, load.fifteen, load_per_core
+        );
+        match later.cpu.shares_since(&earlier.cpu) {
+            Some(shares) => {
+                if let Some(level) = severity(shares.steal, &config.cpu_steal) {
+                    issues.push(format!("Steal {:.1}%", shares.steal));
+                    escalate(&mut status, level);
+                }
+                if let Some(level) = severity(shares.iowait, &config.cpu_iowait) {
+                    issues.push(format!("I/O wait {:.1}%", shares.iowait));
+                    escalate(&mut status, level);
+                }
+                details.push_str(&format!(", steal: {:.1}%, iowait: {:.1}%", shares.steal, shares.iowait));
+            }
+            None => details.push_str(", steal and iowait not measured (no CPU time elapsed)"),
+        }
+
+        if !issues.is_empty() {
+            details.push_str(&format!(" - Issues: {}", issues.join(", ")));
+        }
+        (status, details)
     }
 
     /// Check network connectivity to essential services
@@ -123,8 +478,6 @@ impl SystemChecker {
 
     /// Check Backend.AI configuration files
     pub fn check_configuration_files() -> Result<(HealthStatus, String)> {
-        use std::path::Path;
-
         let config_files = vec![
             ("manager.toml", "Manager configuration"),
             ("agent.toml", "Agent configuration"), 
@@ -166,77 +519,19 @@ impl SystemChecker {
         Ok((status, details))
     }
 
-    /// Check if required ports are available/in use
-    pub async fn check_port_usage() -> Result<(HealthStatus, String)> {
-        let required_ports = vec![
-            (8081, "Manager API"),
-            (8101, "PostgreSQL"),
-            (8111, "Redis"),
-            (8121, "etcd"),
-            (9090, "Prometheus"),
-            (3000, "Grafana"),
-        ];
-
-        let mut ports_in_use = Vec::new();
-        let mut ports_available = Vec::new();
-
-        for (port, service) in required_ports {
-            let address = format!("127.0.0.1:{}", port);
-            match tokio::net::TcpStream::connect(&address).await {
-                Ok(_) => {
-                    ports_in_use.push(format!("{} ({})", port, service));
-                }
-                Err(_) => {
-                    ports_available.push(format!("{} ({})", port, service));
-                }
-            }
-        }
-
-        // For a health check, we want most ports to be in use (services running)
-        let status = if ports_in_use.len() >= 4 {
-            HealthStatus::Healthy
-        } else if ports_in_use.len() >= 2 {
-            HealthStatus::Degraded
-        } else {
-            HealthStatus::Unhealthy
-        };
-
-        let details = format!(
-            "{} services listening, {} ports available",
-            ports_in_use.len(),
-            ports_available.len()
-        );
-
-        Ok((status, details))
-    }
-
     /// Comprehensive system health check
-    pub async fn comprehensive_system_check() -> Result<Vec<HealthCheckResult>> {
+    pub async fn comprehensive_system_check(
+        config: &SystemConfig,
+        trend: Option<&mut DiskTrend>,
+        containers: Option<&[LabeledContainer]>,
+    ) -> Result<Vec<HealthCheckResult>> {
         use chrono::Utc;
-        
-        let mut results = Vec::new();
-
-        // Docker daemon check
-        let (status, details) = Self::check_docker_daemon()?;
-        results.push(HealthCheckResult {
-            service_name: "Docker Daemon".to_string(),
-            status,
-            response_time_ms: 0,
-            details,
-            timestamp: Utc::now(),
-            error_message: None,
-        });
 
         // System resources check
-        let (status, details) = Self::check_system_resources()?;
-        results.push(HealthCheckResult {
-            service_name: "System Resources".to_string(),
-            status,
-            response_time_ms: 0,
-            details,
-            timestamp: Utc::now(),
-            error_message: None,
-        });
+        let mut results = Self::check_system_resources(config, trend).await;
+
+        // Kernel tunables and host prerequisites
+        results.extend(Self::audit_host(config));
 
         // Configuration files check
         let (status, details) = Self::check_configuration_files()?;
@@ -261,18 +556,224 @@ impl SystemChecker {
             error_message: None,
         });
 
-        // Port usage check
-        let start_time = std::time::Instant::now();
-        let (status, details) = Self::check_port_usage().await?;
-        results.push(HealthCheckResult {
-            service_name: "Port Usage".to_string(),
-            status,
-            response_time_ms: start_time.elapsed().as_millis() as u64,
-            details,
-            timestamp: Utc::now(),
-            error_message: None,
-        });
+        // Owners of the service ports
+        results.extend(Self::check_port_usage(config, containers));
 
         Ok(results)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{NetworkMountsConfig, Threshold};
+    use std::path::PathBuf;
+
+    const MEMINFO: &str = include_str!("../tests/fixtures/proc/meminfo");
+    const LOADAVG: &str = include_str!("../tests/fixtures/proc/loadavg");
+    const STAT: &str = include_str!("../tests/fixtures/proc/stat");
+    const STAT_AFTER_1S: &str = include_str!("../tests/fixtures/proc-stat/stat-after-1s");
+
+    fn fixture_config() -> SystemConfig {
+        SystemConfig {
+            proc_root: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc"),
+            cpu_sample_millis: 0,
+            // The fixture mountinfo lists NFS and CephFS mounts that do not exist here
+            network_mounts: NetworkMountsConfig { discover: false, ..NetworkMountsConfig::default() },
+            ..SystemConfig::default()
+        }
+    }
+
+    #[test]
+    fn parses_meminfo() {
+        let memory = parse_meminfo(MEMINFO).unwrap();
+        assert_eq!(memory.mem_total_kb, 1056475068);
+        assert_eq!(memory.mem_available_kb, 31694252);
+        assert_eq!(memory.swap_total_kb, 8388604);
+        assert_eq!(memory.swap_free_kb, 3187668);
+        assert!((memory.used_percent() - 97.0).abs() < 0.01);
+        assert!((memory.swap_used_percent().unwrap() - 62.0).abs() < 0.01);
+
+        let error = parse_meminfo("MemTotal: 1024 kB\nMemFree: 512 kB\n").unwrap_err();
+        assert_eq!(error.to_string(), "meminfo has no MemAvailable field");
+    }
+
+    #[test]
+    fn memory_pressure_and_swap_are_reported() {
+        let memory = parse_meminfo(MEMINFO).unwrap();
+        let (status, details) = SystemChecker::evaluate_memory(&memory, &SystemConfig::default());
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert_eq!(
+            details,
+            "30.2 GiB of 1007.5 GiB available (97.0% used), swap: 5.0 GiB of 8.0 GiB used (62.0%) - Issues: Memory 97.0% used"
+        );
+
+        let config = SystemConfig {
+            memory_used: Threshold::default(),
+            swap_used: Threshold { degraded: Some(50.0), unhealthy: None },
+            ..SystemConfig::default()
+        };
+        let (status, details) = SystemChecker::evaluate_memory(&memory, &config);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: Swap 62.0% used"));
+
+        let no_swap = MemInfo { swap_total_kb: 0, swap_free_kb: 0, ..memory };
+        let (_, details) = SystemChecker::evaluate_memory(&no_swap, &config);
+        assert!(details.ends_with(", no swap"));
+    }
+
+    #[test]
+    fn parses_loadavg() {
+        assert_eq!(parse_loadavg(LOADAVG).unwrap(), LoadAvg { one: 21.84, five: 19.02, fifteen: 17.55 });
+        assert!(parse_loadavg("").is_err());
+    }
+
+    #[test]
+    fn steal_and_iowait_come_from_stat_deltas() {
+        let earlier = parse_proc_stat(STAT).unwrap();
+        let later = parse_proc_stat(STAT_AFTER_1S).unwrap();
+        assert_eq!(earlier.cores, 8);
+        assert_eq!(earlier.cpu.steal, 6498760);
+
+        let shares = later.cpu.shares_since(&earlier.cpu).unwrap();
+        assert!((shares.steal - 27.0).abs() < 0.01);
+        assert!((shares.iowait - 4.0).abs() < 0.01);
+        assert_eq!(earlier.cpu.shares_since(&earlier.cpu), None);
+    }
+
+    #[test]
+    fn old_kernels_without_steal_column() {
+        let stat = parse_proc_stat("cpu  100 0 50 1000 7\ncpu0 100 0 50 1000 7\nintr 0\n").unwrap();
+        assert_eq!(stat.cores, 1);
+        assert_eq!(stat.cpu.iowait, 7);
+        assert_eq!(stat.cpu.steal, 0);
+        assert!(parse_proc_stat("intr 0\n").is_err());
+    }
+
+    #[test]
+    fn cpu_load_steal_and_iowait_are_checked() {
+        let load = parse_loadavg(LOADAVG).unwrap();
+        let earlier = parse_proc_stat(STAT).unwrap();
+        let later = parse_proc_stat(STAT_AFTER_1S).unwrap();
+
+        let (status, details) = SystemChecker::evaluate_cpu(&load, &earlier, &later, &SystemConfig::default());
+        assert_eq!(status, HealthStatus::Degraded);
+        assert_eq!(
+            details,
+            "8 CPUs, load 21.84/19.02/17.55 (2.38 per core over 5 min), steal: 27.0%, iowait: 4.0% - Issues: Load 2.38 per core, Steal 27.0%"
+        );
+
+        let config = SystemConfig {
+            load_per_core: Threshold { degraded: Some(2.0), unhealthy: Some(2.2) },
+            cpu_iowait: Threshold { degraded: Some(3.0), unhealthy: None },
+            ..SystemConfig::default()
+        };
+        let (status, details) = SystemChecker::evaluate_cpu(&load, &earlier, &later, &config);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Load 2.38 per core, Steal 27.0%, I/O wait 4.0%"));
+    }
+
+    #[tokio::test]
+    async fn resources_are_read_from_proc_root() {
+        let results = SystemChecker::check_system_resources(&fixture_config(), None).await;
+        let names: Vec<&str> = results.iter().map(|result| result.service_name.as_str()).collect();
+        assert_eq!(names, ["System Memory", "System CPU", "Disk (Root)"]);
+        assert_eq!(results[0].status, HealthStatus::Unhealthy);
+        // Both reads see the same fixture, so no CPU time elapses in between
+        assert!(results[1].details.contains("steal and iowait not measured"));
+        assert_ne!(results[2].status, HealthStatus::Unknown);
+
+        let missing = SystemConfig { proc_root: PathBuf::from("/nonexistent/proc"), ..fixture_config() };
+        let results = SystemChecker::check_system_resources(&missing, None).await;
+        assert_eq!(results[0].status, HealthStatus::Unknown);
+        assert!(results[0].details.starts_with("Cannot read /nonexistent/proc/meminfo"));
+        assert_eq!(results[1].status, HealthStatus::Unknown);
+    }
+
+    fn daemon_diagnostics() -> DaemonDiagnostics {
+        use crate::docker::DiskUsage;
+        DaemonDiagnostics {
+            server_version: "24.0.7".to_string(),
+            api_version: "1.43".to_string(),
+            min_api_version: "1.12".to_string(),
+            client_api_version: "1.41".to_string(),
+            storage_driver: "overlay2".to_string(),
+            docker_root_dir: Some("/var/lib/docker".to_string()),
+            default_runtime: Some("runc".to_string()),
+            runtimes: vec!["nvidia".to_string(), "runc".to_string()],
+            images: DiskUsage { count: 42, size_bytes: 30 * 1024 * 1024 * 1024 },
+            containers: DiskUsage { count: 12, size_bytes: 512 * 1024 * 1024 },
+            volumes: DiskUsage { count: 8, size_bytes: 2 * 1024 * 1024 * 1024 },
+            build_cache: DiskUsage { count: 3, size_bytes: 100 * 1024 * 1024 },
+            dangling_images: 4,
+            dangling_volumes: 1,
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_api_versions() {
+        assert_eq!(parse_api_version("1.43"), Some((1, 43)));
+        assert_eq!(parse_api_version("1.9"), Some((1, 9)));
+        // Compared numerically, so 1.9 is older than 1.41
+        assert!(parse_api_version("1.9") < parse_api_version("1.41"));
+        assert_eq!(parse_api_version("1"), None);
+        assert_eq!(parse_api_version("v1.43"), None);
+        assert_eq!(parse_api_version(""), None);
+    }
+
+    #[test]
+    fn healthy_daemon_is_summarized() {
+        let (status, details) = SystemChecker::evaluate_docker_daemon(&daemon_diagnostics(), Some(40.0), &DockerDaemonConfig::default());
+        assert_eq!(status, HealthStatus::Healthy);
+        assert_eq!(
+            details,
+            "Docker 24.0.7 (API 1.43, client 1.41), driver: overlay2, images: 42 (30720MB), containers: 12 (512MB), \
+             volumes: 8 (2048MB), build cache: 100MB, data root: 40.0%, dangling: 4 images/1 volumes, nvidia runtime: yes"
+        );
+    }
+
+    #[test]
+    fn daemon_api_mismatch_is_reported() {
+        let too_old = DaemonDiagnostics { min_api_version: "1.44".to_string(), ..daemon_diagnostics() };
+        let (status, details) = SystemChecker::evaluate_docker_daemon(&too_old, None, &DockerDaemonConfig::default());
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Client API 1.41 older than server minimum 1.44"), "{}", details);
+
+        let too_new = DaemonDiagnostics { api_version: "1.40".to_string(), ..daemon_diagnostics() };
+        let (status, details) = SystemChecker::evaluate_docker_daemon(&too_new, None, &DockerDaemonConfig::default());
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: Client API 1.41 newer than server API 1.40"), "{}", details);
+    }
+
+    #[test]
+    fn daemon_thresholds_come_from_config() {
+        let cluttered = DaemonDiagnostics {
+            dangling_images: 51,
+            dangling_volumes: 21,
+            build_cache: crate::docker::DiskUsage { count: 90, size_bytes: 21 * 1024 * 1024 * 1024 },
+            ..daemon_diagnostics()
+        };
+        let (status, details) = SystemChecker::evaluate_docker_daemon(&cluttered, Some(90.0), &DockerDaemonConfig::default());
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(
+            details.ends_with("Issues: Data root filling up: 90.0%, 51 dangling images, 21 dangling volumes, Build cache 21504MB"),
+            "{}",
+            details
+        );
+
+        let (status, details) = SystemChecker::evaluate_docker_daemon(&daemon_diagnostics(), Some(96.0), &DockerDaemonConfig::default());
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Data root critically full: 96.0%"), "{}", details);
+
+        let strict = DockerDaemonConfig {
+            data_root_used: Threshold { degraded: None, unhealthy: Some(30.0) },
+            dangling_images: Threshold { degraded: Some(3.0), unhealthy: None },
+            dangling_volumes: Threshold::default(),
+            build_cache_mib: Threshold::default(),
+        };
+        let (status, details) = SystemChecker::evaluate_docker_daemon(&cluttered, Some(40.0), &strict);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Data root critically full: 40.0%, 51 dangling images"), "{}", details);
+    }
+}
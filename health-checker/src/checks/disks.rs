@@ -0,0 +1,511 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::mounts::blocked_probes;
+use crate::config::{DiskConfig, SystemConfig};
+use crate::severity::{escalate, severity};
+use crate::{HealthCheckResult, HealthStatus};
+
+/// statvfs can block on a dead network filesystem; the checker moves on after this long
+const STATVFS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Block and inode counts of one filesystem from statvfs
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    /// Free space usable by unprivileged users
+    pub available_bytes: u64,
+    pub inodes_total: u64,
+    pub inodes_free: u64,
+    pub read_only: bool,
+}
+
+impl DiskUsage {
+    /// Match df(1): used / (used + available to unprivileged users)
+    pub fn used_percent(&self) -> f64 {
+        let usable = self.used_bytes + self.available_bytes;
+        if usable == 0 {
+            return 0.0;
+        }
+        self.used_bytes as f64 / usable as f64 * 100.0
+    }
+
+    /// None on filesystems without a fixed inode table, such as btrfs
+    pub fn inodes_used_percent(&self) -> Option<f64> {
+        (self.inodes_total > 0)
+            .then(|| self.inodes_total.saturating_sub(self.inodes_free) as f64 / self.inodes_total as f64 * 100.0)
+    }
+}
+
+// statvfs field widths differ between platforms
+#[allow(clippy::unnecessary_cast)]
+pub fn disk_usage(path: &Path) -> Result<DiskUsage> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let block_size = if stat.f_frsize > 0 { stat.f_frsize as u64 } else { stat.f_bsize as u64 };
+    Ok(DiskUsage {
+        total_bytes: stat.f_blocks as u64 * block_size,
+        used_bytes: (stat.f_blocks as u64).saturating_sub(stat.f_bfree as u64) * block_size,
+        available_bytes: stat.f_bavail as u64 * block_size,
+        inodes_total: stat.f_files as u64,
+        inodes_free: stat.f_ffree as u64,
+        read_only: stat.f_flag as u64 & libc::ST_RDONLY as u64 != 0,
+    })
+}
+
+/// One line of `/proc/<pid>/mountinfo`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountInfo {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+    /// Per-mount options such as `ro` or `noatime`
+    pub options: Vec<String>,
+    /// Filesystem-wide options such as `errors=remount-ro`
+    pub super_options: Vec<String>,
+}
+
+/// Parse mountinfo, skipping lines that do not have the expected fields
+pub fn parse_mountinfo(content: &str) -> Vec<MountInfo> {
+    content
+        .lines()
+        .filter_map(|line| {
+            // Optional fields end with a lone `-`, followed by type, source and super options
+            let (mount_fields, fs_fields) = line.split_once(" - ")?;
+            let mount_fields: Vec<&str> = mount_fields.split(' ').collect();
+            let mut fs_fields = fs_fields.split(' ');
+            if mount_fields.len() < 6 {
+                return None;
+            }
+            Some(MountInfo {
+                mount_point: PathBuf::from(unescape_mount_field(mount_fields[4])),
+                options: mount_fields[5].split(',').map(str::to_string).collect(),
+                fs_type: fs_fields.next()?.to_string(),
+                source: unescape_mount_field(fs_fields.next()?),
+                super_options: fs_fields.next().unwrap_or_default().split(',').map(str::to_string).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Mounts of the checker's own mount namespace; empty when mountinfo cannot be read
+pub fn read_mountinfo(proc_root: &Path) -> Vec<MountInfo> {
+    let mountinfo_path = proc_root.join("self/mountinfo");
+    match std::fs::read_to_string(&mountinfo_path) {
+        Ok(content) => parse_mountinfo(&content),
+        Err(e) => {
+            log::debug!("Cannot read {}: {}", mountinfo_path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// Undo the octal escapes the kernel uses for space, tab, newline and backslash
+fn unescape_mount_field(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(position) = rest.find('\\') {
+        unescaped.push_str(&rest[..position]);
+        let escape = rest.get(position + 1..position + 4);
+        match escape.and_then(|digits| u8::from_str_radix(digits, 8).ok()) {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[position + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[position + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// The mount `path` lives on; later mounts shadow earlier ones at the same point
+pub fn mount_containing<'a>(mounts: &'a [MountInfo], path: &Path) -> Option<&'a MountInfo> {
+    mounts
+        .iter()
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        .max_by_key(|mount| mount.mount_point.components().count())
+}
+
+/// Free space of each disk over time, for time-to-full estimates in monitor mode
+pub struct DiskTrend {
+    retention: chrono::Duration,
+    samples: BTreeMap<PathBuf, VecDeque<(DateTime<Utc>, u64)>>,
+}
+
+impl DiskTrend {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention: chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX),
+            samples: BTreeMap::new(),
+        }
+    }
+
+    /// Record the free space of `path` and estimate the hours until it runs out at the recent rate
+    pub fn record(&mut self, path: &Path, available_bytes: u64) -> Option<f64> {
+        self.record_at(Utc::now(), path, available_bytes)
+    }
+
+    fn record_at(&mut self, at: DateTime<Utc>, path: &Path, available_bytes: u64) -> Option<f64> {
+        let samples = self.samples.entry(path.to_path_buf()).or_default();
+        samples.push_back((at, available_bytes));
+        while samples.front().is_some_and(|(sampled_at, _)| at - *sampled_at > self.retention) {
+            samples.pop_front();
+        }
+        hours_to_full(samples)
+    }
+}
+
+/// Least-squares fit of free space over time; None unless free space is shrinking
+fn hours_to_full(samples: &VecDeque<(DateTime<Utc>, u64)>) -> Option<f64> {
+    let (first_at, _) = *samples.front()?;
+    let (_, latest_free) = *samples.back()?;
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|(at, free)| ((*at - first_at).num_milliseconds() as f64 / 3_600_000.0, *free as f64))
+        .collect();
+
+    let count = points.len() as f64;
+    let mean_hours = points.iter().map(|(hours, _)| hours).sum::<f64>() / count;
+    let mean_free = points.iter().map(|(_, free)| free).sum::<f64>() / count;
+    let covariance: f64 = points.iter().map(|(hours, free)| (hours - mean_hours) * (free - mean_free)).sum();
+    let variance: f64 = points.iter().map(|(hours, _)| (hours - mean_hours).powi(2)).sum();
+    if variance == 0.0 {
+        return None;
+    }
+
+    let bytes_per_hour = covariance / variance;
+    (bytes_per_hour < 0.0).then(|| latest_free as f64 / -bytes_per_hour)
+}
+
+/// `resolved_path` is `disk.path` with symlinks resolved, which is what has to be the mount point
+pub fn evaluate_disk(
+    disk: &DiskConfig,
+    resolved_path: &Path,
+    usage: &DiskUsage,
+    mount: Option<&MountInfo>,
+    hours_to_full: Option<f64>,
+) -> (HealthStatus, String) {
+    let mut status = HealthStatus::Healthy;
+    let mut issues = Vec::new();
+
+    if usage.read_only {
+        let remounted = mount.is_some_and(|mount| mount.super_options.iter().any(|option| option == "errors=remount-ro"));
+        issues.push(if remounted {
+            "Read-only (errors=remount-ro, check the kernel log for I/O errors)".to_string()
+        } else {
+            "Read-only".to_string()
+        });
+        status = HealthStatus::Unhealthy;
+    }
+
+    if disk.mount_point {
+        let mounted_at = mount.map(|mount| mount.mount_point.as_path());
+        if mounted_at != Some(resolved_path) {
+            issues.push(match mounted_at {
+                Some(mount_point) => format!("Not mounted, writes go to {}", mount_point.display()),
+                None => "Not mounted".to_string(),
+            });
+            status = HealthStatus::Unhealthy;
+        }
+    }
+
+    let used_percent = usage.used_percent();
+    if let Some(level) = severity(used_percent, &disk.used) {
+        issues.push(format!("Disk {:.1}% full", used_percent));
+        escalate(&mut status, level);
+    }
+
+    let inodes_used_percent = usage.inodes_used_percent();
+    if let Some(percent) = inodes_used_percent {
+        if let Some(level) = severity(percent, &disk.inodes_used) {
+            issues.push(format!("Inodes {:.1}% used", percent));
+            escalate(&mut status, level);
+        }
+    }
+
+    if let (Some(minimum), Some(hours)) = (disk.min_hours_to_full, hours_to_full) {
+        if hours < minimum {
+            issues.push(format!("Full in about {}", format_hours(hours)));
+            escalate(&mut status, HealthStatus::Degraded);
+        }
+    }
+
+    let mut details = disk.path.display().to_string();
+    if let Some(mount) = mount {
+        details.push_str(&format!(" on {} ({} at {})", mount.source, mount.fs_type, mount.mount_point.display()));
+    }
+    details.push_str(&format!(
+        ": {:.1}% used, {} free of {}",
+        used_percent,
+        format_bytes(usage.available_bytes),
+        format_bytes(usage.total_bytes)
+    ));
+    match inodes_used_percent {
+        Some(percent) => details.push_str(&format!(", inodes {:.1}% used", percent)),
+        None => details.push_str(", no inode limit"),
+    }
+    if let Some(hours) = hours_to_full {
+        details.push_str(&format!(", full in ~{}", format_hours(hours)));
+    }
+    if !issues.is_empty() {
+        details.push_str(&format!(" - Issues: {}", issues.join(", ")));
+    }
+
+    (status, details)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.1} GiB", bytes as f64 / 1024.0 / 1024.0 / 1024.0)
+}
+
+fn format_hours(hours: f64) -> String {
+    if hours < 48.0 {
+        format!("{:.1}h", hours)
+    } else {
+        format!("{:.0}d", hours / 24.0)
+    }
+}
+
+impl super::SystemChecker {
+    /// One result per configured data path; `trend` is only kept in monitor mode
+    pub async fn check_disks(config: &SystemConfig, mut trend: Option<&mut DiskTrend>) -> Vec<HealthCheckResult> {
+        let mounts = read_mountinfo(&config.proc_root);
+
+        let mut results = Vec::new();
+        for disk in &config.disks {
+            let start_time = std::time::Instant::now();
+            let evaluation = match Self::stat_disk(&disk.path).await {
+                Ok((canonical_path, usage)) => {
+                    let mount = mount_containing(&mounts, &canonical_path);
+                    let hours_to_full = trend.as_deref_mut().and_then(|trend| trend.record(&disk.path, usage.available_bytes));
+                    evaluate_disk(disk, &canonical_path, &usage, mount, hours_to_full)
+                }
+                Err(e) => (HealthStatus::Unhealthy, format!("{:#}", e)),
+            };
+            results.push(HealthCheckResult {
+                service_name: format!("Disk ({})", disk.name),
+                status: evaluation.0,
+                response_time_ms: start_time.elapsed().as_millis() as u64,
+                details: evaluation.1,
+                timestamp: Utc::now(),
+                error_message: None,
+            });
+        }
+        results
+    }
+
+    /// Resolve symlinks (e.g. a relocated Docker data-root) and statvfs the result on a worker thread.
+    /// A path whose previous statvfs is still blocked is not tried again.
+    async fn stat_disk(path: &Path) -> Result<(PathBuf, DiskUsage)> {
+        {
+            let mut blocked = blocked_probes();
+            if let Some(since) = blocked.get(path) {
+                return Err(anyhow!(
+                    "Previous statvfs on {} still blocked after {}s, not checking again",
+                    path.display(),
+                    since.elapsed().as_secs()
+                ));
+            }
+            blocked.insert(path.to_path_buf(), Instant::now());
+        }
+
+        let owned_path = path.to_path_buf();
+        let stat = move || -> Result<(PathBuf, DiskUsage)> {
+            let outcome = owned_path
+                .canonicalize()
+                .with_context(|| format!("Cannot resolve {}", owned_path.display()))
+                .and_then(|canonical_path| {
+                    let usage = disk_usage(&canonical_path).with_context(|| format!("Cannot stat {}", owned_path.display()))?;
+                    Ok((canonical_path, usage))
+                });
+            blocked_probes().remove(&owned_path);
+            outcome
+        };
+        match super::run_in_worker(STATVFS_TIMEOUT, stat).await {
+            Ok(Some(result)) => result,
+            Ok(None) => Err(anyhow!("statvfs on {} timed out after {}s", path.display(), STATVFS_TIMEOUT.as_secs())),
+            Err(e) => {
+                blocked_probes().remove(path);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Threshold;
+
+    const MOUNTINFO: &str = include_str!("../../tests/fixtures/proc/self/mountinfo");
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn usage(used_gib: u64, available_gib: u64) -> DiskUsage {
+        DiskUsage {
+            total_bytes: (used_gib + available_gib) * GIB,
+            used_bytes: used_gib * GIB,
+            available_bytes: available_gib * GIB,
+            inodes_total: 1000,
+            inodes_free: 900,
+            read_only: false,
+        }
+    }
+
+    fn disk(name: &str, path: &str) -> DiskConfig {
+        DiskConfig { name: name.to_string(), path: PathBuf::from(path), ..DiskConfig::default() }
+    }
+
+    #[test]
+    fn parses_mountinfo() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+        assert_eq!(mounts.len(), 14);
+
+        let etcd = mount_containing(&mounts, Path::new("/var/lib/etcd/member")).unwrap();
+        assert_eq!(etcd.mount_point, Path::new("/var/lib/etcd"));
+        assert_eq!(etcd.fs_type, "ext4");
+        assert_eq!(etcd.source, "/dev/nvme2n1p1");
+        assert_eq!(etcd.options[0], "ro");
+        assert_eq!(etcd.super_options, ["ro", "errors=remount-ro"]);
+
+        let nfs = mount_containing(&mounts, Path::new("/vfroot/local/user/data")).unwrap();
+        assert_eq!((nfs.fs_type.as_str(), nfs.source.as_str()), ("nfs4", "10.0.0.5:/export/vfroot"));
+
+        let scratch = mount_containing(&mounts, Path::new("/mnt/scratch disk/kernel-1")).unwrap();
+        assert_eq!(scratch.source, "/dev/sdb1");
+
+        assert_eq!(mount_containing(&mounts, Path::new("/home/work")).unwrap().mount_point, Path::new("/"));
+        // A sibling with a common string prefix is not inside the mount
+        assert_eq!(mount_containing(&mounts, Path::new("/var/lib/etcd2")).unwrap().mount_point, Path::new("/"));
+    }
+
+    #[test]
+    fn healthy_disk_details() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+        let docker = disk("Docker data root", "/var/lib/docker");
+        let (status, details) = evaluate_disk(&docker, &docker.path, &usage(300, 700), mount_containing(&mounts, &docker.path), None);
+        assert_eq!(status, HealthStatus::Healthy);
+        assert_eq!(
+            details,
+            "/var/lib/docker on /dev/nvme1n1 (xfs at /var/lib/docker): 30.0% used, 700.0 GiB free of 1000.0 GiB, inodes 10.0% used"
+        );
+    }
+
+    #[test]
+    fn per_disk_block_and_inode_thresholds() {
+        let etcd = DiskConfig { used: Threshold { degraded: Some(70.0), unhealthy: Some(80.0) }, ..disk("etcd", "/var/lib/etcd") };
+        assert_eq!(evaluate_disk(&etcd, &etcd.path, &usage(75, 25), None, None).0, HealthStatus::Degraded);
+        let (status, details) = evaluate_disk(&etcd, &etcd.path, &usage(85, 15), None, None);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Disk 85.0% full"));
+
+        let inodes_exhausted = DiskUsage { inodes_free: 20, ..usage(10, 90) };
+        let (status, details) = evaluate_disk(&etcd, &etcd.path, &inodes_exhausted, None, None);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Inodes 98.0% used"));
+
+        let no_inode_table = DiskUsage { inodes_total: 0, inodes_free: 0, ..usage(10, 90) };
+        assert!(evaluate_disk(&etcd, &etcd.path, &no_inode_table, None, None).1.ends_with("no inode limit"));
+    }
+
+    #[test]
+    fn read_only_remount_is_unhealthy() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+        let etcd = disk("etcd", "/var/lib/etcd");
+        let read_only = DiskUsage { read_only: true, ..usage(10, 90) };
+        let (status, details) = evaluate_disk(&etcd, &etcd.path, &read_only, mount_containing(&mounts, &etcd.path), None);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Read-only (errors=remount-ro, check the kernel log for I/O errors)"));
+    }
+
+    #[test]
+    fn unmounted_mount_point_is_unhealthy() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+        let vfolder = DiskConfig { mount_point: true, ..disk("vfolders", "/vfroot/local") };
+        let (status, _) = evaluate_disk(&vfolder, &vfolder.path, &usage(10, 90), mount_containing(&mounts, &vfolder.path), None);
+        assert_eq!(status, HealthStatus::Healthy);
+
+        let missing = DiskConfig { mount_point: true, ..disk("vfolders", "/vfroot/nfs2") };
+        let (status, details) = evaluate_disk(&missing, &missing.path, &usage(10, 90), mount_containing(&mounts, &missing.path), None);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Not mounted, writes go to /"));
+
+        // A symlink to the mount point is checked where it leads
+        let linked = DiskConfig { mount_point: true, ..disk("vfolders", "/data/vfroot") };
+        let resolved = Path::new("/vfroot/local");
+        let (status, details) = evaluate_disk(&linked, resolved, &usage(10, 90), mount_containing(&mounts, resolved), None);
+        assert_eq!(status, HealthStatus::Healthy, "{}", details);
+    }
+
+    #[test]
+    fn time_to_full_from_free_space_trend() {
+        let mut trend = DiskTrend::new(Duration::from_secs(3600));
+        let path = Path::new("/var/lib/etcd");
+        let start = Utc::now();
+        assert_eq!(trend.record_at(start, path, 100 * GIB), None);
+        // 2 GiB less every 10 minutes: 12 GiB/h, 90 GiB left
+        let minutes = |m| start + chrono::Duration::minutes(m);
+        trend.record_at(minutes(10), path, 98 * GIB);
+        trend.record_at(minutes(20), path, 96 * GIB);
+        trend.record_at(minutes(30), path, 94 * GIB);
+        trend.record_at(minutes(40), path, 92 * GIB);
+        let hours = trend.record_at(minutes(50), path, 90 * GIB).unwrap();
+        assert!((hours - 7.5).abs() < 0.01, "{}", hours);
+
+        let (status, details) = evaluate_disk(&disk("etcd", "/var/lib/etcd"), Path::new("/var/lib/etcd"), &usage(10, 90), None, Some(hours));
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with(", full in ~7.5h - Issues: Full in about 7.5h"));
+
+        // Old samples age out, and a disk that stops shrinking has no estimate
+        assert_eq!(trend.record_at(minutes(105), path, 90 * GIB), None);
+        assert_eq!(trend.samples[path].len(), 2);
+        assert_eq!(trend.record_at(minutes(110), path, 95 * GIB), None);
+    }
+
+    #[test]
+    fn unescapes_mount_fields() {
+        assert_eq!(unescape_mount_field("/mnt/scratch\\040disk"), "/mnt/scratch disk");
+        assert_eq!(unescape_mount_field("/a\\134b\\011"), "/a\\b\t");
+        assert_eq!(unescape_mount_field("/odd\\x"), "/odd\\x");
+    }
+
+    #[tokio::test]
+    async fn missing_data_path_is_unhealthy() {
+        let config = SystemConfig {
+            proc_root: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc"),
+            disks: vec![disk("Root", "/"), disk("Postgres data", "/nonexistent/postgres")],
+            ..SystemConfig::default()
+        };
+        let results = super::super::SystemChecker::check_disks(&config, None).await;
+        assert_eq!(results[0].service_name, "Disk (Root)");
+        assert_ne!(results[0].status, HealthStatus::Unknown);
+        assert_eq!(results[1].service_name, "Disk (Postgres data)");
+        assert_eq!(results[1].status, HealthStatus::Unhealthy);
+        assert!(results[1].details.starts_with("Cannot resolve /nonexistent/postgres: "));
+    }
+
+    #[tokio::test]
+    async fn blocked_path_is_not_checked_again() {
+        let path = std::env::temp_dir().join(format!("health-checker-blocked-disk-{}", std::process::id()));
+        blocked_probes().insert(path.clone(), Instant::now());
+        let config = SystemConfig { disks: vec![disk("Scratch", path.to_str().unwrap())], ..SystemConfig::default() };
+        let results = super::super::SystemChecker::check_disks(&config, None).await;
+        blocked_probes().remove(&path);
+        assert_eq!(results[0].status, HealthStatus::Unhealthy);
+        assert!(results[0].details.contains("still blocked after 0s, not checking again"), "{}", results[0].details);
+    }
+}
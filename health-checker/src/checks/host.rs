@@ -0,0 +1,522 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use super::disks;
+use super::ports::PortProcess;
+use crate::config::{HostAuditConfig, SystemConfig};
+use crate::{HealthCheckResult, HealthStatus};
+
+/// Recommended sysctls for agent nodes, which `[system.host_audit.sysctls]` is merged over
+const RECOMMENDED_SYSCTLS: &[(&str, &str)] = &[
+    // Elasticsearch, some JVM and ML workloads map many regions
+    ("vm.max_map_count", ">= 262144"),
+    // Jupyter, VS Code and file sync tools in sessions watch many files
+    ("fs.inotify.max_user_watches", ">= 524288"),
+    ("net.core.somaxconn", ">= 1024"),
+    // Container networking through the Docker bridge
+    ("net.ipv4.ip_forward", "1"),
+];
+
+const AGENT_MODULE: &str = "ai.backend.agent";
+const AGENT_WATCHER_MODULE: &str = "ai.backend.agent.watcher";
+
+/// Expected value of a sysctl
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    Exact(String),
+    AtLeast(u64),
+}
+
+impl Expectation {
+    /// `">= N"` is a minimum, anything else an exact value compared with whitespace normalized
+    pub fn parse(expected: &str) -> Result<Self> {
+        match expected.trim().strip_prefix(">=") {
+            Some(minimum) => minimum
+                .trim()
+                .parse()
+                .map(Expectation::AtLeast)
+                .map_err(|_| anyhow!("Invalid minimum {:?}", expected)),
+            None => Ok(Expectation::Exact(normalize_whitespace(expected))),
+        }
+    }
+
+    fn is_met(&self, current: &str) -> bool {
+        match self {
+            Expectation::Exact(expected) => &normalize_whitespace(current) == expected,
+            Expectation::AtLeast(minimum) => current.trim().parse::<u64>().is_ok_and(|value| value >= *minimum),
+        }
+    }
+
+    /// Value to set to meet the expectation
+    fn target(&self) -> String {
+        match self {
+            Expectation::Exact(expected) => expected.clone(),
+            Expectation::AtLeast(minimum) => minimum.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::Exact(expected) => write!(f, "{}", expected),
+            Expectation::AtLeast(minimum) => write!(f, ">= {}", minimum),
+        }
+    }
+}
+
+fn normalize_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A host setting that differs from the profile
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deviation {
+    pub setting: String,
+    pub current: String,
+    pub expected: String,
+    pub fix: String,
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is {}, expected {} (fix: {})", self.setting, self.current, self.expected, self.fix)
+    }
+}
+
+/// The built-in sysctl profile with config overrides applied; empty overrides drop an entry
+pub fn sysctl_profile(overrides: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut profile: BTreeMap<String, String> = RECOMMENDED_SYSCTLS
+        .iter()
+        .map(|(name, expected)| (name.to_string(), expected.to_string()))
+        .collect();
+    for (name, expected) in overrides {
+        if expected.trim().is_empty() {
+            profile.remove(name);
+        } else {
+            profile.insert(name.clone(), expected.clone());
+        }
+    }
+    profile
+}
+
+/// Current values of the profile's sysctls and the ones that deviate
+pub fn audit_sysctls(proc_root: &Path, profile: &BTreeMap<String, String>) -> (Vec<String>, Vec<Deviation>) {
+    let mut values = Vec::new();
+    let mut deviations = Vec::new();
+    for (name, expected) in profile {
+        let path = proc_root.join("sys").join(name.replace('.', "/"));
+        let current = std::fs::read_to_string(&path).ok().map(|value| normalize_whitespace(&value));
+        match Expectation::parse(expected) {
+            Ok(expectation) => {
+                if current.as_deref().is_some_and(|current| expectation.is_met(current)) {
+                    values.push(format!("{}={}", name, current.unwrap_or_default()));
+                    continue;
+                }
+                deviations.push(Deviation {
+                    setting: name.clone(),
+                    current: current.unwrap_or_else(|| "missing".to_string()),
+                    expected: expectation.to_string(),
+                    fix: format!("sysctl -w {}={} and persist it in /etc/sysctl.d/", name, expectation.target()),
+                });
+            }
+            Err(e) => deviations.push(Deviation {
+                setting: name.clone(),
+                current: current.unwrap_or_else(|| "missing".to_string()),
+                expected: expected.clone(),
+                fix: format!("correct [system.host_audit.sysctls]: {}", e),
+            }),
+        }
+    }
+    (values, deviations)
+}
+
+/// Module names from `/proc/modules`
+pub fn parse_loaded_modules(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Module names from `modules.builtin`, e.g. `kernel/fs/overlayfs/overlay.ko`
+pub fn parse_builtin_modules(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| Path::new(line.trim()).file_stem()?.to_str())
+        .map(|name| name.replace('-', "_"))
+        .collect()
+}
+
+/// Modules may be loaded (`/proc/modules`) or built into the kernel (`/sys/module/<name>` or
+/// `modules.builtin`); kernels without module support have no `/proc/modules` at all
+pub fn audit_modules(proc_root: &Path, sysfs_root: &Path, modules: &[String]) -> Vec<Deviation> {
+    let loaded = std::fs::read_to_string(proc_root.join("modules"))
+        .map(|content| parse_loaded_modules(&content))
+        .unwrap_or_default();
+    let builtin = std::fs::read_to_string(proc_root.join("sys/kernel/osrelease"))
+        .and_then(|release| std::fs::read_to_string(format!("/lib/modules/{}/modules.builtin", release.trim())))
+        .map(|content| parse_builtin_modules(&content))
+        .unwrap_or_default();
+
+    modules
+        .iter()
+        .filter(|module| {
+            // The kernel reports `br-netfilter` as `br_netfilter`
+            let name = module.replace('-', "_");
+            !loaded.contains(&name) && !builtin.contains(&name) && !sysfs_root.join("module").join(&name).exists()
+        })
+        .map(|module| Deviation {
+            setting: format!("module {}", module),
+            current: "not loaded".to_string(),
+            expected: "loaded".to_string(),
+            fix: format!("modprobe {0} and add it to /etc/modules-load.d/{0}.conf", module),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CgroupVersion {
+    V1,
+    V2,
+}
+
+/// cgroup version and controllers that are not available to the agent
+pub fn audit_cgroups(sysfs_root: &Path, controllers: &[String]) -> Result<(CgroupVersion, Vec<Deviation>)> {
+    let cgroup_root = sysfs_root.join("fs/cgroup");
+    let unified_controllers = cgroup_root.join("cgroup.controllers");
+
+    if unified_controllers.exists() {
+        let available = std::fs::read_to_string(&unified_controllers)
+            .with_context(|| format!("Cannot read {}", unified_controllers.display()))?;
+        let available: Vec<&str> = available.split_whitespace().collect();
+        let deviations = controllers
+            .iter()
+            .filter(|controller| !available.contains(&controller.as_str()))
+            .map(|controller| Deviation {
+                setting: format!("cgroup controller {}", controller),
+                current: "not in cgroup.controllers".to_string(),
+                expected: "available".to_string(),
+                fix: format!(
+                    "check that the kernel supports {0} and that cgroup_disable={0} is not on the kernel command line",
+                    controller
+                ),
+            })
+            .collect();
+        return Ok((CgroupVersion::V2, deviations));
+    }
+
+    if !cgroup_root.is_dir() {
+        return Err(anyhow!("No cgroup filesystem at {}", cgroup_root.display()));
+    }
+    let deviations = controllers
+        .iter()
+        .map(|controller| match controller.as_str() {
+            "io" => "blkio",
+            other => other,
+        })
+        .filter(|controller| !cgroup_root.join(controller).is_dir())
+        .map(|controller| Deviation {
+            setting: format!("cgroup controller {}", controller),
+            current: "not mounted".to_string(),
+            expected: format!("mounted at {}", cgroup_root.join(controller).display()),
+            fix: format!(
+                "mount -t cgroup -o {0} cgroup {1}, or boot with systemd.unified_cgroup_hierarchy=1 for cgroup v2",
+                controller,
+                cgroup_root.join(controller).display()
+            ),
+        })
+        .collect();
+    Ok((CgroupVersion::V1, deviations))
+}
+
+pub fn audit_shm(shm_path: &Path, size_bytes: u64, min_mib: u64) -> Option<Deviation> {
+    let size_mib = size_bytes / 1024 / 1024;
+    (size_mib < min_mib).then(|| Deviation {
+        setting: shm_path.display().to_string(),
+        current: format!("{} MiB", size_mib),
+        expected: format!(">= {} MiB", min_mib),
+        fix: format!(
+            "mount -o remount,size={}M {} and set the size in /etc/fstab",
+            min_mib,
+            shm_path.display()
+        ),
+    })
+}
+
+pub fn audit_nofile(soft: u64, hard: u64, minimum: u64) -> Option<Deviation> {
+    (soft < minimum).then(|| Deviation {
+        setting: "open file limit".to_string(),
+        current: format!("{} (hard {})", soft, hard),
+        expected: format!(">= {}", minimum),
+        fix: "raise LimitNOFILE in the agent's systemd unit, or nofile in /etc/security/limits.conf".to_string(),
+    })
+}
+
+/// Soft and hard limits from the `Max open files` line of `/proc/<pid>/limits`
+pub fn parse_nofile_limits(content: &str) -> Option<(u64, u64)> {
+    let values = content.lines().find_map(|line| line.strip_prefix("Max open files"))?;
+    let mut values = values.split_whitespace().map(|value| match value {
+        "unlimited" => Some(u64::MAX),
+        value => value.parse().ok(),
+    });
+    Some((values.next()??, values.next()??))
+}
+
+/// Lowest PID with `ai.backend.agent` on its command line, leaving out the agent watcher
+pub fn find_agent_pid(proc_root: &Path) -> Option<u32> {
+    std::fs::read_dir(proc_root)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| PortProcess::read(proc_root, pid))
+        .filter(|process| process.cmdline.contains(AGENT_MODULE) && !process.cmdline.contains(AGENT_WATCHER_MODULE))
+        .map(|process| process.pid)
+        .min()
+}
+
+/// Soft and hard open file limits of the running agent, which its sessions inherit
+fn agent_nofile_limits(proc_root: &Path) -> Result<(u32, u64, u64)> {
+    let pid = find_agent_pid(proc_root)
+        .ok_or_else(|| anyhow!("No process with {} on its command line", AGENT_MODULE))?;
+    let limits_path = proc_root.join(pid.to_string()).join("limits");
+    let content = std::fs::read_to_string(&limits_path)
+        .with_context(|| format!("Cannot read {}", limits_path.display()))?;
+    let (soft, hard) = parse_nofile_limits(&content)
+        .ok_or_else(|| anyhow!("No open file limit in {}", limits_path.display()))?;
+    Ok((pid, soft, hard))
+}
+
+/// `summary` when nothing deviates, otherwise every deviation at `severity`
+fn deviations_evaluation(summary: String, deviations: &[Deviation], severity: HealthStatus) -> (HealthStatus, String) {
+    if deviations.is_empty() {
+        return (HealthStatus::Healthy, summary);
+    }
+    let issues: Vec<String> = deviations.iter().map(Deviation::to_string).collect();
+    (severity, format!("{} - Issues: {}", summary, issues.join("; ")))
+}
+
+impl super::SystemChecker {
+    /// Compare sysctls, kernel modules, cgroups, `/dev/shm` and the agent's open file limit with the profile
+    pub fn audit_host(config: &SystemConfig) -> Vec<HealthCheckResult> {
+        let audit: &HostAuditConfig = &config.host_audit;
+        if !audit.enabled {
+            return Vec::new();
+        }
+        let mut results = Vec::new();
+
+        let start_time = std::time::Instant::now();
+        let (values, deviations) = audit_sysctls(&config.proc_root, &sysctl_profile(&audit.sysctls));
+        let summary = match values.is_empty() {
+            true => "No sysctl matches the profile".to_string(),
+            false => values.join(", "),
+        };
+        let evaluation = deviations_evaluation(summary, &deviations, HealthStatus::Degraded);
+        results.push(Self::resource_result("Host Sysctls", Ok(evaluation), start_time));
+
+        let start_time = std::time::Instant::now();
+        let deviations = audit_modules(&config.proc_root, &audit.sysfs_root, &audit.modules);
+        let summary = format!("Required: {}", audit.modules.join(", "));
+        let evaluation = deviations_evaluation(summary, &deviations, HealthStatus::Degraded);
+        results.push(Self::resource_result("Host Kernel Modules", Ok(evaluation), start_time));
+
+        // Without its controllers the agent cannot enforce the resource limits of sessions
+        let start_time = std::time::Instant::now();
+        let evaluation = audit_cgroups(&audit.sysfs_root, &audit.cgroup_controllers).map(|(version, deviations)| {
+            let version = match version {
+                CgroupVersion::V1 => "v1",
+                CgroupVersion::V2 => "v2",
+            };
+            let summary = format!("cgroup {}, required controllers: {}", version, audit.cgroup_controllers.join(", "));
+            deviations_evaluation(summary, &deviations, HealthStatus::Unhealthy)
+        });
+        results.push(Self::resource_result("Host cgroups", evaluation, start_time));
+
+        if audit.shm_min_mib > 0 {
+            let start_time = std::time::Instant::now();
+            let evaluation = disks::disk_usage(&audit.shm_path)
+                .with_context(|| format!("Cannot stat {}", audit.shm_path.display()))
+                .map(|usage| {
+                    let deviation = audit_shm(&audit.shm_path, usage.total_bytes, audit.shm_min_mib);
+                    let summary = format!("{}: {} MiB", audit.shm_path.display(), usage.total_bytes / 1024 / 1024);
+                    deviations_evaluation(summary, deviation.as_slice(), HealthStatus::Degraded)
+                });
+            results.push(Self::resource_result("Host Shared Memory", evaluation, start_time));
+        }
+
+        if audit.nofile_min > 0 {
+            let start_time = std::time::Instant::now();
+            let evaluation = agent_nofile_limits(&config.proc_root).map(|(pid, soft, hard)| {
+                let deviation = audit_nofile(soft, hard, audit.nofile_min);
+                let summary = format!("agent (PID {}): soft {}, hard {}", pid, soft, hard);
+                deviations_evaluation(summary, deviation.as_slice(), HealthStatus::Degraded)
+            });
+            results.push(Self::resource_result("Host Open Files", evaluation, start_time));
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+    }
+
+    #[test]
+    fn parses_expectations() {
+        assert_eq!(Expectation::parse(">= 262144").unwrap(), Expectation::AtLeast(262144));
+        assert_eq!(Expectation::parse(">=10").unwrap(), Expectation::AtLeast(10));
+        assert_eq!(Expectation::parse("32768   60999").unwrap(), Expectation::Exact("32768 60999".to_string()));
+        assert!(Expectation::parse(">= lots").is_err());
+
+        assert!(Expectation::AtLeast(1024).is_met("4096\n"));
+        assert!(!Expectation::AtLeast(1024).is_met("128"));
+        assert!(!Expectation::AtLeast(1024).is_met("unlimited"));
+        assert!(Expectation::Exact("32768 60999".to_string()).is_met("32768\t60999\n"));
+    }
+
+    #[test]
+    fn config_overrides_the_built_in_profile() {
+        let overrides = BTreeMap::from([
+            ("vm.max_map_count".to_string(), ">= 65530".to_string()),
+            ("net.ipv4.ip_forward".to_string(), "".to_string()),
+            ("net.ipv4.ip_local_port_range".to_string(), "32768 60999".to_string()),
+        ]);
+        let profile = sysctl_profile(&overrides);
+        assert_eq!(profile["vm.max_map_count"], ">= 65530");
+        assert_eq!(profile["net.core.somaxconn"], ">= 1024");
+        assert!(!profile.contains_key("net.ipv4.ip_forward"));
+        assert_eq!(profile.len(), 4);
+    }
+
+    #[test]
+    fn sysctl_deviations_have_current_expected_and_fix() {
+        let mut profile = sysctl_profile(&BTreeMap::new());
+        profile.insert("kernel.does_not_exist".to_string(), "1".to_string());
+        let (values, deviations) = audit_sysctls(&fixture("proc"), &profile);
+
+        assert_eq!(values, ["net.core.somaxconn=4096", "net.ipv4.ip_forward=1"]);
+        let deviations: Vec<String> = deviations.iter().map(Deviation::to_string).collect();
+        assert_eq!(
+            deviations,
+            [
+                "fs.inotify.max_user_watches is 8192, expected >= 524288 (fix: sysctl -w fs.inotify.max_user_watches=524288 and persist it in /etc/sysctl.d/)",
+                "kernel.does_not_exist is missing, expected 1 (fix: sysctl -w kernel.does_not_exist=1 and persist it in /etc/sysctl.d/)",
+                "vm.max_map_count is 65530, expected >= 262144 (fix: sysctl -w vm.max_map_count=262144 and persist it in /etc/sysctl.d/)",
+            ]
+        );
+    }
+
+    #[test]
+    fn modules_can_be_loaded_or_built_in() {
+        let modules: Vec<String> = ["overlay", "br_netfilter", "nf-conntrack", "ip_vs"].map(str::to_string).to_vec();
+        let deviations = audit_modules(&fixture("proc"), &fixture("host-sysfs/v2"), &modules);
+        assert_eq!(deviations.len(), 1);
+        assert_eq!(
+            deviations[0].to_string(),
+            "module ip_vs is not loaded, expected loaded (fix: modprobe ip_vs and add it to /etc/modules-load.d/ip_vs.conf)"
+        );
+
+        // Without /proc/modules only built-in modules count
+        let deviations = audit_modules(Path::new("/nonexistent"), &fixture("host-sysfs/v2"), &modules);
+        assert_eq!(deviations.len(), 3);
+
+        let builtin = parse_builtin_modules("kernel/fs/overlayfs/overlay.ko\nkernel/net/bridge/br_netfilter.ko\n");
+        assert_eq!(builtin, ["overlay", "br_netfilter"]);
+    }
+
+    #[test]
+    fn cgroup_v2_controllers() {
+        let controllers = HostAuditConfig::default().cgroup_controllers;
+        let (version, deviations) = audit_cgroups(&fixture("host-sysfs/v2"), &controllers).unwrap();
+        assert_eq!(version, CgroupVersion::V2);
+        assert_eq!(deviations.len(), 1);
+        assert_eq!(deviations[0].setting, "cgroup controller cpuset");
+        assert_eq!(deviations[0].current, "not in cgroup.controllers");
+    }
+
+    #[test]
+    fn cgroup_v1_mounted_controllers() {
+        let mut controllers = HostAuditConfig::default().cgroup_controllers;
+        let (version, deviations) = audit_cgroups(&fixture("host-sysfs/v1"), &controllers).unwrap();
+        assert_eq!(version, CgroupVersion::V1);
+        assert!(deviations.is_empty(), "io is checked as blkio: {:?}", deviations);
+
+        controllers.push("hugetlb".to_string());
+        let (_, deviations) = audit_cgroups(&fixture("host-sysfs/v1"), &controllers).unwrap();
+        assert_eq!(deviations.len(), 1);
+        assert!(deviations[0].expected.ends_with("host-sysfs/v1/fs/cgroup/hugetlb"));
+
+        assert!(audit_cgroups(Path::new("/nonexistent"), &controllers).is_err());
+    }
+
+    #[test]
+    fn shm_and_nofile_minimums() {
+        let shm = audit_shm(Path::new("/dev/shm"), 64 * 1024 * 1024, 1024).unwrap();
+        assert_eq!(
+            shm.to_string(),
+            "/dev/shm is 64 MiB, expected >= 1024 MiB (fix: mount -o remount,size=1024M /dev/shm and set the size in /etc/fstab)"
+        );
+        assert_eq!(audit_shm(Path::new("/dev/shm"), 64 * 1024 * 1024 * 1024, 1024), None);
+
+        let nofile = audit_nofile(1024, 4096, 65536).unwrap();
+        assert_eq!(nofile.current, "1024 (hard 4096)");
+        assert_eq!(audit_nofile(1048576, 1048576, 65536), None);
+    }
+
+    #[test]
+    fn agent_open_file_limits() {
+        assert_eq!(find_agent_pid(&fixture("proc")), Some(7002));
+        assert_eq!(find_agent_pid(&fixture("host-sysfs")), None);
+        assert_eq!(agent_nofile_limits(&fixture("proc")).unwrap(), (7002, 1048576, 1048576));
+
+        assert_eq!(parse_nofile_limits("Max open files            unlimited            unlimited            files"), Some((u64::MAX, u64::MAX)));
+        assert_eq!(parse_nofile_limits("Max processes             2061484              2061484              processes"), None);
+    }
+
+    #[test]
+    fn audit_results_per_category() {
+        let config = SystemConfig {
+            proc_root: fixture("proc"),
+            host_audit: HostAuditConfig {
+                sysfs_root: fixture("host-sysfs/v2"),
+                shm_path: PathBuf::from("/"),
+                shm_min_mib: 1,
+                nofile_min: 1,
+                ..HostAuditConfig::default()
+            },
+            ..SystemConfig::default()
+        };
+        let results = super::super::SystemChecker::audit_host(&config);
+        let summary: Vec<(&str, &HealthStatus)> = results
+            .iter()
+            .map(|result| (result.service_name.as_str(), &result.status))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Host Sysctls", &HealthStatus::Degraded),
+                ("Host Kernel Modules", &HealthStatus::Healthy),
+                ("Host cgroups", &HealthStatus::Unhealthy),
+                ("Host Shared Memory", &HealthStatus::Healthy),
+                ("Host Open Files", &HealthStatus::Healthy),
+            ]
+        );
+        assert!(results[0].details.starts_with("net.core.somaxconn=4096, net.ipv4.ip_forward=1 - Issues: fs.inotify"));
+        assert_eq!(results[4].details, "agent (PID 7002): soft 1048576, hard 1048576");
+
+        let disabled = SystemConfig {
+            host_audit: HostAuditConfig { enabled: false, ..HostAuditConfig::default() },
+            ..SystemConfig::default()
+        };
+        assert!(super::super::SystemChecker::audit_host(&disabled).is_empty());
+    }
+}
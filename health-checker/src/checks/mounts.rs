@@ -0,0 +1,499 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::disks::{self, MountInfo};
+use crate::config::{NetworkMountsConfig, SystemConfig, Threshold};
+use crate::severity::{escalate, severity};
+use crate::{HealthCheckResult, HealthStatus};
+
+/// Filesystem types treated as network filesystems during discovery
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "ceph",
+    "fuse.ceph-fuse",
+    "lustre",
+    "cifs",
+    "smb3",
+    "glusterfs",
+    "fuse.glusterfs",
+    "beegfs",
+    "gpfs",
+    "wekafs",
+];
+
+/// Mount points and disk paths being probed, with when the probe started. An entry outlives a
+/// timed-out probe until its worker returns, and the path is not probed again until then, so a
+/// hung mount holds at most one thread.
+static BLOCKED_PROBES: Mutex<BTreeMap<PathBuf, Instant>> = Mutex::new(BTreeMap::new());
+
+pub(super) fn blocked_probes() -> MutexGuard<'static, BTreeMap<PathBuf, Instant>> {
+    BLOCKED_PROBES.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ProbeStep {
+    Stat,
+    Readdir,
+    Statvfs,
+    Write,
+    Fsync,
+    Read,
+    Remove,
+}
+
+impl ProbeStep {
+    const ALL: [ProbeStep; 7] = [
+        ProbeStep::Stat,
+        ProbeStep::Readdir,
+        ProbeStep::Statvfs,
+        ProbeStep::Write,
+        ProbeStep::Fsync,
+        ProbeStep::Read,
+        ProbeStep::Remove,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            ProbeStep::Stat => "stat",
+            ProbeStep::Readdir => "readdir",
+            ProbeStep::Statvfs => "statvfs",
+            ProbeStep::Write => "write",
+            ProbeStep::Fsync => "fsync",
+            ProbeStep::Read => "read",
+            ProbeStep::Remove => "remove",
+        }
+    }
+
+    fn is_write(self) -> bool {
+        matches!(self, ProbeStep::Write | ProbeStep::Fsync | ProbeStep::Read | ProbeStep::Remove)
+    }
+}
+
+/// A network filesystem to probe
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkMount {
+    pub mount_point: PathBuf,
+    /// Filesystem type and source from mountinfo; unknown for configured paths that are not mounted
+    pub fs_type: Option<String>,
+    pub source: Option<String>,
+    pub read_only: bool,
+}
+
+impl NetworkMount {
+    fn from_mountinfo(mount: &MountInfo) -> Self {
+        Self {
+            mount_point: mount.mount_point.clone(),
+            fs_type: Some(mount.fs_type.clone()),
+            source: Some(mount.source.clone()),
+            read_only: mount.options.iter().any(|option| option == "ro"),
+        }
+    }
+}
+
+/// Discovered network filesystems followed by configured mount points that were not discovered
+pub fn network_mounts(mounts: &[MountInfo], config: &NetworkMountsConfig) -> Vec<NetworkMount> {
+    let mut network_mounts: Vec<NetworkMount> = Vec::new();
+    if config.discover {
+        for mount in mounts.iter().filter(|mount| NETWORK_FS_TYPES.contains(&mount.fs_type.as_str())) {
+            // Bind mounts of one export show up once per mount point; a remount replaces the entry
+            network_mounts.retain(|known| known.mount_point != mount.mount_point);
+            network_mounts.push(NetworkMount::from_mountinfo(mount));
+        }
+    }
+    for path in &config.paths {
+        if network_mounts.iter().any(|known| &known.mount_point == path) {
+            continue;
+        }
+        let mount = mounts.iter().rev().find(|mount| &mount.mount_point == path);
+        network_mounts.push(match mount {
+            Some(mount) => NetworkMount::from_mountinfo(mount),
+            None => NetworkMount { mount_point: path.clone(), fs_type: None, source: None, read_only: false },
+        });
+    }
+    network_mounts
+}
+
+/// Probe file of this host, so nodes sharing an export do not remove each other's file
+pub fn probe_file_path(mount_point: &Path, probe_file: &str) -> PathBuf {
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+    let hostname = hostname.trim();
+    if hostname.is_empty() {
+        mount_point.join(probe_file)
+    } else {
+        mount_point.join(format!("{}.{}", probe_file, hostname))
+    }
+}
+
+type ProbeTimings = Vec<(ProbeStep, Duration)>;
+
+/// Walk the probe steps, recording the current step in `progress` so a hang can be attributed
+fn probe_mount(
+    mount_point: &Path,
+    probe_path: Option<&Path>,
+    progress: &AtomicUsize,
+) -> Result<ProbeTimings, (ProbeStep, anyhow::Error)> {
+    let mut timings = Vec::new();
+    let mut file = None;
+    let content = format!("health-checker probe {}\n", Utc::now().to_rfc3339());
+
+    for (index, step) in ProbeStep::ALL.into_iter().enumerate() {
+        if step.is_write() && probe_path.is_none() {
+            break;
+        }
+        progress.store(index, Ordering::SeqCst);
+        let start_time = Instant::now();
+        let outcome: Result<()> = match (step, probe_path) {
+            (ProbeStep::Stat, _) => std::fs::metadata(mount_point).map(|_| ()).map_err(Into::into),
+            (ProbeStep::Readdir, _) => std::fs::read_dir(mount_point)
+                .and_then(|mut entries| entries.next().transpose())
+                .map(|_| ())
+                .map_err(Into::into),
+            (ProbeStep::Statvfs, _) => disks::disk_usage(mount_point).map(|_| ()),
+            (ProbeStep::Write, Some(path)) => std::fs::File::create(path)
+                .and_then(|mut created| {
+                    created.write_all(content.as_bytes())?;
+                    file = Some(created);
+                    Ok(())
+                })
+                .map_err(Into::into),
+            (ProbeStep::Fsync, _) => match &file {
+                Some(file) => file.sync_all().map_err(Into::into),
+                None => Ok(()),
+            },
+            // Read through a new handle, so the data comes back from the server where the client allows
+            (ProbeStep::Read, Some(path)) => {
+                let mut read_back = String::new();
+                std::fs::File::open(path)
+                    .and_then(|mut opened| opened.read_to_string(&mut read_back))
+                    .map_err(anyhow::Error::from)
+                    .and_then(|_| match read_back == content {
+                        true => Ok(()),
+                        false => Err(anyhow!("probe file content differs from what was written")),
+                    })
+            }
+            (ProbeStep::Remove, Some(path)) => std::fs::remove_file(path).map_err(Into::into),
+            (_, None) => Ok(()),
+        };
+        if let Err(e) = outcome {
+            if step.is_write() && step != ProbeStep::Remove {
+                if let Some(path) = probe_path {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+            return Err((step, e));
+        }
+        timings.push((step, start_time.elapsed()));
+    }
+    Ok(timings)
+}
+
+fn describe_mount(mount: &NetworkMount) -> String {
+    match (&mount.source, &mount.fs_type) {
+        (Some(source), Some(fs_type)) => format!("{} ({})", source, fs_type),
+        _ => format!("{} (not in mountinfo)", mount.mount_point.display()),
+    }
+}
+
+fn evaluate_probe(mount: &NetworkMount, timings: &[(ProbeStep, Duration)], latency_ms: &Threshold) -> (HealthStatus, String) {
+    let mut status = HealthStatus::Healthy;
+    let mut issues = Vec::new();
+
+    let total_ms = timings.iter().map(|(_, duration)| duration.as_secs_f64() * 1000.0).sum::<f64>();
+    if let Some(level) = severity(total_ms, latency_ms) {
+        let (slowest_step, slowest) = timings
+            .iter()
+            .max_by_key(|(_, duration)| *duration)
+            .map(|(step, duration)| (step.label(), duration.as_millis()))
+            .unwrap_or_default();
+        issues.push(format!("Slow: {:.0}ms (slowest: {} {}ms)", total_ms, slowest_step, slowest));
+        escalate(&mut status, level);
+    }
+
+    let steps: Vec<String> = timings
+        .iter()
+        .map(|(step, duration)| format!("{} {}ms", step.label(), duration.as_millis()))
+        .collect();
+    let mut details = format!("{}: {}, total {:.0}ms", describe_mount(mount), steps.join(", "), total_ms);
+    if !timings.iter().any(|(step, _)| step.is_write()) {
+        details.push_str(if mount.read_only { ", read-only, write probe skipped" } else { ", write probe disabled" });
+    }
+    if !issues.is_empty() {
+        details.push_str(&format!(" - Issues: {}", issues.join(", ")));
+    }
+    (status, details)
+}
+
+/// Stale handles and I/O errors mean the mount is broken; a write probe the checker may not do only degrades
+fn evaluate_failure(mount: &NetworkMount, step: ProbeStep, error: &anyhow::Error) -> (HealthStatus, String) {
+    let os_error = error.downcast_ref::<std::io::Error>().and_then(std::io::Error::raw_os_error);
+    let (status, issue) = match os_error {
+        Some(libc::ESTALE) => (HealthStatus::Unhealthy, format!("Stale file handle in {}", step.label())),
+        Some(libc::EIO) => (HealthStatus::Unhealthy, format!("I/O error in {}", step.label())),
+        Some(libc::EACCES | libc::EPERM) if step.is_write() => {
+            (HealthStatus::Degraded, format!("Write probe not permitted: {}", error))
+        }
+        _ => (HealthStatus::Unhealthy, format!("{} failed: {}", step.label(), error)),
+    };
+    (status, format!("{} - Issues: {}", describe_mount(mount), issue))
+}
+
+impl super::SystemChecker {
+    /// Probe each network filesystem with a bounded-time worker, one result per mount point
+    pub async fn check_network_mounts(config: &SystemConfig) -> Vec<HealthCheckResult> {
+        let network_config = &config.network_mounts;
+        let mountinfo = disks::read_mountinfo(&config.proc_root);
+        let mounts = network_mounts(&mountinfo, network_config);
+        let timeout = Duration::from_secs(network_config.timeout_secs);
+        let mountinfo_known = !mountinfo.is_empty();
+
+        let probes = mounts.iter().map(|mount| async move {
+            let start_time = Instant::now();
+            // Probing an unmounted mount point would only exercise the disk underneath it
+            let (status, details) = if mount.fs_type.is_none() && mountinfo_known {
+                (HealthStatus::Unhealthy, format!("{} - Issues: Not mounted", describe_mount(mount)))
+            } else {
+                Self::probe_network_mount(mount, network_config, timeout).await
+            };
+            HealthCheckResult {
+                service_name: format!("Network Mount ({})", mount.mount_point.display()),
+                status,
+                response_time_ms: start_time.elapsed().as_millis() as u64,
+                details,
+                timestamp: Utc::now(),
+                error_message: None,
+            }
+        });
+        futures::future::join_all(probes).await
+    }
+
+    async fn probe_network_mount(
+        mount: &NetworkMount,
+        config: &NetworkMountsConfig,
+        timeout: Duration,
+    ) -> (HealthStatus, String) {
+        {
+            let mut blocked = blocked_probes();
+            if let Some(since) = blocked.get(&mount.mount_point) {
+                return (
+                    HealthStatus::Unhealthy,
+                    format!(
+                        "{} - Issues: Previous probe still blocked after {}s, not probing again",
+                        describe_mount(mount),
+                        since.elapsed().as_secs()
+                    ),
+                );
+            }
+            blocked.insert(mount.mount_point.clone(), Instant::now());
+        }
+
+        let progress = Arc::new(AtomicUsize::new(0));
+        let mount_point = mount.mount_point.clone();
+        let probe_path = (config.write_probe && !mount.read_only)
+            .then(|| probe_file_path(&mount.mount_point, &config.probe_file));
+        let worker_progress = Arc::clone(&progress);
+        let probe = move || {
+            let outcome = probe_mount(&mount_point, probe_path.as_deref(), &worker_progress);
+            blocked_probes().remove(&mount_point);
+            outcome
+        };
+
+        match super::run_in_worker(timeout, probe).await {
+            Ok(Some(Ok(timings))) => evaluate_probe(mount, &timings, &config.latency_ms),
+            Ok(Some(Err((step, e)))) => evaluate_failure(mount, step, &e),
+            Ok(None) => {
+                let step = ProbeStep::ALL[progress.load(Ordering::SeqCst)];
+                (
+                    HealthStatus::Unhealthy,
+                    format!(
+                        "{} - Issues: Hung in {} for more than {}s",
+                        describe_mount(mount),
+                        step.label(),
+                        timeout.as_secs_f64()
+                    ),
+                )
+            }
+            Err(e) => {
+                blocked_probes().remove(&mount.mount_point);
+                (HealthStatus::Unknown, format!("{:#}", e.context("Cannot probe mount")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = include_str!("../../tests/fixtures/proc/self/mountinfo");
+
+    /// Fresh directory under the system temp dir standing in for a mount point
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("health-checker-mounts-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn local_mount(mount_point: &Path) -> NetworkMount {
+        NetworkMount {
+            mount_point: mount_point.to_path_buf(),
+            fs_type: Some("nfs4".to_string()),
+            source: Some("10.0.0.5:/export/vfroot".to_string()),
+            read_only: false,
+        }
+    }
+
+    #[test]
+    fn discovers_network_filesystems() {
+        let mounts = disks::parse_mountinfo(MOUNTINFO);
+        let config = NetworkMountsConfig {
+            paths: vec![PathBuf::from("/vfroot/local"), PathBuf::from("/mnt/scratch disk"), PathBuf::from("/lustre/vfroot")],
+            ..NetworkMountsConfig::default()
+        };
+        let network = network_mounts(&mounts, &config);
+        let summary: Vec<(&Path, Option<&str>)> = network
+            .iter()
+            .map(|mount| (mount.mount_point.as_path(), mount.fs_type.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Path::new("/vfroot/local"), Some("nfs4")),
+                (Path::new("/vfroot/ceph"), Some("ceph")),
+                (Path::new("/mnt/scratch disk"), Some("ext4")),
+                (Path::new("/lustre/vfroot"), None),
+            ]
+        );
+        assert_eq!(network[1].source.as_deref(), Some("10.0.0.6:6789,10.0.0.7:6789:/volumes/vfroot"));
+
+        let configured_only = NetworkMountsConfig { discover: false, ..config };
+        assert_eq!(network_mounts(&mounts, &configured_only).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn configured_mount_that_is_not_mounted() {
+        let config = SystemConfig {
+            proc_root: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc"),
+            network_mounts: NetworkMountsConfig {
+                discover: false,
+                paths: vec![PathBuf::from("/lustre/vfroot")],
+                ..NetworkMountsConfig::default()
+            },
+            ..SystemConfig::default()
+        };
+        let results = super::super::SystemChecker::check_network_mounts(&config).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].service_name, "Network Mount (/lustre/vfroot)");
+        assert_eq!(results[0].status, HealthStatus::Unhealthy);
+        assert_eq!(results[0].details, "/lustre/vfroot (not in mountinfo) - Issues: Not mounted");
+    }
+
+    #[tokio::test]
+    async fn probes_writable_mount() {
+        let dir = scratch_dir("writable");
+        let config = NetworkMountsConfig::default();
+        let (status, details) =
+            super::super::SystemChecker::probe_network_mount(&local_mount(&dir), &config, Duration::from_secs(10)).await;
+        assert_eq!(status, HealthStatus::Healthy, "{}", details);
+        assert!(details.starts_with("10.0.0.5:/export/vfroot (nfs4): stat "));
+        for step in ["readdir", "statvfs", "write", "fsync", "read", "remove"] {
+            assert!(details.contains(&format!(", {} ", step)), "{}", details);
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0, "probe file is removed");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn read_only_mount_is_not_written() {
+        let dir = scratch_dir("read-only");
+        let mount = NetworkMount { read_only: true, ..local_mount(&dir) };
+        let (status, details) = super::super::SystemChecker::probe_network_mount(
+            &mount,
+            &NetworkMountsConfig::default(),
+            Duration::from_secs(10),
+        )
+        .await;
+        assert_eq!(status, HealthStatus::Healthy);
+        assert!(details.ends_with(", read-only, write probe skipped"), "{}", details);
+        assert!(!details.contains("fsync"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn hung_probe_is_reported_and_not_repeated() {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        // Opening a FIFO for writing blocks until a reader shows up, like a write to a dead server
+        let dir = scratch_dir("hung");
+        let config = NetworkMountsConfig::default();
+        let fifo = probe_file_path(&dir, &config.probe_file);
+        let c_path = std::ffi::CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+        let mount = local_mount(&dir);
+        let timeout = Duration::from_millis(200);
+        let (status, details) = super::super::SystemChecker::probe_network_mount(&mount, &config, timeout).await;
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Hung in write for more than 0.2s"), "{}", details);
+
+        let (status, details) = super::super::SystemChecker::probe_network_mount(&mount, &config, timeout).await;
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.contains("Previous probe still blocked after "), "{}", details);
+
+        // Release the worker; it removes the FIFO and its blocked entry on the way out
+        let reader = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&fifo)
+            .unwrap();
+        for _ in 0..100 {
+            if !blocked_probes().contains_key(&dir) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        drop(reader);
+        assert!(!blocked_probes().contains_key(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stale_handles_and_latency() {
+        let mount = local_mount(Path::new("/vfroot/local"));
+        let stale = anyhow::Error::from(std::io::Error::from_raw_os_error(libc::ESTALE));
+        assert_eq!(
+            evaluate_failure(&mount, ProbeStep::Stat, &stale),
+            (
+                HealthStatus::Unhealthy,
+                "10.0.0.5:/export/vfroot (nfs4) - Issues: Stale file handle in stat".to_string()
+            )
+        );
+        let denied = anyhow::Error::from(std::io::Error::from_raw_os_error(libc::EACCES));
+        assert_eq!(evaluate_failure(&mount, ProbeStep::Write, &denied).0, HealthStatus::Degraded);
+        assert_eq!(evaluate_failure(&mount, ProbeStep::Readdir, &denied).0, HealthStatus::Unhealthy);
+
+        let timings = [
+            (ProbeStep::Stat, Duration::from_millis(2)),
+            (ProbeStep::Readdir, Duration::from_millis(5)),
+            (ProbeStep::Statvfs, Duration::from_millis(3)),
+            (ProbeStep::Write, Duration::from_millis(40)),
+            (ProbeStep::Fsync, Duration::from_millis(1450)),
+            (ProbeStep::Read, Duration::from_millis(10)),
+            (ProbeStep::Remove, Duration::from_millis(20)),
+        ];
+        let (status, details) = evaluate_probe(&mount, &timings, &NetworkMountsConfig::default().latency_ms);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert_eq!(
+            details,
+            "10.0.0.5:/export/vfroot (nfs4): stat 2ms, readdir 5ms, statvfs 3ms, write 40ms, fsync 1450ms, read 10ms, remove 20ms, total 1530ms - Issues: Slow: 1530ms (slowest: fsync 1450ms)"
+        );
+    }
+}
@@ -0,0 +1,514 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use crate::config::{ServicePortConfig, SystemConfig};
+use crate::docker::LabeledContainer;
+use crate::gpu::parse_container_id;
+use crate::severity::escalate;
+use crate::{HealthCheckResult, HealthStatus};
+
+/// `st` column value of listening sockets in `/proc/net/tcp`
+const TCP_LISTEN: &str = "0A";
+
+/// Process Docker forwards published ports through unless the userland proxy is disabled
+const DOCKER_PROXY: &str = "docker-proxy";
+
+/// A listening TCP socket from `/proc/net/tcp` or `/proc/net/tcp6`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listener {
+    pub address: IpAddr,
+    pub port: u16,
+    pub inode: u64,
+}
+
+/// Listening sockets of a `/proc/net/tcp` or `/proc/net/tcp6` table; other states are skipped
+pub fn parse_proc_net_tcp(content: &str) -> Vec<Listener> {
+    content.lines().skip(1).filter_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(3) != Some(&TCP_LISTEN) {
+            return None;
+        }
+        let (address, port) = fields.get(1)?.split_once(':')?;
+        Some(Listener {
+            address: parse_hex_address(address)?,
+            port: u16::from_str_radix(port, 16).ok()?,
+            inode: fields.get(9)?.parse().ok()?,
+        })
+    }).collect()
+}
+
+/// The kernel prints addresses as 32-bit words in host byte order
+fn parse_hex_address(hex: &str) -> Option<IpAddr> {
+    let bytes = (0..hex.len()).step_by(8)
+        .map(|start| u32::from_str_radix(hex.get(start..start + 8)?, 16).ok())
+        .collect::<Option<Vec<u32>>>()?
+        .into_iter()
+        .flat_map(u32::to_ne_bytes)
+        .collect::<Vec<u8>>();
+    match bytes.len() {
+        4 => Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?))),
+        16 => Some(IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?))),
+        _ => None,
+    }
+}
+
+/// Listening sockets of the checker's network namespace; `tcp6` is optional for hosts without IPv6
+pub fn listening_sockets(proc_root: &Path) -> Result<Vec<Listener>> {
+    let tcp = std::fs::read_to_string(proc_root.join("net/tcp"))
+        .with_context(|| format!("Cannot read {}", proc_root.join("net/tcp").display()))?;
+    let mut listeners = parse_proc_net_tcp(&tcp);
+    if let Ok(tcp6) = std::fs::read_to_string(proc_root.join("net/tcp6")) {
+        listeners.extend(parse_proc_net_tcp(&tcp6));
+    }
+    Ok(listeners)
+}
+
+/// Lowest PID holding each of `inodes`, from the `socket:[inode]` links in `<proc_root>/<pid>/fd`.
+/// Processes of other users are only visible to root.
+pub fn socket_owners(proc_root: &Path, inodes: &HashSet<u64>) -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(proc_root) else {
+        return owners;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let inode = target.to_str()
+                .and_then(|target| target.strip_prefix("socket:["))
+                .and_then(|target| target.strip_suffix(']'))
+                .and_then(|inode| inode.parse::<u64>().ok());
+            if let Some(inode) = inode.filter(|inode| inodes.contains(inode)) {
+                owners.entry(inode)
+                    .and_modify(|owner: &mut u32| *owner = (*owner).min(pid))
+                    .or_insert(pid);
+            }
+        }
+    }
+    owners
+}
+
+/// A process holding a listening socket
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortProcess {
+    pub pid: u32,
+    pub name: String,
+    pub cmdline: String,
+    pub container_id: Option<String>,
+}
+
+impl PortProcess {
+    /// `None` when the process exited since its socket was found
+    pub fn read(proc_root: &Path, pid: u32) -> Option<Self> {
+        let process_dir = proc_root.join(pid.to_string());
+        let name = std::fs::read_to_string(process_dir.join("comm")).ok()?.trim().to_string();
+        let cmdline = std::fs::read(process_dir.join("cmdline"))
+            .map(|cmdline| {
+                String::from_utf8_lossy(&cmdline)
+                    .split('\0')
+                    .filter(|arg| !arg.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        let container_id = std::fs::read_to_string(process_dir.join("cgroup"))
+            .ok()
+            .and_then(|cgroup| parse_container_id(&cgroup));
+        Some(Self { pid, name, cmdline, container_id })
+    }
+
+    fn is_docker_proxy(&self) -> bool {
+        self.name == DOCKER_PROXY
+    }
+}
+
+/// Who holds a port: a host process, a container, or a container behind docker-proxy
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PortOwner {
+    pub process: Option<PortProcess>,
+    /// Container name, or its short ID when Docker does not know it
+    pub container: Option<String>,
+    pub image: Option<String>,
+}
+
+impl PortOwner {
+    /// Resolve the container of `process` from its cgroup, or for docker-proxy and sockets without
+    /// a visible process, the running container that publishes `port`
+    pub fn resolve(port: u16, process: Option<PortProcess>, containers: Option<&[LabeledContainer]>) -> Self {
+        let containers = containers.unwrap_or_default();
+        let container_id = process.as_ref().and_then(|process| process.container_id.clone());
+        let container = match container_id {
+            Some(container_id) => match containers.iter().find(|container| container.id == container_id) {
+                Some(container) => Some(container),
+                None => {
+                    return Self {
+                        process,
+                        container: Some(container_id.chars().take(12).collect()),
+                        image: None,
+                    };
+                }
+            },
+            None if process.as_ref().is_none_or(PortProcess::is_docker_proxy) => publishing_container(containers, port),
+            None => None,
+        };
+        Self {
+            process,
+            container: container.map(|container| container.name.clone()),
+            image: container.map(|container| container.image.clone()).filter(|image| !image.is_empty()),
+        }
+    }
+
+    /// Whether there is nothing to compare with the expected owners
+    fn is_unknown(&self) -> bool {
+        match &self.process {
+            Some(process) => process.is_docker_proxy() && self.container.is_none(),
+            None => self.container.is_none(),
+        }
+    }
+
+    /// Whether a process name, command line, container name or image contains one of `owners`
+    fn matches(&self, owners: &[String]) -> bool {
+        let process = self.process.as_ref().filter(|process| !process.is_docker_proxy());
+        let candidates: Vec<String> = [
+            process.map(|process| process.name.as_str()),
+            process.map(|process| process.cmdline.as_str()),
+            self.container.as_deref(),
+            self.image.as_deref(),
+        ].into_iter().flatten().map(str::to_lowercase).collect();
+        owners.is_empty() || owners.iter().any(|owner| {
+            let owner = owner.to_lowercase();
+            candidates.iter().any(|candidate| candidate.contains(&owner))
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let container = self.container.as_ref().map(|container| match &self.image {
+            Some(image) => format!("container {} ({})", container, image),
+            None => format!("container {}", container),
+        });
+        match (&self.process, container) {
+            (Some(process), Some(container)) if process.is_docker_proxy() => {
+                format!("{} via {} (PID {})", container, process.name, process.pid)
+            }
+            (Some(process), Some(container)) => format!("{} (PID {}) in {}", process.name, process.pid, container),
+            (Some(process), None) if process.is_docker_proxy() => {
+                format!("{} (PID {}) for an unknown container", process.name, process.pid)
+            }
+            (Some(process), None) => format!("{} (PID {})", process.name, process.pid),
+            (None, Some(container)) => container,
+            (None, None) => "unknown process".to_string(),
+        }
+    }
+}
+
+/// A service port bound to one address
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortBinding {
+    pub address: IpAddr,
+    pub owner: PortOwner,
+}
+
+/// Running container publishing `port` on the host
+fn publishing_container(containers: &[LabeledContainer], port: u16) -> Option<&LabeledContainer> {
+    containers.iter()
+        .filter(|container| container.state == "running")
+        .find(|container| container.published_ports.iter().any(|published| published.host_port == port))
+}
+
+/// Bindings of `port`: its listening sockets with their owners, or the addresses a container
+/// publishes it on through NAT rules when no socket is listening (userland proxy disabled)
+pub fn port_bindings(
+    port: u16,
+    listeners: &[Listener],
+    owners: &HashMap<u64, PortProcess>,
+    containers: Option<&[LabeledContainer]>,
+) -> Vec<PortBinding> {
+    let bindings: Vec<PortBinding> = listeners.iter()
+        .filter(|listener| listener.port == port)
+        .map(|listener| PortBinding {
+            address: listener.address,
+            owner: PortOwner::resolve(port, owners.get(&listener.inode).cloned(), containers),
+        })
+        .collect();
+    if !bindings.is_empty() {
+        return bindings;
+    }
+
+    let Some(container) = publishing_container(containers.unwrap_or_default(), port) else {
+        return Vec::new();
+    };
+    container.published_ports.iter()
+        .filter(|published| published.host_port == port)
+        .filter_map(|published| published.host_ip.parse().ok())
+        .map(|address| PortBinding {
+            address,
+            owner: PortOwner::resolve(port, None, containers),
+        })
+        .collect()
+}
+
+fn is_loopback(address: &IpAddr) -> bool {
+    address.to_canonical().is_loopback()
+}
+
+/// Compare the owners and addresses of a port with its config
+pub fn evaluate_port(port: &ServicePortConfig, bindings: &[PortBinding]) -> (HealthStatus, String) {
+    if bindings.is_empty() {
+        return (HealthStatus::Unhealthy, format!("Nothing listening on port {}", port.port));
+    }
+
+    let owners: BTreeSet<String> = bindings.iter().map(|binding| binding.owner.describe()).collect();
+    let addresses: BTreeSet<String> = bindings.iter().map(|binding| binding.address.to_string()).collect();
+    let summary = format!(
+        "{} on {}",
+        owners.into_iter().collect::<Vec<_>>().join(", "),
+        addresses.into_iter().collect::<Vec<_>>().join(", "),
+    );
+
+    let mut status = HealthStatus::Healthy;
+    let mut problems = Vec::new();
+    if bindings.iter().any(|binding| !binding.owner.is_unknown() && !binding.owner.matches(&port.owners)) {
+        escalate(&mut status, HealthStatus::Unhealthy);
+        problems.push(format!("Wrong program, expected {}", port.owners.join(" or ")));
+    }
+    let exposed: Vec<String> = bindings.iter()
+        .filter(|binding| !is_loopback(&binding.address))
+        .map(|binding| binding.address.to_string())
+        .collect();
+    if port.loopback_only && !exposed.is_empty() {
+        escalate(&mut status, HealthStatus::Degraded);
+        problems.push(format!("Bound to {}, expected loopback only", exposed.join(", ")));
+    }
+    if status == HealthStatus::Healthy && !port.owners.is_empty() && bindings.iter().any(|binding| binding.owner.is_unknown()) {
+        status = HealthStatus::Unknown;
+        problems.push("Owner not visible (processes of other users are only visible to root)".to_string());
+    }
+
+    match problems.is_empty() {
+        true => (status, summary),
+        false => (status, format!("{}: {}", problems.join("; "), summary)),
+    }
+}
+
+impl super::SystemChecker {
+    /// Identify the process or container holding each configured service port through procfs,
+    /// with published ports from the local Docker engine when it can be asked
+    pub fn check_port_usage(config: &SystemConfig, containers: Option<&[LabeledContainer]>) -> Vec<HealthCheckResult> {
+        if config.ports.is_empty() {
+            return Vec::new();
+        }
+        let start_time = std::time::Instant::now();
+        let listeners = match listening_sockets(&config.proc_root) {
+            Ok(listeners) => listeners,
+            Err(e) => return vec![Self::resource_result("Port Usage", Err(e), start_time)],
+        };
+
+        let inodes: HashSet<u64> = listeners.iter()
+            .filter(|listener| listener.inode != 0 && config.ports.iter().any(|port| port.port == listener.port))
+            .map(|listener| listener.inode)
+            .collect();
+        let processes: HashMap<u64, PortProcess> = socket_owners(&config.proc_root, &inodes)
+            .into_iter()
+            .filter_map(|(inode, pid)| Some((inode, PortProcess::read(&config.proc_root, pid)?)))
+            .collect();
+
+        config.ports.iter().map(|port| {
+            let bindings = port_bindings(port.port, &listeners, &processes, containers);
+            let evaluation = evaluate_port(port, &bindings);
+            Self::resource_result(&format!("Port {} ({})", port.port, port.name), Ok(evaluation), start_time)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::PublishedPort;
+    use std::path::PathBuf;
+
+    const DB_CONTAINER: &str = "d4c3b2a1f0e9d8c7b6a5f4e3d2c1b0a9f8e7d6c5b4a3f2e1d0c9b8a7f6e5d4c3";
+    const REDIS_CONTAINER: &str = "5e1d7a3c9b2f4e6a8c0d2f4b6a8e0c2d4f6b8a0c2e4d6f8b0a2c4e6d8f0b2a4c";
+
+    fn proc_root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc")
+    }
+
+    fn container(id: &str, name: &str, image: &str, published_ports: Vec<PublishedPort>) -> LabeledContainer {
+        LabeledContainer {
+            id: id.to_string(),
+            name: name.to_string(),
+            state: "running".to_string(),
+            labels: HashMap::new(),
+            gpu_devices: Vec::new(),
+            image: image.to_string(),
+            published_ports,
+        }
+    }
+
+    fn published(host_ip: &str, host_port: u16, container_port: u16) -> PublishedPort {
+        PublishedPort { host_ip: host_ip.to_string(), host_port, container_port }
+    }
+
+    fn halfstack_containers() -> Vec<LabeledContainer> {
+        vec![
+            container(DB_CONTAINER, "backendai-halfstack-db-1", "postgres:15.1-alpine", vec![
+                published("0.0.0.0", 8101, 5432),
+                published("::", 8101, 5432),
+            ]),
+            // Host network, so nothing is published
+            container(REDIS_CONTAINER, "backendai-halfstack-redis-1", "redis:7.2.11-alpine", Vec::new()),
+        ]
+    }
+
+    fn fixture_config() -> SystemConfig {
+        let mut ports = ServicePortConfig::halfstack();
+        for port in &mut ports {
+            port.loopback_only = matches!(port.name.as_str(), "PostgreSQL" | "Redis");
+        }
+        SystemConfig { proc_root: proc_root(), ports, ..SystemConfig::default() }
+    }
+
+    #[test]
+    fn parses_listening_sockets_of_both_tables() {
+        let listeners = listening_sockets(&proc_root()).unwrap();
+        assert_eq!(listeners.len(), 8);
+        assert_eq!(listeners[0], Listener {
+            address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 8101,
+            inode: 41234,
+        });
+        assert_eq!(listeners[1].address, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(listeners[1].port, 8111);
+        // Established connections are not listeners
+        assert!(!listeners.iter().any(|listener| listener.inode == 61000));
+
+        assert_eq!(listeners[6], Listener {
+            address: IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            port: 8101,
+            inode: 41240,
+        });
+        assert_eq!(listeners[7].address, IpAddr::V6(Ipv6Addr::LOCALHOST));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_count_as_loopback() {
+        let address = parse_hex_address("0000000000000000FFFF00000100007F").unwrap();
+        assert_eq!(address.to_string(), "::ffff:127.0.0.1");
+        assert!(is_loopback(&address));
+        assert!(!is_loopback(&parse_hex_address("00000000").unwrap()));
+        assert_eq!(parse_hex_address("0100007"), None);
+    }
+
+    #[test]
+    fn maps_socket_inodes_to_the_lowest_pid() {
+        let owners = socket_owners(&proc_root(), &HashSet::from([41234, 41240, 52000, 53000, 54000]));
+        assert_eq!(owners, HashMap::from([(41234, 2231), (41240, 2238), (52000, 4410), (53000, 6001)]));
+    }
+
+    #[test]
+    fn reads_process_command_line_and_container() {
+        let manager = PortProcess::read(&proc_root(), 6001).unwrap();
+        assert_eq!(manager.name, "python3");
+        assert_eq!(manager.cmdline, "/opt/backend.ai/bin/python -m ai.backend.manager.server");
+        assert_eq!(manager.container_id, None);
+
+        let redis = PortProcess::read(&proc_root(), 3120).unwrap();
+        assert_eq!(redis.container_id.as_deref(), Some(REDIS_CONTAINER));
+        assert_eq!(PortProcess::read(&proc_root(), 999_999), None);
+    }
+
+    #[tokio::test]
+    async fn reports_owners_conflicts_and_exposed_ports() {
+        let containers = halfstack_containers();
+        let results = crate::checks::SystemChecker::check_port_usage(&fixture_config(), Some(&containers));
+        let names: Vec<&str> = results.iter().map(|result| result.service_name.as_str()).collect();
+        assert_eq!(names, [
+            "Port 8081 (Manager API)",
+            "Port 8101 (PostgreSQL)",
+            "Port 8111 (Redis)",
+            "Port 8121 (etcd)",
+            "Port 9090 (Prometheus)",
+            "Port 3000 (Grafana)",
+        ]);
+
+        assert_eq!(results[0].status, HealthStatus::Healthy);
+        assert_eq!(results[0].details, "python3 (PID 6001) on 0.0.0.0");
+
+        assert_eq!(results[1].status, HealthStatus::Degraded);
+        assert_eq!(
+            results[1].details,
+            "Bound to 0.0.0.0, ::, expected loopback only: \
+             container backendai-halfstack-db-1 (postgres:15.1-alpine) via docker-proxy (PID 2231), \
+             container backendai-halfstack-db-1 (postgres:15.1-alpine) via docker-proxy (PID 2238) on 0.0.0.0, ::"
+        );
+
+        assert_eq!(results[2].status, HealthStatus::Healthy);
+        assert_eq!(
+            results[2].details,
+            "redis-server (PID 3120) in container backendai-halfstack-redis-1 (redis:7.2.11-alpine) on 127.0.0.1"
+        );
+
+        assert_eq!(results[3].status, HealthStatus::Unhealthy);
+        assert_eq!(results[3].details, "Wrong program, expected etcd: nginx (PID 4410) on 0.0.0.0");
+
+        assert_eq!(results[4].status, HealthStatus::Unknown);
+        assert!(results[4].details.starts_with("Owner not visible"), "{}", results[4].details);
+        assert!(results[4].details.ends_with("unknown process on 127.0.0.1, ::1"), "{}", results[4].details);
+
+        assert_eq!(results[5].status, HealthStatus::Unhealthy);
+        assert_eq!(results[5].details, "Nothing listening on port 3000");
+    }
+
+    #[test]
+    fn docker_proxy_without_docker_is_unknown() {
+        let results = crate::checks::SystemChecker::check_port_usage(&fixture_config(), None);
+        assert_eq!(results[1].status, HealthStatus::Degraded);
+        assert!(results[1].details.contains("docker-proxy (PID 2231) for an unknown container"));
+
+        let mut config = fixture_config();
+        config.ports[1].loopback_only = false;
+        let results = crate::checks::SystemChecker::check_port_usage(&config, None);
+        assert_eq!(results[1].status, HealthStatus::Unknown);
+
+        // Containers of unknown engines are shown by short ID and matched by process only
+        let redis = PortOwner::resolve(8111, PortProcess::read(&proc_root(), 3120), None);
+        assert_eq!(redis.describe(), "redis-server (PID 3120) in container 5e1d7a3c9b2f");
+        assert!(redis.matches(&["redis".to_string()]));
+    }
+
+    #[test]
+    fn ports_published_through_nat_belong_to_their_container() {
+        let grafana = container(
+            "0f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0",
+            "backendai-halfstack-grafana-1",
+            "grafana/grafana:10.4.2",
+            vec![published("127.0.0.1", 3000, 3000)],
+        );
+        let containers = vec![grafana.clone()];
+        let bindings = port_bindings(3000, &[], &HashMap::new(), Some(&containers));
+        let port = ServicePortConfig { loopback_only: true, ..ServicePortConfig::halfstack().remove(5) };
+        assert_eq!(
+            evaluate_port(&port, &bindings),
+            (HealthStatus::Healthy, "container backendai-halfstack-grafana-1 (grafana/grafana:10.4.2) on 127.0.0.1".to_string())
+        );
+
+        let stopped = vec![LabeledContainer { state: "exited".to_string(), ..grafana }];
+        assert!(port_bindings(3000, &[], &HashMap::new(), Some(&stopped)).is_empty());
+    }
+
+    #[test]
+    fn any_owner_is_accepted_without_expected_owners() {
+        let port = ServicePortConfig { name: "SSH".to_string(), port: 22, ..ServicePortConfig::default() };
+        let bindings = vec![PortBinding { address: IpAddr::V4(Ipv4Addr::UNSPECIFIED), owner: PortOwner::default() }];
+        assert_eq!(evaluate_port(&port, &bindings), (HealthStatus::Healthy, "unknown process on 0.0.0.0".to_string()));
+    }
+}
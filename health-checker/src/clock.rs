@@ -0,0 +1,689 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::info;
+use tokio::process::Command;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::{Threshold, TimeConfig};
+use crate::net::request_failure;
+use crate::severity::{escalate, severity};
+use crate::{Backend, HealthCheckResult, HealthStatus};
+
+/// Seconds from the NTP era start (1900) to the Unix epoch
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+const NTP_PACKET_LEN: usize = 48;
+const NTP_DEFAULT_PORT: u16 = 123;
+/// Leap indicator 0, version 4, mode 3 (client)
+const SNTP_CLIENT_HEADER: u8 = 0x23;
+const NTP_MODE_SERVER: u8 = 4;
+/// Leap indicator of a server whose own clock is not synchronized
+const NTP_LEAP_UNSYNCHRONIZED: u8 = 3;
+
+/// Synchronization state the kernel keeps for NTP daemons, from adjtimex(2)
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelClock {
+    pub synchronized: bool,
+    pub max_error_us: i64,
+    pub est_error_us: i64,
+}
+
+/// Read the kernel clock state without changing it; allowed without CAP_SYS_TIME
+#[cfg(target_os = "linux")]
+pub fn kernel_clock() -> Result<KernelClock> {
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+    if state < 0 {
+        return Err(std::io::Error::last_os_error()).context("adjtimex failed");
+    }
+    Ok(KernelClock {
+        synchronized: state != libc::TIME_ERROR && timex.status & libc::STA_UNSYNC == 0,
+        max_error_us: timex.maxerror as i64,
+        est_error_us: timex.esterror as i64,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn kernel_clock() -> Result<KernelClock> {
+    Err(anyhow!("adjtimex is only available on Linux"))
+}
+
+/// Fields of `chronyc -c tracking`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChronyTracking {
+    pub reference: String,
+    pub stratum: u32,
+    /// Difference between the system clock and NTP time chronyd is slewing away, in seconds
+    pub system_offset: f64,
+    /// `Normal`, `Insert second`, `Delete second` or `Not synchronised`
+    pub leap_status: String,
+}
+
+impl ChronyTracking {
+    pub fn synchronized(&self) -> bool {
+        self.leap_status != "Not synchronised"
+    }
+}
+
+pub fn parse_chrony_tracking(csv: &str) -> Result<ChronyTracking> {
+    let fields: Vec<&str> = csv.trim().split(',').collect();
+    if fields.len() < 14 {
+        return Err(anyhow!("Unexpected chronyc tracking output: {}", csv.trim()));
+    }
+    Ok(ChronyTracking {
+        reference: fields[1].to_string(),
+        stratum: fields[2].parse().context("Invalid stratum")?,
+        system_offset: fields[4].parse().context("Invalid system time offset")?,
+        leap_status: fields[13].to_string(),
+    })
+}
+
+/// State of systemd-timesyncd from `timedatectl show` and `timedatectl show-timesync`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimesyncdState {
+    /// Whether NTP synchronization is enabled (`timedatectl set-ntp`)
+    pub ntp: bool,
+    pub synchronized: bool,
+    pub server: Option<String>,
+}
+
+pub fn parse_timedatectl(output: &str) -> TimesyncdState {
+    let mut state = TimesyncdState::default();
+    for (key, value) in output.lines().filter_map(|line| line.split_once('=')) {
+        match key {
+            "NTP" => state.ntp = value == "yes",
+            "NTPSynchronized" => state.synchronized = value == "yes",
+            "ServerName" if !value.is_empty() => state.server = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    state
+}
+
+/// Daemon keeping the local clock in sync
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncDaemon {
+    Chrony(ChronyTracking),
+    Timesyncd(TimesyncdState),
+    /// Neither chronyd nor an enabled systemd-timesyncd; ntpd and others only show in the kernel state
+    NotFound,
+}
+
+/// Output of a tool, or `None` when it is not installed, its daemon is not running or it does not
+/// answer within `timeout`, e.g. timedatectl waiting on a stuck D-Bus
+async fn tool_output(program: &str, args: &[&str], timeout: Duration) -> Option<String> {
+    let output = Command::new(program).args(args).kill_on_drop(true).output();
+    let output = match tokio::time::timeout(timeout, output).await {
+        Ok(output) => output.ok()?,
+        Err(_) => {
+            info!("{} {} did not finish within {}s", program, args.join(" "), timeout.as_secs());
+            return None;
+        }
+    };
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub async fn sync_daemon(timeout: Duration) -> SyncDaemon {
+    if let Some(tracking) = tool_output("chronyc", &["-c", "tracking"], timeout).await {
+        match parse_chrony_tracking(&tracking) {
+            Ok(tracking) => return SyncDaemon::Chrony(tracking),
+            Err(e) => info!("Ignoring chronyc output: {}", e),
+        }
+    }
+    if let Some(show) = tool_output("timedatectl", &["show"], timeout).await {
+        let timesync = tool_output("timedatectl", &["show-timesync"], timeout).await.unwrap_or_default();
+        let state = parse_timedatectl(&format!("{}{}", show, timesync));
+        if state.ntp {
+            return SyncDaemon::Timesyncd(state);
+        }
+    }
+    SyncDaemon::NotFound
+}
+
+/// `seconds` with a sign, in milliseconds below one second
+fn format_offset(seconds: f64) -> String {
+    if seconds.abs() < 0.01 {
+        format!("{:+.3}ms", seconds * 1000.0)
+    } else if seconds.abs() < 1.0 {
+        format!("{:+.1}ms", seconds * 1000.0)
+    } else {
+        format!("{:+.2}s", seconds)
+    }
+}
+
+/// Combine the daemon and kernel view of the local clock
+pub fn evaluate_sync(kernel: Option<&KernelClock>, daemon: &SyncDaemon, offset_ms: &Threshold) -> (HealthStatus, String) {
+    let mut status = HealthStatus::Healthy;
+    let mut issues = Vec::new();
+
+    let mut details = match daemon {
+        SyncDaemon::Chrony(tracking) if tracking.synchronized() => {
+            let offset = tracking.system_offset.abs() * 1000.0;
+            if let Some(level) = severity(offset, offset_ms) {
+                issues.push(format!("Large offset: {}", format_offset(tracking.system_offset)));
+                escalate(&mut status, level);
+            }
+            format!(
+                "chrony synchronized to {} (stratum {}), offset {}",
+                tracking.reference, tracking.stratum, format_offset(tracking.system_offset),
+            )
+        }
+        SyncDaemon::Chrony(_) => {
+            issues.push("chronyd has no usable source".to_string());
+            escalate(&mut status, HealthStatus::Degraded);
+            "chrony not synchronised".to_string()
+        }
+        SyncDaemon::Timesyncd(state) => {
+            let server = state.server.as_deref().unwrap_or("no server");
+            if !state.synchronized {
+                issues.push(format!("systemd-timesyncd not synchronized ({})", server));
+                escalate(&mut status, HealthStatus::Degraded);
+            }
+            format!("systemd-timesyncd with {}", server)
+        }
+        SyncDaemon::NotFound if kernel.is_some_and(|kernel| kernel.synchronized) => {
+            "synchronized by an NTP daemon".to_string()
+        }
+        SyncDaemon::NotFound => {
+            issues.push("No NTP daemon found, e.g. enable one with timedatectl set-ntp true".to_string());
+            escalate(&mut status, HealthStatus::Degraded);
+            "no NTP daemon".to_string()
+        }
+    };
+
+    if let Some(kernel) = kernel {
+        if kernel.synchronized {
+            let est_error = kernel.est_error_us as f64 / 1000.0;
+            details.push_str(&format!(", kernel estimated error {:.1}ms", est_error));
+            if let Some(level) = severity(est_error, offset_ms) {
+                issues.push(format!("Large estimated error: {:.1}ms", est_error));
+                escalate(&mut status, level);
+            }
+        } else {
+            details.push_str(&format!(", kernel clock unsynchronized (max error {:.1}s)", kernel.max_error_us as f64 / 1_000_000.0));
+            if status == HealthStatus::Healthy {
+                issues.push("Kernel clock not marked synchronized".to_string());
+                escalate(&mut status, HealthStatus::Degraded);
+            }
+        }
+    }
+
+    if !issues.is_empty() {
+        details.push_str(&format!(" - Issues: {}", issues.join(", ")));
+    }
+    (status, details)
+}
+
+/// NTP timestamp: seconds since 1900 in the upper 32 bits, the fraction in the lower 32 bits
+pub fn ntp_timestamp(time: SystemTime) -> u64 {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() + NTP_UNIX_OFFSET;
+    let fraction = (u64::from(since_epoch.subsec_nanos()) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
+
+/// `later - earlier` in seconds; wraps correctly across NTP eras
+fn ntp_difference(later: u64, earlier: u64) -> f64 {
+    later.wrapping_sub(earlier) as i64 as f64 / 4_294_967_296.0
+}
+
+pub fn sntp_request(transmit: u64) -> [u8; NTP_PACKET_LEN] {
+    let mut packet = [0u8; NTP_PACKET_LEN];
+    packet[0] = SNTP_CLIENT_HEADER;
+    packet[40..48].copy_from_slice(&transmit.to_be_bytes());
+    packet
+}
+
+/// Result of one SNTP exchange
+#[derive(Debug, Clone, PartialEq)]
+pub struct SntpReply {
+    pub stratum: u8,
+    /// Reference clock code for stratum 1, the upstream server address otherwise
+    pub reference: String,
+    /// Seconds the server clock is ahead of the local clock
+    pub offset: f64,
+    /// Round trip time minus the server's processing time, in seconds
+    pub delay: f64,
+}
+
+fn timestamp_at(packet: &[u8], start: usize) -> u64 {
+    u64::from_be_bytes(packet[start..start + 8].try_into().unwrap_or_default())
+}
+
+/// Parse the server reply to a request sent at `transmit` and received at `destination` (RFC 4330)
+pub fn parse_sntp_reply(packet: &[u8], transmit: u64, destination: u64) -> Result<SntpReply> {
+    if packet.len() < NTP_PACKET_LEN {
+        return Err(anyhow!("Short NTP reply of {} bytes", packet.len()));
+    }
+    if packet[0] & 0x07 != NTP_MODE_SERVER {
+        return Err(anyhow!("Not an NTP server reply (mode {})", packet[0] & 0x07));
+    }
+    if timestamp_at(packet, 24) != transmit {
+        return Err(anyhow!("NTP reply does not match the request"));
+    }
+    let stratum = packet[1];
+    let reference_id = &packet[12..16];
+    if stratum == 0 {
+        let code = String::from_utf8_lossy(reference_id).trim_end_matches('\0').to_string();
+        return Err(anyhow!("NTP server sent kiss-o'-death {}", code));
+    }
+    if packet[0] >> 6 == NTP_LEAP_UNSYNCHRONIZED {
+        return Err(anyhow!("NTP server clock is not synchronized"));
+    }
+
+    let receive = timestamp_at(packet, 32);
+    let server_transmit = timestamp_at(packet, 40);
+    if server_transmit == 0 {
+        return Err(anyhow!("NTP reply without transmit timestamp"));
+    }
+    let reference = match stratum {
+        1 => String::from_utf8_lossy(reference_id).trim_end_matches('\0').to_string(),
+        _ => std::net::Ipv4Addr::new(reference_id[0], reference_id[1], reference_id[2], reference_id[3]).to_string(),
+    };
+    Ok(SntpReply {
+        stratum,
+        reference,
+        offset: (ntp_difference(receive, transmit) + ntp_difference(server_transmit, destination)) / 2.0,
+        delay: ntp_difference(destination, transmit) - ntp_difference(server_transmit, receive),
+    })
+}
+
+/// `host:port` of an NTP server given as `host`, `host:port`, an IPv6 address or `[v6]:port`
+fn ntp_address(server: &str) -> String {
+    if server.parse::<std::net::Ipv6Addr>().is_ok() {
+        format!("[{}]:{}", server, NTP_DEFAULT_PORT)
+    } else if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{}:{}", server, NTP_DEFAULT_PORT)
+    }
+}
+
+/// Send one SNTP request to `server` and wait up to `timeout` for the reply
+pub async fn query_sntp(server: &str, timeout: Duration) -> Result<SntpReply> {
+    let exchange = async {
+        let target = tokio::net::lookup_host(ntp_address(server)).await
+            .with_context(|| format!("Cannot resolve {}", server))?
+            .next()
+            .ok_or_else(|| anyhow!("{} has no address", server))?;
+        let socket = match target {
+            std::net::SocketAddr::V4(_) => tokio::net::UdpSocket::bind("0.0.0.0:0").await?,
+            std::net::SocketAddr::V6(_) => tokio::net::UdpSocket::bind("[::]:0").await?,
+        };
+        socket.connect(target).await?;
+
+        let transmit = ntp_timestamp(SystemTime::now());
+        socket.send(&sntp_request(transmit)).await?;
+        let mut packet = [0u8; 512];
+        let length = socket.recv(&mut packet).await?;
+        parse_sntp_reply(&packet[..length], transmit, ntp_timestamp(SystemTime::now()))
+    };
+    tokio::time::timeout(timeout, exchange).await
+        .map_err(|_| anyhow!("No reply from {} within {}s", server, timeout.as_secs_f64()))?
+}
+
+pub fn evaluate_ntp_offset(server: &str, reply: &SntpReply, offset_ms: &Threshold) -> (HealthStatus, String) {
+    let direction = if reply.offset > 0.0 { "behind" } else { "ahead" };
+    let details = format!(
+        "Offset {} to {} (local clock {}), stratum {} via {}, delay {:.1}ms",
+        format_offset(reply.offset), server, direction, reply.stratum, reply.reference, reply.delay * 1000.0,
+    );
+    let status = severity(reply.offset.abs() * 1000.0, offset_ms).unwrap_or(HealthStatus::Healthy);
+    (status, details)
+}
+
+/// Seconds `remote` is ahead of the local clock at the middle of the request
+pub fn skew_seconds(remote: DateTime<Utc>, sent: DateTime<Utc>, received: DateTime<Utc>) -> f64 {
+    let midpoint = sent + (received - sent) / 2;
+    (remote - midpoint).num_microseconds().unwrap_or(i64::MAX) as f64 / 1_000_000.0
+}
+
+/// Skew to the host serving `url`, from the `Date` header of any response
+pub async fn http_date_skew(client: &reqwest::Client, url: &str) -> Result<f64> {
+    let sent = Utc::now();
+    let response = client.get(url).send().await
+        .map_err(|e| anyhow!("Cannot reach {}: {}", url, request_failure(e)))?;
+    let received = Utc::now();
+    let date = response.headers().get(reqwest::header::DATE)
+        .ok_or_else(|| anyhow!("No Date header in the response of {}", url))?
+        .to_str()?;
+    let date = DateTime::parse_from_rfc2822(date)
+        .with_context(|| format!("Invalid Date header {:?}", date))?
+        .with_timezone(&Utc);
+    // The header is truncated to the second, so its middle is the best estimate
+    Ok(skew_seconds(date + chrono::Duration::milliseconds(500), sent, received))
+}
+
+pub fn evaluate_skew(skew: f64, source: &str, skew_ms: &Threshold) -> (HealthStatus, String) {
+    let direction = if skew > 0.0 { "ahead of" } else { "behind" };
+    let details = format!("Clock {} ({} this host), from {}", format_offset(skew), direction, source);
+    let status = severity(skew.abs() * 1000.0, skew_ms).unwrap_or(HealthStatus::Healthy);
+    (status, details)
+}
+
+/// A result whose evaluation failed is Unknown: a peer that cannot be asked says nothing about the clock
+fn time_result(service_name: String, evaluation: Result<(HealthStatus, String)>, start_time: Instant) -> HealthCheckResult {
+    let (status, details, error_message) = match evaluation {
+        Ok((status, details)) => (status, details, None),
+        Err(e) => (HealthStatus::Unknown, format!("{:#}", e), Some(e.to_string())),
+    };
+    HealthCheckResult {
+        service_name,
+        status,
+        response_time_ms: start_time.elapsed().as_millis() as u64,
+        details,
+        timestamp: Utc::now(),
+        error_message,
+    }
+}
+
+async fn check_endpoint_skew(client: &reqwest::Client, name: &str, url: &str, config: &TimeConfig) -> HealthCheckResult {
+    let start_time = Instant::now();
+    let evaluation = http_date_skew(client, url).await
+        .map(|skew| evaluate_skew(skew, "HTTP Date", &config.skew_ms));
+    time_result(format!("Clock Skew ({})", name), evaluation, start_time)
+}
+
+impl crate::HealthChecker {
+    /// Local clock synchronization, the offset to the NTP server and the skew to other hosts
+    pub async fn check_time(&self) -> Result<Vec<HealthCheckResult>> {
+        let config = &self.config.time;
+        if !config.enabled {
+            return Ok(vec![Self::backend_unavailable_result(
+                "Time",
+                &HealthStatus::Unknown,
+                "Time checks disabled in config",
+            )]);
+        }
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let mut results = Vec::new();
+
+        let start_time = Instant::now();
+        let kernel = kernel_clock()
+            .map_err(|e| info!("Cannot read the kernel clock state: {}", e))
+            .ok();
+        let evaluation = evaluate_sync(kernel.as_ref(), &sync_daemon(timeout).await, &config.offset_ms);
+        results.push(time_result("Clock Sync".to_string(), Ok(evaluation), start_time));
+
+        if let Some(server) = &config.ntp_server {
+            let start_time = Instant::now();
+            let evaluation = query_sntp(server, timeout).await
+                .map(|reply| evaluate_ntp_offset(server, &reply, &config.offset_ms));
+            results.push(time_result(format!("NTP Offset ({})", server), evaluation, start_time));
+        }
+
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        results.extend(futures::future::join_all(config.endpoints.iter().map(|endpoint| {
+            check_endpoint_skew(&client, &endpoint.name, &endpoint.url, config)
+        })).await);
+
+        // The local engine shares this host's clock
+        if let (true, Backend::Available(hosts)) = (config.docker_hosts, self.docker_hosts().await) {
+            for host in hosts {
+                let Ok(client) = host.client().await else {
+                    continue;
+                };
+                if client.is_local() {
+                    continue;
+                }
+                let start_time = Instant::now();
+                let sent = Utc::now();
+                let evaluation = match tokio::time::timeout(timeout, client.system_time()).await {
+                    Ok(Ok(remote)) => Ok(evaluate_skew(skew_seconds(remote, sent, Utc::now()), "docker info", &config.skew_ms)),
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Err(anyhow!("docker info timed out after {}s", config.timeout_secs)),
+                };
+                results.push(time_result(format!("Clock Skew ({})", host.name), evaluation, start_time));
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const TRACKING_SYNCED: &str = include_str!("../tests/fixtures/chrony/tracking-synced.csv");
+    const TRACKING_UNSYNCED: &str = include_str!("../tests/fixtures/chrony/tracking-unsynced.csv");
+    const SHOW_SYNCED: &str = include_str!("../tests/fixtures/timedatectl/show-synced.txt");
+    const SHOW_TIMESYNC: &str = include_str!("../tests/fixtures/timedatectl/show-timesync.txt");
+    const SHOW_NTP_DISABLED: &str = include_str!("../tests/fixtures/timedatectl/show-ntp-disabled.txt");
+
+    fn kernel(synchronized: bool, est_error_us: i64) -> KernelClock {
+        KernelClock { synchronized, max_error_us: 16_000_000, est_error_us }
+    }
+
+    fn offset_ms() -> Threshold {
+        TimeConfig::default().offset_ms
+    }
+
+    #[test]
+    fn parses_chrony_tracking() {
+        let tracking = parse_chrony_tracking(TRACKING_SYNCED).unwrap();
+        assert_eq!(tracking.reference, "169.254.169.123");
+        assert_eq!(tracking.stratum, 4);
+        assert!((tracking.system_offset + 0.000018402).abs() < 1e-12);
+        assert!(tracking.synchronized());
+
+        let tracking = parse_chrony_tracking(TRACKING_UNSYNCED).unwrap();
+        assert!(!tracking.synchronized());
+        assert!(parse_chrony_tracking("506 Cannot talk to daemon").is_err());
+    }
+
+    #[test]
+    fn parses_timedatectl() {
+        let state = parse_timedatectl(&format!("{}{}", SHOW_SYNCED, SHOW_TIMESYNC));
+        assert_eq!(state, TimesyncdState { ntp: true, synchronized: true, server: Some("ntp.ubuntu.com".to_string()) });
+        assert_eq!(parse_timedatectl(SHOW_NTP_DISABLED), TimesyncdState::default());
+    }
+
+    #[tokio::test]
+    async fn hung_tools_are_given_up_on() {
+        assert_eq!(tool_output("echo", &["tracking"], Duration::from_secs(5)).await.as_deref(), Some("tracking\n"));
+        let start = Instant::now();
+        assert_eq!(tool_output("sleep", &["10"], Duration::from_millis(100)).await, None);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(tool_output("health-checker-missing-tool", &[], Duration::from_secs(5)).await, None);
+    }
+
+    #[test]
+    fn sync_state_of_every_daemon_is_evaluated() {
+        let chrony = SyncDaemon::Chrony(parse_chrony_tracking(TRACKING_SYNCED).unwrap());
+        assert_eq!(
+            evaluate_sync(Some(&kernel(true, 1500)), &chrony, &offset_ms()),
+            (
+                HealthStatus::Healthy,
+                "chrony synchronized to 169.254.169.123 (stratum 4), offset -0.018ms, kernel estimated error 1.5ms".to_string(),
+            )
+        );
+
+        let drifting = SyncDaemon::Chrony(ChronyTracking {
+            system_offset: 0.25,
+            ..parse_chrony_tracking(TRACKING_SYNCED).unwrap()
+        });
+        let (status, details) = evaluate_sync(Some(&kernel(true, 1500)), &drifting, &offset_ms());
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: Large offset: +250.0ms"), "{}", details);
+
+        let unsynced = SyncDaemon::Chrony(parse_chrony_tracking(TRACKING_UNSYNCED).unwrap());
+        assert_eq!(
+            evaluate_sync(Some(&kernel(false, 0)), &unsynced, &offset_ms()),
+            (
+                HealthStatus::Degraded,
+                "chrony not synchronised, kernel clock unsynchronized (max error 16.0s) - Issues: chronyd has no usable source".to_string(),
+            )
+        );
+
+        let timesyncd = SyncDaemon::Timesyncd(TimesyncdState { ntp: true, synchronized: false, server: None });
+        let (status, details) = evaluate_sync(None, &timesyncd, &offset_ms());
+        assert_eq!(status, HealthStatus::Degraded);
+        assert_eq!(details, "systemd-timesyncd with no server - Issues: systemd-timesyncd not synchronized (no server)");
+
+        // ntpd and other daemons are only visible through the kernel state
+        assert_eq!(evaluate_sync(Some(&kernel(true, 2_000_000)), &SyncDaemon::NotFound, &offset_ms()).0, HealthStatus::Unhealthy);
+        assert_eq!(evaluate_sync(Some(&kernel(true, 2000)), &SyncDaemon::NotFound, &offset_ms()).0, HealthStatus::Healthy);
+        let (status, details) = evaluate_sync(Some(&kernel(false, 0)), &SyncDaemon::NotFound, &offset_ms());
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.starts_with("no NTP daemon, kernel clock unsynchronized (max error 16.0s) - Issues: No NTP daemon found"), "{}", details);
+    }
+
+    /// Build the reply of a server whose clock is `server_offset` seconds ahead
+    fn sntp_reply(request: &[u8], server_offset: f64, stratum: u8, reference: &[u8; 4]) -> [u8; NTP_PACKET_LEN] {
+        let now = ntp_timestamp(SystemTime::now()).wrapping_add((server_offset * 4_294_967_296.0) as i64 as u64);
+        let mut packet = [0u8; NTP_PACKET_LEN];
+        packet[0] = 0x24;
+        packet[1] = stratum;
+        packet[12..16].copy_from_slice(reference);
+        packet[24..32].copy_from_slice(&request[40..48]);
+        packet[32..40].copy_from_slice(&now.to_be_bytes());
+        packet[40..48].copy_from_slice(&now.wrapping_add(1 << 22).to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn computes_offset_and_delay_from_timestamps() {
+        let transmit = ntp_timestamp(UNIX_EPOCH + Duration::from_secs(1_760_000_000));
+        let request = sntp_request(transmit);
+        assert_eq!(request[0], SNTP_CLIENT_HEADER);
+
+        // Server 2s ahead, 10ms each way, 2ms processing
+        let second = 1u64 << 32;
+        let millis = |ms: u64| ms * second / 1000;
+        let mut reply = [0u8; NTP_PACKET_LEN];
+        reply[0] = 0x24;
+        reply[1] = 2;
+        reply[12..16].copy_from_slice(&[10, 0, 0, 1]);
+        reply[24..32].copy_from_slice(&transmit.to_be_bytes());
+        reply[32..40].copy_from_slice(&(transmit + 2 * second + millis(10)).to_be_bytes());
+        reply[40..48].copy_from_slice(&(transmit + 2 * second + millis(12)).to_be_bytes());
+        let destination = transmit + millis(22);
+
+        let parsed = parse_sntp_reply(&reply, transmit, destination).unwrap();
+        assert_eq!(parsed.stratum, 2);
+        assert_eq!(parsed.reference, "10.0.0.1");
+        assert!((parsed.offset - 2.0).abs() < 1e-6, "{}", parsed.offset);
+        assert!((parsed.delay - 0.020).abs() < 1e-6, "{}", parsed.delay);
+
+        assert!(parse_sntp_reply(&reply, transmit + 1, destination).unwrap_err().to_string().contains("does not match"));
+        assert!(parse_sntp_reply(&reply[..40], transmit, destination).is_err());
+
+        let mut kiss = reply;
+        kiss[1] = 0;
+        kiss[12..16].copy_from_slice(b"RATE");
+        assert_eq!(parse_sntp_reply(&kiss, transmit, destination).unwrap_err().to_string(), "NTP server sent kiss-o'-death RATE");
+
+        let mut unsynchronized = reply;
+        unsynchronized[0] = 0xE4;
+        assert!(parse_sntp_reply(&unsynchronized, transmit, destination).is_err());
+    }
+
+    #[test]
+    fn ntp_servers_get_the_default_port() {
+        assert_eq!(ntp_address("ntp.ubuntu.com"), "ntp.ubuntu.com:123");
+        assert_eq!(ntp_address("10.0.0.1:1123"), "10.0.0.1:1123");
+        assert_eq!(ntp_address("2001:db8::1"), "[2001:db8::1]:123");
+        assert_eq!(ntp_address("[2001:db8::1]:123"), "[2001:db8::1]:123");
+    }
+
+    /// A stand-in NTP server answering every request with its clock `server_offset` seconds ahead
+    async fn serve_ntp(server_offset: f64, stratum: u8, reference: &'static [u8; 4]) -> String {
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut request = [0u8; NTP_PACKET_LEN];
+            while let Ok((_, client)) = socket.recv_from(&mut request).await {
+                let reply = sntp_reply(&request, server_offset, stratum, reference);
+                let _ = socket.send_to(&reply, client).await;
+            }
+        });
+        address.to_string()
+    }
+
+    #[tokio::test]
+    async fn queries_a_stand_in_ntp_server() {
+        let server = serve_ntp(2.5, 1, b"GPS\0").await;
+        let reply = query_sntp(&server, Duration::from_secs(2)).await.unwrap();
+        assert_eq!(reply.reference, "GPS");
+        assert!((reply.offset - 2.5).abs() < 0.05, "{}", reply.offset);
+        assert!(reply.delay < 0.05, "{}", reply.delay);
+
+        let (status, details) = evaluate_ntp_offset(&server, &reply, &offset_ms());
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.starts_with("Offset +2.5"), "{}", details);
+        assert!(details.contains("(local clock behind), stratum 1 via GPS"), "{}", details);
+
+        let server = serve_ntp(-0.040, 2, &[192, 168, 0, 10]).await;
+        let reply = query_sntp(&server, Duration::from_secs(2)).await.unwrap();
+        let (status, details) = evaluate_ntp_offset(&server, &reply, &offset_ms());
+        assert_eq!(status, HealthStatus::Healthy, "{}", details);
+        assert!(details.contains("(local clock ahead), stratum 2 via 192.168.0.10"), "{}", details);
+
+        let server = serve_ntp(0.0, 0, b"DENY").await;
+        let error = query_sntp(&server, Duration::from_secs(2)).await.unwrap_err();
+        assert_eq!(error.to_string(), "NTP server sent kiss-o'-death DENY");
+    }
+
+    #[tokio::test]
+    async fn silent_ntp_server_times_out() {
+        let silent = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server = silent.local_addr().unwrap().to_string();
+        let error = query_sntp(&server, Duration::from_millis(200)).await.unwrap_err();
+        assert_eq!(error.to_string(), format!("No reply from {} within 0.2s", server));
+    }
+
+    /// A stand-in HTTP server whose `Date` header is `server_offset` ahead, or without one
+    async fn serve_date(server_offset: Option<chrono::Duration>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0u8; 4096];
+                let _ = stream.read(&mut request).await;
+                let date = match server_offset {
+                    Some(offset) => format!("Date: {}\r\n", (Utc::now() + offset).format("%a, %d %b %Y %H:%M:%S GMT")),
+                    None => String::new(),
+                };
+                let response = format!("HTTP/1.1 401 Unauthorized\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n", date);
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}/server/version", address)
+    }
+
+    #[tokio::test]
+    async fn skew_comes_from_http_date_headers() {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(2)).build().unwrap();
+        let config = TimeConfig::default();
+
+        let url = serve_date(Some(chrono::Duration::seconds(90))).await;
+        let result = check_endpoint_skew(&client, "manager", &url, &config).await;
+        assert_eq!(result.service_name, "Clock Skew (manager)");
+        assert_eq!(result.status, HealthStatus::Unhealthy, "{}", result.details);
+        assert!(result.details.contains("ahead of this host), from HTTP Date"), "{}", result.details);
+        let skew = http_date_skew(&client, &url).await.unwrap();
+        assert!((skew - 90.0).abs() <= 1.0, "{}", skew);
+
+        let url = serve_date(Some(chrono::Duration::zero())).await;
+        let result = check_endpoint_skew(&client, "storage-proxy", &url, &config).await;
+        assert_eq!(result.status, HealthStatus::Healthy, "{}", result.details);
+
+        let url = serve_date(None).await;
+        let result = check_endpoint_skew(&client, "agent", &url, &config).await;
+        assert_eq!(result.status, HealthStatus::Unknown);
+        assert!(result.details.starts_with("No Date header"), "{}", result.details);
+    }
+
+    #[test]
+    fn skew_is_measured_from_the_middle_of_the_request() {
+        let sent = DateTime::parse_from_rfc3339("2026-10-18T09:00:00Z").unwrap().with_timezone(&Utc);
+        let received = sent + chrono::Duration::milliseconds(400);
+        let remote = sent + chrono::Duration::milliseconds(3200);
+        assert!((skew_seconds(remote, sent, received) - 3.0).abs() < 1e-9);
+
+        let (status, details) = evaluate_skew(-3.0, "docker info", &TimeConfig::default().skew_ms);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert_eq!(details, "Clock -3.00s (behind this host), from docker info");
+    }
+}
@@ -0,0 +1,654 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Config file picked up from the working directory when `--config` is not given
+pub const DEFAULT_CONFIG_FILE: &str = "health-checker.toml";
+
+/// Health checker configuration loaded from a TOML file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub docker: DockerConfig,
+    pub gpu: GpuConfig,
+    pub infrastructure: InfrastructureConfig,
+    pub orphans: OrphansConfig,
+    pub system: SystemConfig,
+    /// OpenAI-compatible inference endpoints, e.g. vLLM model services
+    pub model_services: Vec<ModelServiceConfig>,
+    /// Clock synchronization and skew between hosts
+    pub time: TimeConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockerConfig {
+    /// Set to false on hosts without a Docker engine (e.g. containerd-only Kubernetes nodes)
+    pub enabled: bool,
+    /// Docker engines to inspect; the local engine is used when empty
+    pub endpoints: Vec<DockerEndpoint>,
+    /// Thresholds applied to the diagnostics of every engine
+    pub daemon: DockerDaemonConfig,
+}
+
+impl Default for DockerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            endpoints: Vec::new(),
+            daemon: DockerDaemonConfig::default(),
+        }
+    }
+}
+
+/// Thresholds applied to Docker daemon diagnostics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockerDaemonConfig {
+    /// Usage of the filesystem holding the data root, in percent; only measured for local engines
+    pub data_root_used: Threshold,
+    pub dangling_images: Threshold,
+    pub dangling_volumes: Threshold,
+    /// Build cache size in MiB
+    pub build_cache_mib: Threshold,
+}
+
+impl Default for DockerDaemonConfig {
+    fn default() -> Self {
+        Self {
+            data_root_used: Threshold { degraded: Some(85.0), unhealthy: Some(95.0) },
+            dangling_images: Threshold { degraded: Some(50.0), unhealthy: None },
+            dangling_volumes: Threshold { degraded: Some(20.0), unhealthy: None },
+            build_cache_mib: Threshold { degraded: Some(20.0 * 1024.0), unhealthy: None },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuConfig {
+    pub enabled: bool,
+    /// Expected MIG partitioning; GPUs not listed are not checked
+    pub mig_layout: Vec<MigLayout>,
+    /// Where sysfs is mounted, for PCI discovery and amdgpu RAS counters
+    pub sysfs_root: PathBuf,
+    /// Agent ID in the manager database, for comparing its resource slots; defaults to `i-<hostname>`
+    pub agent_id: Option<String>,
+    /// `cuda.shares` an fGPU agent advertises per physical GPU
+    pub shares_per_device: f64,
+    /// Expected interconnect topology of this node
+    pub topology: TopologyConfig,
+    /// Thresholds for temperature, memory, power and utilization
+    pub policy: GpuHealthPolicy,
+    /// Repeated samples used by `gpu --sample-for` and monitor mode
+    pub sampling: GpuSamplingConfig,
+    /// When a GPU allocated to a session counts as idle
+    pub idle: IdleGpuConfig,
+    /// Where NVIDIA GPU metrics come from
+    pub nvidia_source: NvidiaSource,
+    /// Metrics endpoint scraped with `nvidia_source = "dcgm-exporter"`
+    pub dcgm_exporter_url: String,
+    /// Whether containers can use the NVIDIA GPUs
+    pub container_runtime: ContainerRuntimeConfig,
+    /// How far back XID events in the kernel log are counted; 0 counts every event since boot
+    pub xid_window_secs: u64,
+    /// How long nvidia-smi and rocm-smi may take; a GPU tool that does not answer means the
+    /// driver is unresponsive
+    pub timeout_secs: u64,
+}
+
+impl Default for GpuConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mig_layout: Vec::new(),
+            sysfs_root: PathBuf::from("/sys"),
+            agent_id: None,
+            shares_per_device: 1.0,
+            topology: TopologyConfig::default(),
+            policy: GpuHealthPolicy::default(),
+            sampling: GpuSamplingConfig::default(),
+            idle: IdleGpuConfig::default(),
+            nvidia_source: NvidiaSource::default(),
+            dcgm_exporter_url: "http://localhost:9400/metrics".to_string(),
+            container_runtime: ContainerRuntimeConfig::default(),
+            xid_window_secs: 86400,
+            timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuSamplingConfig {
+    /// How long a threshold must be exceeded before it is reported; while the samples cover
+    /// less than this, it must be exceeded in all of them
+    pub sustain_secs: u64,
+    /// Samples monitor mode keeps per GPU; 0 evaluates every check on its own
+    pub window_secs: u64,
+}
+
+impl Default for GpuSamplingConfig {
+    fn default() -> Self {
+        Self {
+            sustain_secs: 60,
+            window_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleGpuConfig {
+    /// GPU utilization in percent below which an allocated GPU is idle
+    pub utilization: f64,
+    /// How long an allocated GPU may stay idle before it is reported
+    pub after_secs: u64,
+}
+
+impl Default for IdleGpuConfig {
+    fn default() -> Self {
+        Self {
+            utilization: 5.0,
+            after_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerRuntimeConfig {
+    /// Directories holding CDI specs generated by `nvidia-ctk cdi generate`
+    pub cdi_spec_dirs: Vec<PathBuf>,
+    /// Start a container that lists its GPUs on every check, as `gpu --probe-container` does
+    pub probe: bool,
+    /// Image with `nvidia-smi` for the probe container; it must already be on the node
+    pub probe_image: String,
+    pub probe_timeout_secs: u64,
+}
+
+impl Default for ContainerRuntimeConfig {
+    fn default() -> Self {
+        Self {
+            cdi_spec_dirs: vec![PathBuf::from("/etc/cdi"), PathBuf::from("/var/run/cdi")],
+            probe: false,
+            probe_image: "nvidia/cuda:12.4.1-base-ubuntu22.04".to_string(),
+            probe_timeout_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NvidiaSource {
+    /// NVML when built with the `nvidia` feature, otherwise nvidia-smi
+    #[default]
+    Local,
+    /// A dcgm-exporter `/metrics` endpoint, for nodes that already run one for Prometheus
+    DcgmExporter,
+}
+
+/// MIG profiles a GPU should be partitioned into, e.g. `["3g.40gb", "2g.20gb", "2g.20gb"]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigLayout {
+    /// GPU index as shown by nvidia-smi
+    pub gpu: u32,
+    /// An empty list means MIG should be disabled on this GPU
+    pub profiles: Vec<String>,
+}
+
+/// GPU health thresholds, with overrides for GPU models that run hotter or are power capped differently
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuHealthPolicy {
+    /// Core temperature in °C (edge sensor on AMD GPUs)
+    pub temperature: Threshold,
+    /// Junction (hotspot) temperature in °C, on GPUs that report it. It runs well above the
+    /// edge temperature, so it has its own limits.
+    pub junction_temperature: Threshold,
+    /// Memory used, in percent of total (memory pressure on Apple Silicon)
+    pub memory: Threshold,
+    /// Power draw, in percent of the power limit
+    pub power: Threshold,
+    /// GPU utilization in percent
+    pub utilization: Threshold,
+    /// Applied in order to GPUs whose name matches, so later overrides win.
+    /// Setting this replaces the built-in Apple Silicon override.
+    pub overrides: Vec<GpuPolicyOverride>,
+}
+
+impl Default for GpuHealthPolicy {
+    fn default() -> Self {
+        Self {
+            temperature: Threshold { degraded: Some(85.0), unhealthy: Some(95.0) },
+            junction_temperature: Threshold { degraded: Some(100.0), unhealthy: Some(110.0) },
+            memory: Threshold { degraded: Some(90.0), unhealthy: None },
+            power: Threshold { degraded: Some(95.0), unhealthy: None },
+            utilization: Threshold::default(),
+            overrides: vec![GpuPolicyOverride {
+                name: "Apple*".to_string(),
+                temperature: Some(Threshold { degraded: Some(80.0), unhealthy: None }),
+                junction_temperature: None,
+                memory: Some(Threshold { degraded: Some(80.0), unhealthy: None }),
+                power: None,
+                utilization: None,
+            }],
+        }
+    }
+}
+
+/// Values above `degraded` make a GPU Degraded, values above `unhealthy` Unhealthy; unset levels are not checked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Threshold {
+    pub degraded: Option<f64>,
+    pub unhealthy: Option<f64>,
+}
+
+/// Thresholds for GPUs whose name matches `name`, a case-insensitive pattern where `*` matches anything
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuPolicyOverride {
+    pub name: String,
+    pub temperature: Option<Threshold>,
+    pub junction_temperature: Option<Threshold>,
+    pub memory: Option<Threshold>,
+    pub power: Option<Threshold>,
+    pub utilization: Option<Threshold>,
+}
+
+/// Expected GPU interconnect; unset fields are not checked
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TopologyConfig {
+    /// Connection between every pair of GPUs as shown by `nvidia-smi topo -m`, e.g. `NV12`
+    pub gpu_link: Option<String>,
+    /// Active NVLink links on every GPU
+    pub nvlink_links: Option<u32>,
+    /// Minimum PCIe generation and width of every GPU
+    pub pcie_generation: Option<u32>,
+    pub pcie_width: Option<u32>,
+    /// Farthest acceptable connection from a GPU to its NIC (`PIX`, `PXB`, `PHB`, `NODE`); defaults to `PXB`
+    pub nic_link: Option<String>,
+    pub affinity: Vec<GpuAffinity>,
+}
+
+/// NIC and NUMA node a GPU should be attached to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuAffinity {
+    /// GPU index as shown by nvidia-smi
+    pub gpu: u32,
+    /// RDMA device name such as `mlx5_0`
+    pub nic: Option<String>,
+    pub numa_node: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InfrastructureConfig {
+    /// Set to false on hosts that cannot reach PostgreSQL, Redis and etcd (e.g. a bastion host)
+    pub enabled: bool,
+    /// Connection string of the manager database
+    pub postgres_dsn: String,
+}
+
+impl Default for InfrastructureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            postgres_dsn: "host=127.0.0.1 port=8101 user=postgres dbname=backend".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrphansConfig {
+    /// Agent `scratch-root`, holding one directory (and loop image) per kernel
+    pub scratch_root: PathBuf,
+    /// Resources younger than this are never reported, so kernels still being created are skipped
+    pub grace_period_secs: u64,
+}
+
+impl Default for OrphansConfig {
+    fn default() -> Self {
+        Self {
+            scratch_root: PathBuf::from("./scratches"),
+            grace_period_secs: 300,
+        }
+    }
+}
+
+/// Host resource checks of the `system` subcommand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemConfig {
+    /// Where procfs is mounted, for meminfo, loadavg and stat
+    pub proc_root: PathBuf,
+    /// Interval between the two `/proc/stat` reads that steal and iowait are measured over
+    pub cpu_sample_millis: u64,
+    /// Memory in use (MemTotal minus MemAvailable), in percent of MemTotal
+    pub memory_used: Threshold,
+    /// Swap in use, in percent of SwapTotal; not checked without swap
+    pub swap_used: Threshold,
+    /// 5-minute load average divided by the number of CPUs
+    pub load_per_core: Threshold,
+    /// CPU time stolen by the hypervisor, in percent
+    pub cpu_steal: Threshold,
+    /// CPU time spent waiting for I/O, in percent
+    pub cpu_iowait: Threshold,
+    /// Data paths whose filesystem is checked, each with its own thresholds
+    pub disks: Vec<DiskConfig>,
+    /// Free space history used for time-to-full estimates in monitor mode
+    pub trend_window_secs: u64,
+    /// NFS, CephFS, Lustre and other network filesystem probes
+    pub network_mounts: NetworkMountsConfig,
+    /// Kernel tunables and host prerequisites of agent nodes
+    pub host_audit: HostAuditConfig,
+    /// Service ports whose listening process is identified through procfs
+    pub ports: Vec<ServicePortConfig>,
+}
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        Self {
+            proc_root: PathBuf::from("/proc"),
+            cpu_sample_millis: 500,
+            memory_used: Threshold { degraded: Some(90.0), unhealthy: Some(95.0) },
+            swap_used: Threshold { degraded: Some(80.0), unhealthy: None },
+            load_per_core: Threshold { degraded: Some(2.0), unhealthy: Some(4.0) },
+            cpu_steal: Threshold { degraded: Some(10.0), unhealthy: None },
+            cpu_iowait: Threshold { degraded: Some(20.0), unhealthy: None },
+            disks: vec![DiskConfig::default()],
+            trend_window_secs: 3600,
+            network_mounts: NetworkMountsConfig::default(),
+            host_audit: HostAuditConfig::default(),
+            ports: ServicePortConfig::halfstack(),
+        }
+    }
+}
+
+/// A TCP port a Backend.AI service is expected to listen on
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServicePortConfig {
+    /// Name shown in results
+    pub name: String,
+    pub port: u16,
+    /// Case-insensitive substrings of the process name, command line, container name or image
+    /// of the expected owner; any owner is accepted when empty
+    pub owners: Vec<String>,
+    /// Whether the port must only be bound to loopback addresses
+    pub loopback_only: bool,
+}
+
+impl ServicePortConfig {
+    fn new(name: &str, port: u16, owners: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            port,
+            owners: owners.iter().map(|owner| owner.to_string()).collect(),
+            loopback_only: false,
+        }
+    }
+
+    /// Ports of a default halfstack installation
+    pub fn halfstack() -> Vec<Self> {
+        vec![
+            Self::new("Manager API", 8081, &["ai.backend.manager", "backend.ai-manager"]),
+            Self::new("PostgreSQL", 8101, &["postgres"]),
+            Self::new("Redis", 8111, &["redis"]),
+            Self::new("etcd", 8121, &["etcd"]),
+            Self::new("Prometheus", 9090, &["prometheus"]),
+            Self::new("Grafana", 3000, &["grafana"]),
+        ]
+    }
+}
+
+/// Host settings Backend.AI agents rely on, compared with a built-in recommended profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostAuditConfig {
+    pub enabled: bool,
+    /// Where sysfs is mounted, for cgroups and built-in kernel modules
+    pub sysfs_root: PathBuf,
+    /// Expected sysctl values, merged over the built-in profile: `"1"` for an exact value,
+    /// `">= 262144"` for a minimum, `""` to drop a built-in entry
+    pub sysctls: BTreeMap<String, String>,
+    /// Kernel modules that must be loaded
+    pub modules: Vec<String>,
+    /// cgroup controllers that must be available (v2 names; `io` is checked as `blkio` on v1)
+    pub cgroup_controllers: Vec<String>,
+    pub shm_path: PathBuf,
+    /// Minimum size of `shm_path` in MiB; 0 disables the check
+    pub shm_min_mib: u64,
+    /// Minimum soft open file limit of the running agent process; 0 disables the check
+    pub nofile_min: u64,
+}
+
+impl Default for HostAuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sysfs_root: PathBuf::from("/sys"),
+            sysctls: BTreeMap::new(),
+            modules: vec!["overlay".to_string(), "br_netfilter".to_string()],
+            cgroup_controllers: ["cpu", "cpuset", "memory", "io", "pids"].map(str::to_string).to_vec(),
+            shm_path: PathBuf::from("/dev/shm"),
+            shm_min_mib: 1024,
+            nofile_min: 65536,
+        }
+    }
+}
+
+/// Probes of network filesystems such as vfolder mounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkMountsConfig {
+    /// Probe every network filesystem listed in `<proc_root>/self/mountinfo`
+    pub discover: bool,
+    /// Mount points probed in addition to the discovered ones
+    pub paths: Vec<PathBuf>,
+    /// Also write, fsync, read back and remove a probe file; read-only mounts are never written
+    pub write_probe: bool,
+    /// Probe file created in each mount point, suffixed with the hostname
+    pub probe_file: String,
+    /// A probe taking longer than this is reported as hung
+    pub timeout_secs: u64,
+    /// Total probe time in milliseconds
+    pub latency_ms: Threshold,
+}
+
+impl Default for NetworkMountsConfig {
+    fn default() -> Self {
+        Self {
+            discover: true,
+            paths: Vec::new(),
+            write_probe: true,
+            probe_file: ".health-checker-probe".to_string(),
+            timeout_secs: 10,
+            latency_ms: Threshold { degraded: Some(1000.0), unhealthy: None },
+        }
+    }
+}
+
+/// A data path such as the Docker data-root, scratch root, a vfolder mount or the etcd data dir
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiskConfig {
+    /// Name shown in results
+    pub name: String,
+    pub path: PathBuf,
+    /// Whether `path` must be a mount point itself, e.g. a vfolder mount that would otherwise fill the root disk
+    pub mount_point: bool,
+    /// Blocks in use, in percent as shown by df
+    pub used: Threshold,
+    /// Inodes in use, in percent as shown by df -i
+    pub inodes_used: Threshold,
+    /// Estimated hours until full below which the disk is Degraded (monitor mode only)
+    pub min_hours_to_full: Option<f64>,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            name: "Root".to_string(),
+            path: PathBuf::from("/"),
+            mount_point: false,
+            used: Threshold { degraded: Some(90.0), unhealthy: Some(95.0) },
+            inodes_used: Threshold { degraded: Some(90.0), unhealthy: Some(95.0) },
+            min_hours_to_full: Some(24.0),
+        }
+    }
+}
+
+/// An OpenAI-compatible inference endpoint such as a vLLM model service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelServiceConfig {
+    /// Name shown in results
+    pub name: String,
+    /// Base URL without `/v1`, e.g. `http://localhost:8000`
+    pub url: String,
+    /// Model ID that `/v1/models` must list; the first listed model is used when unset
+    pub model: Option<String>,
+    /// Bearer token for servers started with `--api-key`
+    pub api_key: Option<String>,
+    /// Prompt of the test completion
+    pub prompt: String,
+    pub max_tokens: u32,
+    /// Time to first token of the test completion, in milliseconds
+    pub ttft_ms: Threshold,
+    /// Decode rate of the test completion below which the service is Degraded
+    pub min_tokens_per_sec: Option<f64>,
+    /// Scrape vLLM's `/metrics` for queue length and KV-cache usage
+    pub metrics: bool,
+    /// Requests waiting to be scheduled, with `metrics`
+    pub waiting_requests: Threshold,
+    /// GPU KV-cache usage in percent, with `metrics`
+    pub kv_cache_usage: Threshold,
+    pub timeout_secs: u64,
+}
+
+impl Default for ModelServiceConfig {
+    fn default() -> Self {
+        Self {
+            name: "vLLM".to_string(),
+            url: "http://localhost:8000".to_string(),
+            model: None,
+            api_key: None,
+            prompt: "Hello".to_string(),
+            max_tokens: 16,
+            ttft_ms: Threshold { degraded: Some(2000.0), unhealthy: Some(10000.0) },
+            min_tokens_per_sec: None,
+            metrics: false,
+            waiting_requests: Threshold { degraded: Some(10.0), unhealthy: None },
+            kv_cache_usage: Threshold { degraded: Some(90.0), unhealthy: None },
+            timeout_secs: 60,
+        }
+    }
+}
+
+/// Clock synchronization of this host and its skew to an NTP server and other hosts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeConfig {
+    pub enabled: bool,
+    /// NTP server queried with SNTP, as `host` or `host:port`; not queried when unset
+    pub ntp_server: Option<String>,
+    /// Offset to the NTP server and estimated error of the local clock, in milliseconds
+    pub offset_ms: Threshold,
+    /// Clock difference to other hosts in milliseconds; HTTP Date headers only have second resolution
+    pub skew_ms: Threshold,
+    /// Hosts whose HTTP `Date` header is compared with the local clock
+    pub endpoints: Vec<TimeEndpoint>,
+    /// Also compare the clocks of remote Docker engines (`SystemTime` of `docker info`)
+    pub docker_hosts: bool,
+    /// Timeout of each query, and of `chronyc` and `timedatectl`
+    pub timeout_secs: u64,
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ntp_server: None,
+            offset_ms: Threshold { degraded: Some(100.0), unhealthy: Some(1000.0) },
+            skew_ms: Threshold { degraded: Some(2000.0), unhealthy: Some(60000.0) },
+            endpoints: Vec::new(),
+            docker_hosts: true,
+            timeout_secs: 5,
+        }
+    }
+}
+
+/// An HTTP service on another host, e.g. the manager, a storage-proxy or an agent watcher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeEndpoint {
+    /// Name shown in results
+    pub name: String,
+    /// Any URL answering with a `Date` header; error statuses are fine
+    pub url: String,
+}
+
+/// A single Docker engine reachable over a unix socket, TCP+TLS or plain HTTP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DockerEndpoint {
+    /// Host label attached to every result coming from this engine
+    pub name: String,
+    /// `unix:///path/to/docker.sock`, `tcp://host:2376` or `http://host:2375`
+    pub url: String,
+    /// Client certificate TLS settings, required for `https://` and TLS-enabled `tcp://` engines
+    pub tls: Option<DockerTlsConfig>,
+    /// Whether the engine's data root is on this machine's filesystem.
+    /// Defaults to true for unix sockets; set to false for SSH-forwarded sockets.
+    pub local: Option<bool>,
+    /// Request timeout in seconds
+    #[serde(default = "default_docker_timeout")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DockerTlsConfig {
+    pub ca_cert: PathBuf,
+    pub client_cert: PathBuf,
+    pub client_key: PathBuf,
+}
+
+fn default_docker_timeout() -> u64 {
+    10
+}
+
+impl DockerEndpoint {
+    pub fn is_unix_socket(&self) -> bool {
+        self.url.starts_with("unix://") || self.url.starts_with('/')
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.local.unwrap_or_else(|| self.is_unix_socket())
+    }
+}
+
+impl Config {
+    /// Load the config from `path`, or from `health-checker.toml` in the
+    /// working directory if present, falling back to defaults.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => {
+                let default_path = Path::new(DEFAULT_CONFIG_FILE);
+                if default_path.exists() {
+                    Self::from_file(default_path)
+                } else {
+                    Ok(Self::default())
+                }
+            }
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+}
@@ -1,12 +1,32 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use bollard::container::{ListContainersOptions, InspectContainerOptions};
-use bollard::Docker;
+use bollard::models::PortTypeEnum;
+use bollard::{Docker, API_DEFAULT_VERSION};
+use chrono::{DateTime, TimeZone, Utc};
+use futures::stream::{Stream, StreamExt};
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::config::{DockerConfig, DockerEndpoint};
+use crate::events::{ContainerEvent, WATCHED_CONTAINER_EVENTS};
 use crate::HealthStatus;
 
+/// Host label used for the engine found through local defaults
+pub const LOCAL_DOCKER_HOST: &str = "local";
+
+const LOCAL_DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+/// Labels set by the Backend.AI agent on kernel containers
+pub const KERNEL_ID_LABEL: &str = "ai.backend.kernel-id";
+pub const SESSION_ID_LABEL: &str = "ai.backend.session-id";
+pub const ACCESS_KEY_LABEL: &str = "ai.backend.access-key";
+
+pub const KRUNNER_VOLUME_PREFIX: &str = "backendai-krunner.";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContainerInfo {
+    pub host: String,
     pub id: String,
     pub name: String,
     pub image: String,
@@ -14,25 +34,382 @@ pub struct ContainerInfo {
     pub ports: Vec<String>,
 }
 
+/// A container launched by a Backend.AI agent for a session kernel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KernelContainer {
+    pub host: String,
+    pub id: String,
+    pub name: String,
+    pub kernel_id: String,
+    pub session_id: Option<String>,
+    pub created: DateTime<Utc>,
+    pub state: String,
+}
+
+/// Any container with its labels, for tracing host processes back to their container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledContainer {
+    pub id: String,
+    pub name: String,
+    pub state: String,
+    pub labels: std::collections::HashMap<String, String>,
+    /// GPUs given to a running kernel container through NVIDIA device requests or
+    /// `NVIDIA_VISIBLE_DEVICES`, as UUIDs, indexes or `all`; empty for other containers
+    #[serde(default)]
+    pub gpu_devices: Vec<String>,
+    #[serde(default)]
+    pub image: String,
+    /// TCP ports published on the host, through docker-proxy or NAT rules
+    #[serde(default)]
+    pub published_ports: Vec<PublishedPort>,
+}
+
+/// A container port published on the host
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublishedPort {
+    /// Host address the port is bound to; `0.0.0.0` or `::` for every address
+    pub host_ip: String,
+    pub host_port: u16,
+    pub container_port: u16,
+}
+
+/// A `backendai-krunner.*` volume holding the kernel runner for one version/arch/distro
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KrunnerVolume {
+    pub name: String,
+    pub in_use: bool,
+    pub size_bytes: Option<u64>,
+}
+
+/// Daemon-level information gathered from the Docker Engine API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonDiagnostics {
+    pub server_version: String,
+    pub api_version: String,
+    pub min_api_version: String,
+    pub client_api_version: String,
+    pub storage_driver: String,
+    pub docker_root_dir: Option<String>,
+    pub default_runtime: Option<String>,
+    pub runtimes: Vec<String>,
+    pub images: DiskUsage,
+    pub containers: DiskUsage,
+    pub volumes: DiskUsage,
+    pub build_cache: DiskUsage,
+    pub dangling_images: usize,
+    pub dangling_volumes: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Object count and size for one `system df` category
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub count: usize,
+    pub size_bytes: u64,
+}
+
+impl DaemonDiagnostics {
+    pub fn nvidia_runtime_registered(&self) -> bool {
+        self.runtimes.iter().any(|name| name == "nvidia")
+    }
+}
+
+/// Container runtimes registered with the Docker daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DockerRuntimes {
+    pub default_runtime: Option<String>,
+    pub runtimes: Vec<String>,
+}
+
+#[derive(Clone)]
 pub struct DockerClient {
     client: Docker,
+    host: String,
+    local: bool,
+}
+
+/// Minimum delay before reconnecting to an engine that could not be reached
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A configured Docker engine, connected on first use.
+/// A failed connection is retried on later use, so engines that come up after
+/// the checker are picked up in monitor mode.
+pub struct DockerHost {
+    pub name: String,
+    /// `None` for the engine found through local defaults
+    endpoint: Option<DockerEndpoint>,
+    client: tokio::sync::OnceCell<DockerClient>,
+    last_failure: Mutex<Option<(Instant, String)>>,
+    reconnect_interval: Duration,
+}
+
+impl DockerHost {
+    /// The configured endpoints, or the local engine when none are configured
+    pub fn from_config(config: &DockerConfig) -> Vec<Self> {
+        if config.endpoints.is_empty() {
+            return vec![Self::new(LOCAL_DOCKER_HOST.to_string(), None)];
+        }
+        config.endpoints.iter()
+            .map(|endpoint| Self::new(endpoint.name.clone(), Some(endpoint.clone())))
+            .collect()
+    }
+
+    fn new(name: String, endpoint: Option<DockerEndpoint>) -> Self {
+        Self {
+            name,
+            endpoint,
+            client: tokio::sync::OnceCell::new(),
+            last_failure: Mutex::new(None),
+            reconnect_interval: RECONNECT_INTERVAL,
+        }
+    }
+
+    /// The connected client, connecting now if needed.
+    /// Within `RECONNECT_INTERVAL` of a failure the previous error is returned without retrying.
+    pub async fn client(&self) -> Result<&DockerClient> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+        if let Some((failed_at, reason)) = self.last_failure.lock().unwrap().as_ref() {
+            if failed_at.elapsed() < self.reconnect_interval {
+                return Err(anyhow!("{}", reason));
+            }
+        }
+
+        let connection = self.client.get_or_try_init(|| async {
+            match &self.endpoint {
+                Some(endpoint) => DockerClient::connect(endpoint).await,
+                None => DockerClient::new().await,
+            }
+        }).await;
+
+        match connection {
+            Ok(client) => {
+                *self.last_failure.lock().unwrap() = None;
+                Ok(client)
+            }
+            Err(e) => {
+                *self.last_failure.lock().unwrap() = Some((Instant::now(), e.to_string()));
+                Err(e)
+            }
+        }
+    }
 }
 
 impl DockerClient {
     pub async fn new() -> Result<Self> {
+        if let Some(reason) = Self::local_engine_missing() {
+            return Err(anyhow!(reason));
+        }
+
         let client = Docker::connect_with_local_defaults()?;
-        
-        // Test connection
-        let version = client.version().await?;
-        info!("Connected to Docker version: {:?}", version.version);
-        
-        Ok(Self { client })
+        Self::verify(client, LOCAL_DOCKER_HOST.to_string(), true, 10).await
+    }
+
+    pub async fn connect(endpoint: &DockerEndpoint) -> Result<Self> {
+        let client = Self::build_client(endpoint)?;
+        Self::verify(client, endpoint.name.clone(), endpoint.is_local(), endpoint.timeout_secs).await
+    }
+
+    /// Pick the bollard transport matching the endpoint URL scheme
+    fn build_client(endpoint: &DockerEndpoint) -> Result<Docker> {
+        let url = endpoint.url.as_str();
+        let timeout = endpoint.timeout_secs;
+
+        let client = if endpoint.is_unix_socket() {
+            Docker::connect_with_unix(url.trim_start_matches("unix://"), timeout, API_DEFAULT_VERSION)?
+        } else if let Some(tls) = &endpoint.tls {
+            Docker::connect_with_ssl(
+                url,
+                &tls.client_key,
+                &tls.client_cert,
+                &tls.ca_cert,
+                timeout,
+                API_DEFAULT_VERSION,
+            )?
+        } else if url.starts_with("https://") {
+            return Err(anyhow!("Endpoint {} uses https:// but has no [tls] settings", url));
+        } else if url.starts_with("tcp://") || url.starts_with("http://") {
+            Docker::connect_with_http(url, timeout, API_DEFAULT_VERSION)?
+        } else {
+            return Err(anyhow!("Unsupported Docker endpoint URL: {}", url));
+        };
+
+        Ok(client)
+    }
+
+    /// Explain why no local engine can exist, e.g. on containerd-only Kubernetes nodes
+    pub fn local_engine_missing() -> Option<String> {
+        if std::env::var_os("DOCKER_HOST").is_some() {
+            return None;
+        }
+        if std::path::Path::new(LOCAL_DOCKER_SOCKET).exists() {
+            return None;
+        }
+        Some(format!("No Docker socket at {} and DOCKER_HOST is not set", LOCAL_DOCKER_SOCKET))
+    }
+
+    async fn verify(client: Docker, host: String, local: bool, timeout_secs: u64) -> Result<Self> {
+        // Test connection; bound the wait so one dead engine cannot stall the others
+        let version = tokio::time::timeout(Duration::from_secs(timeout_secs), client.version())
+            .await
+            .map_err(|_| anyhow!("Timed out connecting to Docker on {}", host))??;
+        info!("Connected to Docker version {:?} on {}", version.version, host);
+
+        Ok(Self { client, host, local })
+    }
+
+    /// Whether the engine's filesystem is visible from this machine
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
+
+    /// Current time of the engine's host, from the `SystemTime` of `docker info`
+    pub async fn system_time(&self) -> Result<DateTime<Utc>> {
+        let info = self.client.info().await?;
+        let system_time = info.system_time.ok_or_else(|| anyhow!("Engine reported no SystemTime"))?;
+        Ok(DateTime::parse_from_rfc3339(&system_time)
+            .with_context(|| format!("Invalid SystemTime {:?}", system_time))?
+            .with_timezone(&Utc))
+    }
+
+    pub async fn collect_daemon_diagnostics(&self) -> Result<DaemonDiagnostics> {
+        use bollard::image::ListImagesOptions;
+        use bollard::volume::ListVolumesOptions;
+        use std::collections::HashMap;
+
+        let version = self.client.version().await?;
+        let info = self.client.info().await?;
+        let usage = self.client.df().await?;
+
+        let client_version = self.client.client_version();
+
+        let images = usage.images.unwrap_or_default();
+        let containers = usage.containers.unwrap_or_default();
+        let volumes = usage.volumes.unwrap_or_default();
+        let build_cache = usage.build_cache.unwrap_or_default();
+
+        let mut dangling_filter = HashMap::new();
+        dangling_filter.insert("dangling", vec!["true"]);
+
+        let dangling_images = self.client
+            .list_images(Some(ListImagesOptions {
+                filters: dangling_filter.clone(),
+                ..Default::default()
+            }))
+            .await?
+            .len();
+
+        let dangling_volumes = self.client
+            .list_volumes(Some(ListVolumesOptions { filters: dangling_filter }))
+            .await?
+            .volumes
+            .map(|v| v.len())
+            .unwrap_or(0);
+
+        let mut runtimes: Vec<String> = info.runtimes.unwrap_or_default().into_keys().collect();
+        runtimes.sort();
+
+        Ok(DaemonDiagnostics {
+            server_version: version.version.unwrap_or_else(|| "unknown".to_string()),
+            api_version: version.api_version.unwrap_or_default(),
+            min_api_version: version.min_api_version.unwrap_or_default(),
+            client_api_version: format!("{}.{}", client_version.major_version, client_version.minor_version),
+            storage_driver: info.driver.unwrap_or_else(|| "unknown".to_string()),
+            docker_root_dir: info.docker_root_dir,
+            default_runtime: info.default_runtime,
+            runtimes,
+            images: DiskUsage {
+                count: images.len(),
+                size_bytes: images.iter().map(|i| i.size.max(0) as u64).sum(),
+            },
+            containers: DiskUsage {
+                count: containers.len(),
+                size_bytes: containers.iter().map(|c| c.size_rw.unwrap_or(0).max(0) as u64).sum(),
+            },
+            volumes: DiskUsage {
+                count: volumes.len(),
+                size_bytes: volumes.iter()
+                    .filter_map(|v| v.usage_data.as_ref())
+                    .map(|u| u.size.max(0) as u64)
+                    .sum(),
+            },
+            build_cache: DiskUsage {
+                count: build_cache.len(),
+                size_bytes: build_cache.iter().map(|b| b.size.unwrap_or(0).max(0) as u64).sum(),
+            },
+            dangling_images,
+            dangling_volumes,
+            warnings: info.warnings.unwrap_or_default(),
+        })
+    }
+
+    pub async fn runtimes(&self) -> Result<DockerRuntimes> {
+        let info = self.client.info().await?;
+        let mut runtimes: Vec<String> = info.runtimes.unwrap_or_default().into_keys().collect();
+        runtimes.sort();
+        Ok(DockerRuntimes {
+            default_runtime: info.default_runtime,
+            runtimes,
+        })
+    }
+
+    /// Run `command` in a throwaway container given every GPU, the way `docker run --gpus all` does,
+    /// and return its output. The image is not pulled, so nodes without registry access fail fast.
+    pub async fn run_gpu_probe(&self, image: &str, command: &[&str], runtime: Option<&str>, timeout: Duration) -> Result<String> {
+        use bollard::container::{Config, CreateContainerOptions, StartContainerOptions, WaitContainerOptions};
+        use bollard::models::{DeviceRequest, HostConfig};
+
+        if self.client.inspect_image(image).await.is_err() {
+            return Err(anyhow!("Probe image {} is not present on {}, pull it first", image, self.host));
+        }
+
+        let name = format!("health-checker-gpu-probe-{}", std::process::id());
+        let config = Config {
+            image: Some(image.to_string()),
+            cmd: Some(command.iter().map(|arg| arg.to_string()).collect()),
+            host_config: Some(HostConfig {
+                runtime: runtime.map(str::to_string),
+                device_requests: Some(vec![DeviceRequest {
+                    driver: Some("nvidia".to_string()),
+                    count: Some(-1),
+                    capabilities: Some(vec![vec!["gpu".to_string()]]),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let container = self.client
+            .create_container(Some(CreateContainerOptions { name: name.as_str(), platform: None }), config)
+            .await?;
+
+        let outcome = tokio::time::timeout(timeout, async {
+            self.client.start_container(&container.id, None::<StartContainerOptions<String>>).await?;
+            let mut wait = self.client.wait_container(&container.id, None::<WaitContainerOptions<String>>);
+            let exit = wait.next().await;
+            let logs = self.get_container_logs(&container.id, Some("all".to_string())).await?;
+            match exit {
+                Some(Ok(_)) => Ok(logs),
+                Some(Err(e)) => Err(anyhow!("{}: {}", e, logs.trim())),
+                None => Err(anyhow!("Probe container ended without an exit status")),
+            }
+        })
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("Probe container did not finish within {}s", timeout.as_secs())));
+
+        if let Err(e) = self.remove_container(&container.id).await {
+            error!("Failed to remove probe container {}: {}", name, e);
+        }
+        outcome
     }
 
     pub async fn list_backend_ai_containers(&self) -> Result<Vec<ContainerInfo>> {
-        let mut list_options = ListContainersOptions::<String>::default();
-        list_options.all = true;
-        
+        let list_options = ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        };
+
         let containers = self.client.list_containers(Some(list_options)).await?;
         let mut backend_ai_containers = Vec::new();
 
@@ -43,8 +420,8 @@ impl DockerClient {
             let empty_image = String::new();
             let image = container.image.as_ref().unwrap_or(&empty_image);
             
-            if names.iter().any(|name| self.is_backend_ai_container(name)) ||
-               self.is_backend_ai_image(image) {
+            if names.iter().any(|name| Self::is_backend_ai_container(name)) ||
+               Self::is_backend_ai_image(image) {
                 
                 let name = names.first()
                     .map(|n| n.trim_start_matches('/').to_string())
@@ -52,19 +429,20 @@ impl DockerClient {
 
                 let ports = container.ports.as_ref().unwrap_or(&vec![])
                     .iter()
-                    .filter_map(|port| {
+                    .map(|port| {
                         match (port.private_port, port.public_port) {
                             (private_port, Some(public_port)) => {
-                                Some(format!("{}:{}", public_port, private_port))
+                                format!("{}:{}", public_port, private_port)
                             }
                             (private_port, None) => {
-                                Some(format!("{}", private_port))
+                                format!("{}", private_port)
                             }
                         }
                     })
                     .collect();
 
                 backend_ai_containers.push(ContainerInfo {
+                    host: self.host.clone(),
                     id: container.id.unwrap_or_default(),
                     name,
                     image: image.clone(),
@@ -74,7 +452,7 @@ impl DockerClient {
             }
         }
 
-        debug!("Found {} Backend.AI containers", backend_ai_containers.len());
+        debug!("Found {} Backend.AI containers on {}", backend_ai_containers.len(), self.host);
         Ok(backend_ai_containers)
     }
 
@@ -123,9 +501,252 @@ impl DockerClient {
         }
     }
 
+    /// List containers carrying the Backend.AI kernel label, running or not
+    pub async fn list_kernel_containers(&self) -> Result<Vec<KernelContainer>> {
+        use std::collections::HashMap;
+
+        let mut filters = HashMap::new();
+        filters.insert("label".to_string(), vec![KERNEL_ID_LABEL.to_string()]);
+
+        let containers = self.client.list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            filters,
+            ..Default::default()
+        })).await?;
+
+        Ok(containers.into_iter().filter_map(|container| {
+            let labels = container.labels.unwrap_or_default();
+            let id = container.id.unwrap_or_default();
+            let name = container.names.unwrap_or_default()
+                .first()
+                .map(|n| n.trim_start_matches('/').to_string())
+                .unwrap_or_else(|| id.chars().take(12).collect());
+
+            Some(KernelContainer {
+                host: self.host.clone(),
+                kernel_id: labels.get(KERNEL_ID_LABEL)?.clone(),
+                session_id: labels.get(SESSION_ID_LABEL).cloned(),
+                created: container.created
+                    .and_then(|t| Utc.timestamp_opt(t, 0).single())
+                    .unwrap_or_else(Utc::now),
+                state: container.state.unwrap_or_default(),
+                id,
+                name,
+            })
+        }).collect())
+    }
+
+    /// List every container, running or not, with its labels
+    pub async fn list_labeled_containers(&self) -> Result<Vec<LabeledContainer>> {
+        let containers = self.client.list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        })).await?;
+
+        let mut labeled_containers: Vec<LabeledContainer> = containers.into_iter().map(|container| {
+            let id = container.id.unwrap_or_default();
+            LabeledContainer {
+                name: container.names.unwrap_or_default()
+                    .first()
+                    .map(|n| n.trim_start_matches('/').to_string())
+                    .unwrap_or_else(|| id.chars().take(12).collect()),
+                state: container.state.unwrap_or_default(),
+                labels: container.labels.unwrap_or_default(),
+                gpu_devices: Vec::new(),
+                image: container.image.unwrap_or_default(),
+                published_ports: container.ports.unwrap_or_default().into_iter()
+                    .filter(|port| port.typ == Some(PortTypeEnum::TCP))
+                    .filter_map(|port| Some(PublishedPort {
+                        host_ip: port.ip.unwrap_or_else(|| "0.0.0.0".to_string()),
+                        host_port: u16::try_from(port.public_port?).ok()?,
+                        container_port: u16::try_from(port.private_port).ok()?,
+                    }))
+                    .collect(),
+                id,
+            }
+        }).collect();
+
+        // Device requests are only in the inspect output, so only running kernels are inspected
+        for container in &mut labeled_containers {
+            if container.state != "running" || !container.labels.contains_key(KERNEL_ID_LABEL) {
+                continue;
+            }
+            match self.container_gpu_devices(&container.id).await {
+                Ok(gpu_devices) => container.gpu_devices = gpu_devices,
+                Err(e) => debug!("Cannot inspect GPU devices of {}: {}", container.name, e),
+            }
+        }
+
+        Ok(labeled_containers)
+    }
+
+    async fn container_gpu_devices(&self, container_id: &str) -> Result<Vec<String>> {
+        let inspect = self.client.inspect_container(container_id, None::<InspectContainerOptions>).await?;
+
+        let mut gpu_devices = Vec::new();
+        let device_requests = inspect.host_config.and_then(|host_config| host_config.device_requests).unwrap_or_default();
+        for request in device_requests {
+            let requests_gpus = request.driver.as_deref() == Some("nvidia")
+                || request.capabilities.iter().flatten().flatten().any(|capability| capability == "gpu");
+            if !requests_gpus {
+                continue;
+            }
+            match request.device_ids {
+                Some(device_ids) if !device_ids.is_empty() => gpu_devices.extend(device_ids),
+                // `--gpus all` is a request with count -1 and no IDs
+                _ if request.count == Some(-1) => gpu_devices.push("all".to_string()),
+                _ => {}
+            }
+        }
+
+        let env = inspect.config.and_then(|config| config.env).unwrap_or_default();
+        if let Some(visible) = env.iter().find_map(|var| var.strip_prefix("NVIDIA_VISIBLE_DEVICES=")) {
+            gpu_devices.extend(
+                visible.split(',')
+                    .map(|device| device.trim().to_string())
+                    .filter(|device| !device.is_empty() && device != "none" && device != "void"),
+            );
+        }
+
+        gpu_devices.sort();
+        gpu_devices.dedup();
+        Ok(gpu_devices)
+    }
+
+    pub async fn list_krunner_volumes(&self) -> Result<Vec<KrunnerVolume>> {
+        use bollard::volume::ListVolumesOptions;
+        use std::collections::HashMap;
+
+        let mut filters = HashMap::new();
+        filters.insert("name", vec![KRUNNER_VOLUME_PREFIX]);
+        let volumes = self.client
+            .list_volumes(Some(ListVolumesOptions { filters: filters.clone() }))
+            .await?
+            .volumes
+            .unwrap_or_default();
+
+        filters.insert("dangling", vec!["true"]);
+        let unused: Vec<String> = self.client
+            .list_volumes(Some(ListVolumesOptions { filters }))
+            .await?
+            .volumes
+            .unwrap_or_default()
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+
+        // Only `system df` computes volume sizes; without it they are reported as unknown
+        let sizes = match self.client.df().await {
+            Ok(usage) => Self::volume_sizes(&usage.volumes.unwrap_or_default()),
+            Err(e) => {
+                debug!("Cannot get volume sizes on {}: {}", self.host, e);
+                HashMap::new()
+            }
+        };
+
+        Ok(volumes.into_iter()
+            // The name filter matches substrings; keep real krunner volumes only
+            .filter(|v| v.name.starts_with(KRUNNER_VOLUME_PREFIX))
+            .map(|v| KrunnerVolume {
+                in_use: !unused.contains(&v.name),
+                size_bytes: sizes.get(&v.name).copied(),
+                name: v.name,
+            })
+            .collect())
+    }
+
+    pub async fn remove_container(&self, container_id: &str) -> Result<()> {
+        use bollard::container::RemoveContainerOptions;
+
+        self.client.remove_container(container_id, Some(RemoveContainerOptions {
+            force: true,
+            ..Default::default()
+        })).await?;
+        Ok(())
+    }
+
+    pub async fn remove_volume(&self, name: &str) -> Result<()> {
+        use bollard::volume::RemoveVolumeOptions;
+
+        self.client.remove_volume(name, Some(RemoveVolumeOptions { force: false })).await?;
+        Ok(())
+    }
+
+    /// Stream lifecycle events of Backend.AI containers, starting after `since` when resuming
+    pub fn watch_container_events(&self, since: Option<DateTime<Utc>>) -> impl Stream<Item = Result<ContainerEvent>> {
+        use bollard::system::EventsOptions;
+        use std::collections::HashMap;
+
+        let mut filters = HashMap::new();
+        filters.insert("type".to_string(), vec!["container".to_string()]);
+        filters.insert(
+            "event".to_string(),
+            WATCHED_CONTAINER_EVENTS.iter().map(|e| e.to_string()).collect(),
+        );
+
+        let host = self.host.clone();
+        self.client
+            .events(Some(EventsOptions::<String> {
+                since: since.map(Self::events_resume_point),
+                filters,
+                ..Default::default()
+            }))
+            .filter_map(move |message| {
+                let event = match message {
+                    Ok(message) => Self::container_event_from_message(&host, message).map(Ok),
+                    Err(e) => Some(Err(e.into())),
+                };
+                futures::future::ready(event)
+            })
+    }
+
+    /// Volume sizes by name from `system df`; Docker reports -1 for sizes it has not computed
+    fn volume_sizes(volumes: &[bollard::models::Volume]) -> std::collections::HashMap<String, u64> {
+        volumes.iter()
+            .filter_map(|v| {
+                let size = v.usage_data.as_ref()?.size;
+                (size >= 0).then(|| (v.name.clone(), size as u64))
+            })
+            .collect()
+    }
+
+    /// `since` value for the events API right after `last`, as `seconds.nanoseconds`.
+    /// The engine includes events at `since`, so this skips the last one already seen.
+    fn events_resume_point(last: DateTime<Utc>) -> String {
+        let next = last + chrono::Duration::nanoseconds(1);
+        format!("{}.{:09}", next.timestamp(), next.timestamp_subsec_nanos())
+    }
+
+    fn container_event_from_message(host: &str, message: bollard::models::EventMessage) -> Option<ContainerEvent> {
+        let actor = message.actor?;
+        let attributes = actor.attributes.unwrap_or_default();
+        let name = attributes.get("name").cloned().unwrap_or_default();
+        let image = attributes.get("image").cloned().unwrap_or_default();
+        // Event attributes carry the container labels, which identify kernels whatever their name
+        let kernel_id = attributes.get(KERNEL_ID_LABEL).cloned();
+
+        if kernel_id.is_none() && !Self::is_backend_ai_container(&name) && !Self::is_backend_ai_image(&image) {
+            return None;
+        }
+
+        let timestamp = message.time_nano
+            .map(|nanos| Utc.timestamp_nanos(nanos))
+            .or_else(|| message.time.and_then(|t| Utc.timestamp_opt(t, 0).single()))
+            .unwrap_or_else(Utc::now);
+
+        Some(ContainerEvent {
+            host: host.to_string(),
+            container_id: actor.id.unwrap_or_default(),
+            container_name: name,
+            action: message.action.unwrap_or_default(),
+            timestamp,
+            exit_code: attributes.get("exitCode").and_then(|c| c.parse().ok()),
+            kernel_id,
+        })
+    }
+
     pub async fn get_container_logs(&self, container_id: &str, tail: Option<String>) -> Result<String> {
         use bollard::container::LogsOptions;
-        use futures::stream::StreamExt;
 
         let options = LogsOptions::<String> {
             stdout: true,
@@ -152,22 +773,7 @@ impl DockerClient {
         Ok(logs)
     }
 
-    pub async fn get_network_info(&self, network_name: &str) -> Result<Option<bollard::models::Network>> {
-        use bollard::network::InspectNetworkOptions;
-
-        match self.client.inspect_network(network_name, None::<InspectNetworkOptions<String>>).await {
-            Ok(network) => Ok(Some(network)),
-            Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => {
-                Ok(None)
-            }
-            Err(e) => {
-                error!("Failed to inspect network {}: {}", network_name, e);
-                Err(e.into())
-            }
-        }
-    }
-
-    fn is_backend_ai_container(&self, name: &str) -> bool {
+    fn is_backend_ai_container(name: &str) -> bool {
         let name_lower = name.to_lowercase();
         name_lower.contains("backend.ai") ||
         name_lower.contains("halfstack") ||
@@ -183,7 +789,7 @@ impl DockerClient {
         name_lower.contains("node-exporter")
     }
 
-    fn is_backend_ai_image(&self, image: &str) -> bool {
+    fn is_backend_ai_image(image: &str) -> bool {
         let image_lower = image.to_lowercase();
         image_lower.contains("backend.ai") ||
         image_lower.contains("postgres") && (image_lower.contains("15") || image_lower.contains("14")) ||
@@ -200,7 +806,6 @@ impl DockerClient {
 
     pub async fn get_container_stats(&self, container_id: &str) -> Result<String> {
         use bollard::container::StatsOptions;
-        use futures::stream::StreamExt;
 
         let options = StatsOptions {
             stream: false,
@@ -241,4 +846,247 @@ impl DockerClient {
             Ok("No stats available".to_string())
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DockerTlsConfig;
+
+    fn endpoint(url: &str) -> DockerEndpoint {
+        DockerEndpoint {
+            name: "test".to_string(),
+            url: url.to_string(),
+            tls: None,
+            local: None,
+            timeout_secs: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_client_accepts_supported_schemes() {
+        for url in [
+            "unix:///var/run/docker.sock",
+            "/var/run/docker.sock",
+            "tcp://10.0.0.5:2375",
+            "http://10.0.0.5:2375",
+        ] {
+            assert!(DockerClient::build_client(&endpoint(url)).is_ok(), "{} should be accepted", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_client_rejects_https_without_tls() {
+        let err = DockerClient::build_client(&endpoint("https://10.0.0.5:2376")).unwrap_err();
+        assert!(err.to_string().contains("no [tls] settings"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_build_client_uses_tls_settings() {
+        let mut https = endpoint("https://10.0.0.5:2376");
+        https.tls = Some(DockerTlsConfig {
+            ca_cert: "/nonexistent/ca.pem".into(),
+            client_cert: "/nonexistent/cert.pem".into(),
+            client_key: "/nonexistent/key.pem".into(),
+        });
+        // The TLS branch is taken and fails on the missing certificates instead of
+        // falling through to the https:// rejection
+        let err = DockerClient::build_client(&https).unwrap_err();
+        assert!(!err.to_string().contains("no [tls] settings"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_build_client_rejects_unknown_schemes() {
+        for url in ["ssh://user@10.0.0.5", "npipe:////./pipe/docker_engine", "10.0.0.5:2375"] {
+            let err = DockerClient::build_client(&endpoint(url)).unwrap_err();
+            assert!(err.to_string().contains("Unsupported Docker endpoint URL"), "{}: {}", url, err);
+        }
+    }
+
+    #[test]
+    fn test_endpoint_is_local() {
+        let cases = [
+            ("unix:///var/run/docker.sock", None, true),
+            ("/var/run/docker.sock", None, true),
+            ("unix:///tmp/forwarded.sock", Some(false), false),
+            ("tcp://10.0.0.5:2375", None, false),
+            ("http://127.0.0.1:2375", Some(true), true),
+            ("https://10.0.0.5:2376", None, false),
+        ];
+        for (url, local, expected) in cases {
+            let mut ep = endpoint(url);
+            ep.local = local;
+            assert_eq!(ep.is_local(), expected, "{} with local={:?}", url, local);
+        }
+    }
+
+    #[test]
+    fn test_hosts_default_to_local_engine() {
+        let hosts = DockerHost::from_config(&DockerConfig::default());
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].name, LOCAL_DOCKER_HOST);
+        assert!(hosts[0].endpoint.is_none());
+    }
+
+    #[test]
+    fn test_hosts_follow_configured_endpoints() {
+        let mut first = endpoint("unix:///var/run/docker.sock");
+        first.name = "gpu-node-1".to_string();
+        let mut second = endpoint("tcp://10.0.0.5:2375");
+        second.name = "gpu-node-2".to_string();
+        let config = DockerConfig {
+            endpoints: vec![first, second],
+            ..DockerConfig::default()
+        };
+
+        let names: Vec<String> = DockerHost::from_config(&config).into_iter().map(|host| host.name).collect();
+        assert_eq!(names, vec!["gpu-node-1", "gpu-node-2"]);
+    }
+
+    /// Answer every request on `path` like an engine that only implements `GET /version`
+    fn serve_fake_engine(path: &std::path::Path) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::UnixListener::bind(path).unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    while let Ok(n) = stream.read(&mut buf).await {
+                        if n == 0 {
+                            break;
+                        }
+                        let body = r#"{"Version":"24.0.7"}"#;
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        );
+                        if stream.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    fn socket_endpoint(label: &str) -> (DockerEndpoint, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("health-checker-docker-{}-{}", label, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("docker.sock");
+        (endpoint(&format!("unix://{}", path.display())), path)
+    }
+
+    #[tokio::test]
+    async fn test_host_reconnects_after_failure() {
+        let (endpoint, path) = socket_endpoint("reconnect");
+        let mut host = DockerHost::new("test".to_string(), Some(endpoint));
+        host.reconnect_interval = Duration::ZERO;
+
+        assert!(host.client().await.is_err());
+
+        serve_fake_engine(&path);
+        let client = host.client().await.expect("engine is up now");
+        assert!(client.is_local());
+        assert!(host.last_failure.lock().unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_host_waits_before_reconnecting() {
+        let (endpoint, path) = socket_endpoint("backoff");
+        let host = DockerHost::new("test".to_string(), Some(endpoint));
+
+        let first = host.client().await.err().expect("engine is down").to_string();
+
+        // The engine coming up within the reconnect interval is not noticed yet
+        serve_fake_engine(&path);
+        let second = host.client().await.err().expect("engine is down").to_string();
+        assert_eq!(first, second);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    fn event_message(action: &str, attributes: &[(&str, &str)]) -> bollard::models::EventMessage {
+        bollard::models::EventMessage {
+            action: Some(action.to_string()),
+            actor: Some(bollard::models::EventActor {
+                id: Some("3f9a1c".to_string()),
+                attributes: Some(attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()),
+            }),
+            time: Some(1_700_000_000),
+            time_nano: Some(1_700_000_000_123_456_789),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_kernel_events_identified_by_label() {
+        // Kernel names and images carry nothing Backend.AI-specific
+        let message = event_message("oom", &[
+            ("name", "kernel.python.3f9a1c"),
+            ("image", "registry.example.com/ml/python:3.11"),
+            (KERNEL_ID_LABEL, "0f6c2b8e-5d3a-4e21-9c7b-1a2b3c4d5e6f"),
+        ]);
+
+        let event = DockerClient::container_event_from_message("gpu-node-1", message).expect("kernel event");
+        assert_eq!(event.kernel_id.as_deref(), Some("0f6c2b8e-5d3a-4e21-9c7b-1a2b3c4d5e6f"));
+        assert_eq!(event.action, "oom");
+        assert_eq!(event.host, "gpu-node-1");
+        assert_eq!(event.timestamp.timestamp_subsec_nanos(), 123_456_789);
+    }
+
+    #[test]
+    fn test_infrastructure_events_identified_by_name() {
+        let message = event_message("die", &[
+            ("name", "backendai-halfstack-postgres-1"),
+            ("image", "postgres:15.1-alpine"),
+            ("exitCode", "137"),
+        ]);
+
+        let event = DockerClient::container_event_from_message("local", message).expect("halfstack event");
+        assert_eq!(event.kernel_id, None);
+        assert_eq!(event.container_name, "backendai-halfstack-postgres-1");
+        assert_eq!(event.exit_code, Some(137));
+    }
+
+    #[test]
+    fn test_volume_sizes_from_system_df() {
+        use bollard::models::{Volume, VolumeUsageData};
+
+        let volume = |name: &str, size: i64| Volume {
+            name: name.to_string(),
+            usage_data: Some(VolumeUsageData { size, ref_count: 0 }),
+            ..Default::default()
+        };
+        let sizes = DockerClient::volume_sizes(&[
+            volume("backendai-krunner.v10.ubuntu22.04", 300 * 1024 * 1024),
+            volume("backendai-krunner.v9.alpine3.8", -1),
+            Volume { name: "no-usage".to_string(), ..Default::default() },
+        ]);
+        assert_eq!(sizes.len(), 1);
+        assert_eq!(sizes["backendai-krunner.v10.ubuntu22.04"], 300 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_events_resume_point() {
+        let last = Utc.timestamp_nanos(1_700_000_000_000_000_999);
+        assert_eq!(DockerClient::events_resume_point(last), "1700000000.000001000");
+
+        let end_of_second = Utc.timestamp_nanos(1_700_000_000_999_999_999);
+        assert_eq!(DockerClient::events_resume_point(end_of_second), "1700000001.000000000");
+    }
+
+    #[test]
+    fn test_unrelated_events_ignored() {
+        let message = event_message("die", &[("name", "nginx-proxy"), ("image", "nginx:1.25")]);
+        assert!(DockerClient::container_event_from_message("local", message).is_none());
+
+        let mut no_actor = event_message("die", &[]);
+        no_actor.actor = None;
+        assert!(DockerClient::container_event_from_message("local", no_actor).is_none());
+    }
+}
@@ -0,0 +1,322 @@
+use chrono::{DateTime, Utc};
+use futures::stream::StreamExt;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::HealthCheckResult;
+
+/// Container events that indicate an outage, even a short one
+pub const WATCHED_CONTAINER_EVENTS: &[&str] = &["die", "oom", "kill", "health_status", "restart"];
+
+const MAX_EVENTS_PER_CONTAINER: usize = 50;
+const EVENT_STREAM_RETRY_DELAY: Duration = Duration::from_secs(5);
+/// How long per-container event history is kept in monitor mode, at least one check interval
+pub const EVENT_HISTORY_RETENTION: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerEvent {
+    pub host: String,
+    pub container_id: String,
+    pub container_name: String,
+    pub action: String,
+    pub timestamp: DateTime<Utc>,
+    pub exit_code: Option<i64>,
+    /// Set for session kernel containers, from the `ai.backend.kernel-id` label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel_id: Option<String>,
+}
+
+impl std::fmt::Display for ContainerEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} ({}): {}",
+            self.timestamp.format("%H:%M:%S"),
+            self.container_name,
+            self.host,
+            self.action
+        )?;
+        if let Some(exit_code) = self.exit_code {
+            write!(f, " (exit code: {})", exit_code)?;
+        }
+        Ok(())
+    }
+}
+
+/// Service checks that rely on a container, so they are re-run when it changes state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DependentService {
+    PostgreSQL,
+    Redis,
+    Etcd,
+    ManagerApi,
+    Prometheus,
+    Grafana,
+}
+
+impl DependentService {
+    pub fn for_container(container_name: &str) -> Vec<DependentService> {
+        let name = container_name.to_lowercase();
+        let mut services = Vec::new();
+
+        if name.contains("postgres") {
+            // The manager cannot serve requests without its database
+            services.push(DependentService::PostgreSQL);
+            services.push(DependentService::ManagerApi);
+        }
+        if name.contains("redis") {
+            services.push(DependentService::Redis);
+            services.push(DependentService::ManagerApi);
+        }
+        if name.contains("etcd") {
+            services.push(DependentService::Etcd);
+            services.push(DependentService::ManagerApi);
+        }
+        if name.contains("manager") && !services.contains(&DependentService::ManagerApi) {
+            services.push(DependentService::ManagerApi);
+        }
+        if name.contains("prometheus") {
+            services.push(DependentService::Prometheus);
+            services.push(DependentService::Grafana);
+        }
+        if name.contains("grafana") && !services.contains(&DependentService::Grafana) {
+            services.push(DependentService::Grafana);
+        }
+
+        services
+    }
+}
+
+/// Per-container history of recent Docker events, shared with the event watchers.
+/// Events older than the retention are dropped, and with them containers that are long gone.
+#[derive(Clone)]
+pub struct EventTracker {
+    history: Arc<Mutex<HashMap<String, VecDeque<ContainerEvent>>>>,
+    retention: chrono::Duration,
+}
+
+impl EventTracker {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            history: Arc::default(),
+            retention: chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX),
+        }
+    }
+
+    pub fn record(&self, event: ContainerEvent) {
+        let key = format!("{}/{}", event.host, event.container_id);
+        let mut history = self.history.lock().unwrap();
+        let events = history.entry(key).or_default();
+        if events.len() >= MAX_EVENTS_PER_CONTAINER {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// All recorded events since `since`, oldest first; expired events are dropped on the way
+    pub fn events_since(&self, since: DateTime<Utc>) -> Vec<ContainerEvent> {
+        let mut history = self.history.lock().unwrap();
+        let mut events: Vec<ContainerEvent> = history
+            .values()
+            .flat_map(|events| events.iter().filter(|e| e.timestamp >= since).cloned())
+            .collect();
+        events.sort_by_key(|e| e.timestamp);
+        Self::expire(&mut history, Utc::now() - self.retention);
+        events
+    }
+
+    fn expire(history: &mut HashMap<String, VecDeque<ContainerEvent>>, cutoff: DateTime<Utc>) {
+        history.retain(|_, events| {
+            while events.front().is_some_and(|e| e.timestamp < cutoff) {
+                events.pop_front();
+            }
+            !events.is_empty()
+        });
+    }
+
+    pub fn container_history(&self, host: &str, container_id: &str) -> Vec<ContainerEvent> {
+        let history = self.history.lock().unwrap();
+        history
+            .get(&format!("{}/{}", host, container_id))
+            .map(|events| events.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl crate::HealthChecker {
+    /// Subscribe to the events stream of every configured Docker engine.
+    /// Engines that are unreachable are retried until they come up.
+    /// Events are recorded in `tracker` and forwarded on the returned channel.
+    pub async fn spawn_event_watchers(&self, tracker: &EventTracker) -> mpsc::UnboundedReceiver<ContainerEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        if self.config.docker.enabled {
+            for host in &self.docker_hosts {
+                let host = Arc::clone(host);
+                let tracker = tracker.clone();
+                let sender = sender.clone();
+
+                tokio::spawn(async move {
+                    // Resume after the last event seen so nothing is lost while reconnecting
+                    let mut last_seen: Option<DateTime<Utc>> = None;
+                    loop {
+                        let client = match host.client().await {
+                            Ok(client) => client,
+                            Err(e) => {
+                                debug!("Not watching events on {} yet: {}", host.name, e);
+                                tokio::time::sleep(EVENT_STREAM_RETRY_DELAY).await;
+                                continue;
+                            }
+                        };
+                        let mut events = Box::pin(client.watch_container_events(last_seen));
+                        while let Some(event) = events.next().await {
+                            match event {
+                                Ok(event) => {
+                                    info!("Container event: {}", event);
+                                    last_seen = Some(event.timestamp);
+                                    tracker.record(event.clone());
+                                    if sender.send(event).is_err() {
+                                        return;
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!("Docker event stream error: {}", e);
+                                    break;
+                                }
+                            }
+                        }
+                        tokio::time::sleep(EVENT_STREAM_RETRY_DELAY).await;
+                    }
+                });
+            }
+        }
+
+        receiver
+    }
+
+    /// Re-check the container behind `event` and the services that depend on it
+    pub async fn recheck_after_event(&self, event: &ContainerEvent, tracker: &EventTracker) -> Vec<HealthCheckResult> {
+        let mut results = Vec::new();
+
+        if let Some(client) = self.docker_client_for(&event.host).await {
+            let start_time = Instant::now();
+            let (status, details) = client
+                .check_container_health(&event.container_id)
+                .await
+                .unwrap_or_else(|e| (crate::HealthStatus::Unknown, format!("Inspection failed: {}", e)));
+
+            let history = tracker.container_history(&event.host, &event.container_id);
+            results.push(HealthCheckResult {
+                service_name: format!("{} ({})", event.container_name, event.host),
+                status,
+                response_time_ms: start_time.elapsed().as_millis() as u64,
+                details: format!(
+                    "{} - after {} event ({} events recorded for this container)",
+                    details,
+                    event.action,
+                    history.len()
+                ),
+                timestamp: Utc::now(),
+                error_message: None,
+            });
+        }
+
+        // Kernels run user workloads, so their names say nothing about the infrastructure services
+        if event.kernel_id.is_none() {
+            for service in DependentService::for_container(&event.container_name) {
+                results.push(self.check_dependent_service(service).await);
+            }
+        }
+
+        results
+    }
+
+    async fn check_dependent_service(&self, service: DependentService) -> HealthCheckResult {
+        match service {
+            DependentService::PostgreSQL => self.check_postgresql().await,
+            DependentService::Redis => self.check_redis().await,
+            DependentService::Etcd => self.check_etcd().await,
+            DependentService::ManagerApi => self.check_manager_api().await,
+            DependentService::Prometheus => self.check_prometheus().await,
+            DependentService::Grafana => self.check_grafana().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(container_id: &str, action: &str, secs: i64) -> ContainerEvent {
+        ContainerEvent {
+            host: "local".to_string(),
+            container_id: container_id.to_string(),
+            container_name: format!("backendai-halfstack-{}", container_id),
+            action: action.to_string(),
+            timestamp: DateTime::from_timestamp(secs, 0).unwrap(),
+            exit_code: None,
+            kernel_id: None,
+        }
+    }
+
+    #[test]
+    fn test_dependent_services() {
+        assert_eq!(
+            DependentService::for_container("backendai-halfstack-postgres-1"),
+            vec![DependentService::PostgreSQL, DependentService::ManagerApi]
+        );
+        assert_eq!(
+            DependentService::for_container("backendai-manager"),
+            vec![DependentService::ManagerApi]
+        );
+        assert_eq!(
+            DependentService::for_container("backendai-prometheus"),
+            vec![DependentService::Prometheus, DependentService::Grafana]
+        );
+        assert!(DependentService::for_container("backendai-agent").is_empty());
+    }
+
+    #[test]
+    fn test_events_since_merges_containers_in_order() {
+        let tracker = EventTracker::new(EVENT_HISTORY_RETENTION);
+        tracker.record(event("redis", "die", 100));
+        tracker.record(event("postgres", "oom", 50));
+        tracker.record(event("redis", "restart", 101));
+
+        let actions: Vec<String> = tracker.events_since(DateTime::from_timestamp(60, 0).unwrap())
+            .into_iter()
+            .map(|e| format!("{}:{}", e.container_id, e.action))
+            .collect();
+        assert_eq!(actions, vec!["redis:die", "redis:restart"]);
+    }
+
+    #[test]
+    fn test_container_history_is_bounded() {
+        let tracker = EventTracker::new(EVENT_HISTORY_RETENTION);
+        for secs in 0..(MAX_EVENTS_PER_CONTAINER as i64 + 5) {
+            tracker.record(event("redis", "restart", secs));
+        }
+
+        let history = tracker.container_history("local", "redis");
+        assert_eq!(history.len(), MAX_EVENTS_PER_CONTAINER);
+        assert_eq!(history[0].timestamp.timestamp(), 5);
+    }
+
+    #[test]
+    fn test_expired_containers_are_forgotten() {
+        let tracker = EventTracker::new(EVENT_HISTORY_RETENTION);
+        let now = Utc::now().timestamp();
+        tracker.record(event("kernel-1", "die", now - 7200));
+        tracker.record(event("redis", "die", now - 7200));
+        tracker.record(event("redis", "restart", now - 60));
+
+        assert_eq!(tracker.events_since(DateTime::from_timestamp(0, 0).unwrap()).len(), 3);
+        assert!(tracker.container_history("local", "kernel-1").is_empty());
+        assert_eq!(tracker.container_history("local", "redis").len(), 1);
+        assert_eq!(tracker.history.lock().unwrap().len(), 1);
+    }
+}
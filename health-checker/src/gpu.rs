@@ -2,15 +2,50 @@ use anyhow::{anyhow, Result};
 use chrono::Utc;
 use log::{error, info};
 use serde::{Deserialize, Serialize};
-use std::time::Instant;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Output;
+use std::time::{Duration, Instant};
 
+use crate::config::{GpuConfig, GpuHealthPolicy, NvidiaSource};
 use crate::{HealthCheckResult, HealthStatus};
 
+mod dcgm;
+mod mig;
+mod nvidia_smi;
+mod policy;
+mod reliability;
+mod rocm_smi;
+mod runtime;
+mod sampling;
+mod sessions;
+mod slots;
+mod sysfs;
+mod topology;
+
+pub use sampling::GpuSampleWindow;
+pub use sessions::parse_container_id;
+
+/// Run a GPU tool, or `None` when it does not finish within `timeout`. A wedged driver blocks
+/// every call into it, so the tool is killed rather than left behind on each check.
+pub(crate) async fn tool_output(program: &str, args: &[&str], timeout: Duration) -> std::io::Result<Option<Output>> {
+    let output = tokio::process::Command::new(program).args(args).kill_on_drop(true).output();
+    match tokio::time::timeout(timeout, output).await {
+        Ok(output) => output.map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+fn unresponsive(program: &str, args: &[&str], timeout: Duration) -> String {
+    format!("{} {} did not finish within {}s, driver unresponsive", program, args.join(" "), timeout.as_secs())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpuInfo {
     pub id: u32,
     pub name: String,
     pub uuid: Option<String>,
+    pub pci_bus_id: Option<String>,
     pub driver_version: String,
     pub cuda_version: Option<String>,
     pub memory_total: u64,
@@ -18,11 +53,26 @@ pub struct GpuInfo {
     pub memory_free: u64,
     pub utilization_gpu: u32,
     pub utilization_memory: u32,
+    /// Core (edge) temperature; 0 when the GPU has no such sensor, like MI300 parts
     pub temperature: u32,
+    /// Hotspot and HBM temperatures, where the GPU has these sensors
+    #[serde(default)]
+    pub temperature_junction: Option<u32>,
+    #[serde(default)]
+    pub temperature_memory: Option<u32>,
     pub power_usage: f32,
     pub power_limit: f32,
     pub fan_speed: Option<u32>,
     pub processes: Vec<GpuProcess>,
+    #[serde(default)]
+    pub reliability: reliability::GpuReliability,
+    /// `None` on GPUs without MIG support
+    pub mig_mode: Option<mig::MigMode>,
+    #[serde(default)]
+    pub mig_devices: Vec<mig::MigDevice>,
+    /// Running kernel containers the GPU is allocated to
+    #[serde(default)]
+    pub allocations: Vec<sessions::ProcessOwner>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,8 +80,39 @@ pub struct GpuProcess {
     pub pid: u32,
     pub name: String,
     pub memory_used: u64,
+    /// MIG GPU and compute instance the process runs in
+    #[serde(default)]
+    pub gpu_instance_id: Option<u32>,
+    #[serde(default)]
+    pub compute_instance_id: Option<u32>,
+    /// Container and session, from `/proc/<pid>/cgroup` and Docker labels; `None` for host processes
+    #[serde(default)]
+    pub owner: Option<sessions::ProcessOwner>,
+    /// Why the memory counts as leaked: no container, or a stopped container or terminated session
+    #[serde(default)]
+    pub leaked: Option<String>,
 }
 
+impl GpuInfo {
+    pub fn memory_usage_percent(&self) -> f64 {
+        if self.memory_total > 0 {
+            (self.memory_used as f64 / self.memory_total as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Power limit is N/A on some boards, which the XML parser reports as 0
+    pub fn power_usage_percent(&self) -> f64 {
+        if self.power_limit > 0.0 {
+            (self.power_usage as f64 / self.power_limit as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppleGpuInfo {
     pub name: String,
@@ -43,30 +124,60 @@ pub struct AppleGpuInfo {
 }
 
 pub struct GpuMonitor {
+    config: GpuConfig,
     nvidia_available: bool,
+    rocm_available: bool,
     apple_silicon_available: bool,
+    last_xid_changes: std::sync::Mutex<dcgm::LastXidChanges>,
 }
 
 impl GpuMonitor {
-    pub fn new() -> Self {
-        let nvidia_available = Self::check_nvidia_availability();
+    pub async fn new(config: GpuConfig) -> Self {
+        let timeout = Duration::from_secs(config.timeout_secs);
+        // An unreachable exporter is reported by the check rather than hidden as "no GPUs"
+        let nvidia_available = config.nvidia_source == NvidiaSource::DcgmExporter
+            || Self::check_nvidia_availability(timeout).await;
+        let rocm_available = Self::check_rocm_availability(timeout).await;
         let apple_silicon_available = Self::check_apple_silicon_availability();
         
-        info!("GPU Monitor initialized - NVIDIA: {}, Apple Silicon: {}", 
-              nvidia_available, apple_silicon_available);
+        info!("GPU Monitor initialized - NVIDIA: {}, AMD ROCm: {}, Apple Silicon: {}", 
+              nvidia_available, rocm_available, apple_silicon_available);
         
         Self {
+            config,
             nvidia_available,
+            rocm_available,
             apple_silicon_available,
+            last_xid_changes: std::sync::Mutex::new(dcgm::LastXidChanges::default()),
         }
     }
 
-    pub async fn get_gpu_health_checks(&self) -> Result<Vec<HealthCheckResult>> {
+    pub async fn get_gpu_health_checks(
+        &self,
+        containers: Option<&sessions::ContainerDirectory>,
+        advertised_slots: Option<&slots::AdvertisedSlots>,
+        container_runtime: Option<&runtime::ContainerRuntime>,
+        mut window: Option<&mut GpuSampleWindow>,
+    ) -> Result<Vec<HealthCheckResult>> {
         let mut results = Vec::new();
+        let mut vendor_tools = HashMap::new();
+        // GPUs per vendor for the slot comparison; `None` when the vendor tool failed
+        let mut nvidia_gpu_infos = Some(Vec::new());
+        let mut amd_gpu_infos = Some(Vec::new());
 
         if self.nvidia_available {
-            match self.check_nvidia_gpus().await {
-                Ok(mut nvidia_results) => results.append(&mut nvidia_results),
+            let start_time = Instant::now();
+            match self.collect_nvidia_gpu_infos().await {
+                Ok(mut gpu_infos) => {
+                    self.attach_xid_events(&mut gpu_infos);
+                    Self::attach_process_owners(&mut gpu_infos, containers);
+                    if let Some(window) = window.as_deref_mut() {
+                        window.record(&gpu_infos);
+                    }
+                    results.append(&mut self.check_nvidia_gpus(&gpu_infos, start_time, window.as_deref()));
+                    vendor_tools.insert(sysfs::NVIDIA_VENDOR_ID, ("nvidia-smi", Self::reported_bus_ids(&gpu_infos)));
+                    nvidia_gpu_infos = Some(gpu_infos);
+                }
                 Err(e) => {
                     error!("NVIDIA GPU check failed: {}", e);
                     results.push(HealthCheckResult {
@@ -77,8 +188,69 @@ impl GpuMonitor {
                         timestamp: Utc::now(),
                         error_message: Some(e.to_string()),
                     });
+                    vendor_tools.insert(sysfs::NVIDIA_VENDOR_ID, ("nvidia-smi", sysfs::VendorToolStatus::Failed(e.to_string())));
+                    nvidia_gpu_infos = None;
                 }
             }
+        } else {
+            vendor_tools.insert(sysfs::NVIDIA_VENDOR_ID, ("nvidia-smi", sysfs::VendorToolStatus::NotFound));
+        }
+
+        if self.rocm_available {
+            let start_time = Instant::now();
+            match self.collect_amd_gpu_infos().await {
+                Ok(gpu_infos) => {
+                    if let Some(window) = window.as_deref_mut() {
+                        window.record(&gpu_infos);
+                    }
+                    results.append(&mut self.check_amd_gpus(&gpu_infos, start_time, window.as_deref()));
+                    vendor_tools.insert(sysfs::AMD_VENDOR_ID, ("rocm-smi", Self::reported_bus_ids(&gpu_infos)));
+                    amd_gpu_infos = Some(gpu_infos);
+                }
+                Err(e) => {
+                    error!("AMD GPU check failed: {}", e);
+                    results.push(HealthCheckResult {
+                        service_name: "AMD GPU".to_string(),
+                        status: HealthStatus::Unhealthy,
+                        response_time_ms: 0,
+                        details: format!("AMD check failed: {}", e),
+                        timestamp: Utc::now(),
+                        error_message: Some(e.to_string()),
+                    });
+                    vendor_tools.insert(sysfs::AMD_VENDOR_ID, ("rocm-smi", sysfs::VendorToolStatus::Failed(e.to_string())));
+                    amd_gpu_infos = None;
+                }
+            }
+        } else {
+            vendor_tools.insert(sysfs::AMD_VENDOR_ID, ("rocm-smi", sysfs::VendorToolStatus::NotFound));
+        }
+
+        let pci_gpus = match sysfs::discover_pci_gpus(&self.config.sysfs_root) {
+            Ok(pci_gpus) => pci_gpus,
+            Err(e) => {
+                info!("PCI GPU discovery unavailable: {}", e);
+                Vec::new()
+            }
+        };
+        results.append(&mut self.check_pci_gpus(&pci_gpus, &vendor_tools));
+        if let Some(result) = self.check_topology(nvidia_gpu_infos.as_deref().unwrap_or_default(), &pci_gpus).await {
+            results.push(result);
+        }
+
+        if let Some(container_runtime) = container_runtime {
+            results.push(runtime::check_container_runtime(container_runtime, nvidia_gpu_infos.as_deref()));
+        }
+
+        if let Some(advertised_slots) = advertised_slots {
+            results.push(slots::check_resource_slots(
+                advertised_slots,
+                &[("cuda", nvidia_gpu_infos.as_deref()), ("rocm", amd_gpu_infos.as_deref())],
+                self.config.shares_per_device,
+            ));
+        }
+
+        if let Some(window) = window.as_deref() {
+            results.extend(self.check_idle_allocations(nvidia_gpu_infos.as_deref(), containers.is_some(), window));
         }
 
         if self.apple_silicon_available {
@@ -112,25 +284,30 @@ impl GpuMonitor {
         Ok(results)
     }
 
-    async fn check_nvidia_gpus(&self) -> Result<Vec<HealthCheckResult>> {
-        let start_time = Instant::now();
+    fn check_nvidia_gpus(&self, gpu_infos: &[GpuInfo], start_time: Instant, window: Option<&GpuSampleWindow>) -> Vec<HealthCheckResult> {
         let mut results = Vec::new();
 
-        #[cfg(feature = "nvidia")]
-        {
-            use nvml_wrapper::Nvml;
-
-            let nvml = Nvml::init()?;
-            let device_count = nvml.device_count()?;
+        for gpu_info in gpu_infos {
+            let (status, details) = self.evaluate_sampled_gpu(gpu_info, window);
+            results.push(HealthCheckResult {
+                service_name: format!("NVIDIA GPU {} ({})", gpu_info.id, gpu_info.name),
+                status,
+                response_time_ms: start_time.elapsed().as_millis() as u64,
+                details,
+                timestamp: Utc::now(),
+                error_message: None,
+            });
 
-            for i in 0..device_count {
-                let device = nvml.device_by_index(i)?;
-                let gpu_info = self.collect_nvidia_gpu_info(&device).await?;
-                
-                let (status, details) = self.evaluate_nvidia_gpu_health(&gpu_info);
-                
+            // Backend.AI allocates each MIG device separately, so each gets its own result
+            for mig_device in &gpu_info.mig_devices {
+                let (status, details) = mig::evaluate_mig_device(mig_device, &policy::thresholds_for(&self.config.policy, &gpu_info.name));
                 results.push(HealthCheckResult {
-                    service_name: format!("NVIDIA GPU {}", i),
+                    service_name: format!(
+                        "NVIDIA GPU {} MIG {} ({})",
+                        gpu_info.id,
+                        mig_device.index,
+                        mig_device.profile.as_deref().unwrap_or("unknown profile")
+                    ),
                     status,
                     response_time_ms: start_time.elapsed().as_millis() as u64,
                     details,
@@ -138,35 +315,212 @@ impl GpuMonitor {
                     error_message: None,
                 });
             }
+
+            if let Some(result) = self.check_mig_layout(gpu_info) {
+                results.push(result);
+            }
+        }
+
+        results
+    }
+
+    /// Pending MIG mode changes and differences from the configured MIG layout.
+    /// Returns `None` for GPUs with neither a pending change nor a configured layout.
+    fn check_mig_layout(&self, gpu_info: &GpuInfo) -> Option<HealthCheckResult> {
+        let mig_mode = gpu_info.mig_mode.as_ref();
+        let expected = self.config.mig_layout.iter().find(|layout| layout.gpu == gpu_info.id);
+        let pending_change = mig_mode.is_some_and(|mode| mode.current != mode.pending);
+        if expected.is_none() && !pending_change {
+            return None;
+        }
+
+        let mut status = HealthStatus::Healthy;
+        let mut issues = Vec::new();
+
+        if pending_change {
+            issues.push("MIG mode change pending, GPU reset required".to_string());
+            status = HealthStatus::Degraded;
+        }
+
+        let actual: Option<Vec<String>> = gpu_info.mig_devices.iter().map(|device| device.profile.clone()).collect();
+        let mig_enabled = mig_mode.is_some_and(|mode| mode.current);
+        let details = match (expected, actual) {
+            (Some(_), _) if mig_mode.is_none() => {
+                status = HealthStatus::Unknown;
+                "MIG layout configured but this GPU does not support MIG".to_string()
+            }
+            (Some(_), None) => {
+                status = HealthStatus::Unknown;
+                "MIG profiles unavailable from nvidia-smi -L".to_string()
+            }
+            (Some(layout), Some(actual)) => match mig::mig_layout_mismatch(mig_enabled, &layout.profiles, &actual) {
+                Some(mismatch) => {
+                    issues.push(mismatch);
+                    if status == HealthStatus::Healthy {
+                        status = HealthStatus::Degraded;
+                    }
+                    String::new()
+                }
+                None => format!("Matches configured layout: {}", mig::describe_profiles(&actual)),
+            },
+            (None, _) => String::new(),
+        };
+
+        let details = match (details.is_empty(), issues.is_empty()) {
+            (_, true) => details,
+            (true, false) => format!("MIG layout - Issues: {}", issues.join(", ")),
+            (false, false) => format!("{} - Issues: {}", details, issues.join(", ")),
+        };
+
+        Some(HealthCheckResult {
+            service_name: format!("NVIDIA GPU {} MIG Layout", gpu_info.id),
+            status,
+            response_time_ms: 0,
+            details,
+            timestamp: Utc::now(),
+            error_message: None,
+        })
+    }
+
+    async fn collect_nvidia_gpu_infos(&self) -> Result<Vec<GpuInfo>> {
+        match self.config.nvidia_source {
+            NvidiaSource::Local => {
+                let mut gpu_infos = self.collect_nvidia_device_infos().await?;
+                if gpu_infos.iter().any(|gpu_info| !gpu_info.mig_devices.is_empty()) {
+                    self.attach_mig_profiles(&mut gpu_infos).await;
+                }
+                Ok(gpu_infos)
+            }
+            NvidiaSource::DcgmExporter => dcgm::scrape(&self.config.dcgm_exporter_url).await,
+        }
+    }
+
+    /// XID errors only show up in the kernel log, keyed by PCI address.
+    /// The log is read once per check, and only for the local source: a dcgm-exporter
+    /// may run on another machine, whose GPUs this kernel log says nothing about.
+    /// The last XID DCGM reports is held to the same window instead.
+    fn attach_xid_events(&self, gpu_infos: &mut [GpuInfo]) {
+        if self.config.nvidia_source != NvidiaSource::Local {
+            self.last_xid_changes.lock().unwrap().age_out(gpu_infos, Utc::now(), self.config.xid_window_secs);
+            return;
+        }
+
+        let log = match reliability::read_kernel_log() {
+            Ok(log) => log,
+            Err(e) => {
+                info!("Kernel log unavailable, skipping XID detection: {}", e);
+                return;
+            }
+        };
+        let xid_events = reliability::recent_xid_events(
+            reliability::parse_xid_events(&log),
+            reliability::read_uptime(),
+            self.config.xid_window_secs,
+        );
+        for gpu_info in gpu_infos {
+            if let Some(pci_bus_id) = &gpu_info.pci_bus_id {
+                gpu_info.reliability.xid_events = xid_events.iter()
+                    .filter(|event| event.is_for_device(pci_bus_id))
+                    .cloned()
+                    .collect();
+            }
+        }
+    }
+
+    /// MIG profile names and UUIDs are only printed by `nvidia-smi -L`
+    async fn attach_mig_profiles(&self, gpu_infos: &mut [GpuInfo]) {
+        let listings = match tool_output("nvidia-smi", &["-L"], self.timeout()).await {
+            Ok(Some(output)) if output.status.success() => mig::parse_mig_listing(&String::from_utf8_lossy(&output.stdout)),
+            Ok(Some(output)) => {
+                info!("nvidia-smi -L failed, MIG profiles unavailable: {}", String::from_utf8_lossy(&output.stderr));
+                return;
+            }
+            Ok(None) => {
+                info!("{}, MIG profiles unavailable", unresponsive("nvidia-smi", &["-L"], self.timeout()));
+                return;
+            }
+            Err(e) => {
+                info!("Failed to execute nvidia-smi -L, MIG profiles unavailable: {}", e);
+                return;
+            }
+        };
+
+        for gpu_info in gpu_infos {
+            for device in &mut gpu_info.mig_devices {
+                if let Some(listing) = listings.iter().find(|l| l.gpu == gpu_info.id && l.device == device.index) {
+                    device.profile = Some(listing.profile.clone());
+                    device.uuid = Some(listing.uuid.clone());
+                }
+            }
+        }
+    }
+
+    async fn collect_nvidia_device_infos(&self) -> Result<Vec<GpuInfo>> {
+        #[cfg(feature = "nvidia")]
+        {
+            use nvml_wrapper::Nvml;
+
+            let nvml = match Nvml::init() {
+                Ok(nvml) => nvml,
+                Err(e) => {
+                    log::warn!("NVML initialization failed, falling back to nvidia-smi: {}", e);
+                    return self.collect_via_nvidia_smi().await;
+                }
+            };
+            let device_count = nvml.device_count()?;
+
+            let mut gpu_infos = (0..device_count)
+                .map(|i| Self::collect_nvml_gpu_info(&nvml, &nvml.device_by_index(i)?))
+                .collect::<Result<Vec<_>>>()?;
+
+            // nvml-wrapper has no MIG API, so MIG mode and devices come from nvidia-smi
+            if let Ok(smi_infos) = self.collect_via_nvidia_smi().await {
+                for gpu_info in &mut gpu_infos {
+                    if let Some(smi_info) = smi_infos.iter().find(|smi_info| smi_info.uuid == gpu_info.uuid) {
+                        gpu_info.mig_mode = smi_info.mig_mode.clone();
+                        gpu_info.mig_devices = smi_info.mig_devices.clone();
+                    }
+                }
+            }
+
+            Ok(gpu_infos)
         }
 
         #[cfg(not(feature = "nvidia"))]
         {
             // Fallback implementation using nvidia-smi command
-            results.append(&mut self.check_nvidia_via_command().await?);
+            self.collect_via_nvidia_smi().await
         }
-
-        Ok(results)
     }
 
     #[cfg(feature = "nvidia")]
-    async fn collect_nvidia_gpu_info(&self, device: &nvml_wrapper::Device) -> Result<GpuInfo> {
-        use nvml_wrapper::enum_wrappers::device::MemoryInfo;
+    fn collect_nvml_gpu_info(nvml: &nvml_wrapper::Nvml, device: &nvml_wrapper::Device) -> Result<GpuInfo> {
+        use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+        use nvml_wrapper::enums::device::UsedGpuMemory;
 
-        let name = device.name()?;
-        let uuid = device.uuid().ok();
         let memory_info = device.memory_info()?;
         let utilization = device.utilization_rates()?;
-        let temperature = device.temperature(nvml_wrapper::enum_wrappers::device::TemperatureSensor::Gpu)?;
-        let power_usage = device.power_usage()? as f32 / 1000.0; // Convert mW to W
-        let power_limit = device.enforced_power_limit()? as f32 / 1000.0;
-        
+        let cuda_version = nvml.sys_cuda_driver_version().ok().map(|version| {
+            format!(
+                "{}.{}",
+                nvml_wrapper::cuda_driver_version_major(version),
+                nvml_wrapper::cuda_driver_version_minor(version)
+            )
+        });
+
         let processes = match device.running_compute_processes() {
             Ok(proc_info) => {
                 proc_info.into_iter().map(|p| GpuProcess {
                     pid: p.pid,
-                    name: format!("Process {}", p.pid), // Would need additional lookup for name
-                    memory_used: p.used_gpu_memory,
+                    name: nvml.sys_process_name(p.pid, 256).unwrap_or_default(),
+                    memory_used: match p.used_gpu_memory {
+                        UsedGpuMemory::Used(bytes) => bytes,
+                        UsedGpuMemory::Unavailable => 0,
+                    },
+                    gpu_instance_id: p.gpu_instance_id,
+                    compute_instance_id: p.compute_instance_id,
+                    owner: None,
+                    leaked: None,
                 }).collect()
             }
             Err(_) => Vec::new(),
@@ -174,75 +528,308 @@ impl GpuMonitor {
 
         Ok(GpuInfo {
             id: device.index()?,
-            name,
-            uuid: uuid.map(|u| u.to_string()),
-            driver_version: device.driver_version().unwrap_or_default(),
-            cuda_version: device.cuda_driver_version().ok().map(|v| format!("{}.{}", v.major, v.minor)),
+            name: device.name()?,
+            uuid: device.uuid().ok(),
+            pci_bus_id: device.pci_info().ok().map(|pci| pci.bus_id),
+            driver_version: nvml.sys_driver_version().unwrap_or_default(),
+            cuda_version,
             memory_total: memory_info.total,
             memory_used: memory_info.used,
             memory_free: memory_info.free,
             utilization_gpu: utilization.gpu,
             utilization_memory: utilization.memory,
-            temperature,
-            power_usage,
-            power_limit,
+            temperature: device.temperature(TemperatureSensor::Gpu)?,
+            temperature_junction: None,
+            temperature_memory: None,
+            power_usage: device.power_usage()? as f32 / 1000.0, // Convert mW to W
+            power_limit: device.enforced_power_limit()? as f32 / 1000.0,
             fan_speed: device.fan_speed(0).ok(),
             processes,
+            reliability: Self::collect_nvml_reliability(device),
+            mig_mode: None,
+            mig_devices: Vec::new(),
+            allocations: Vec::new(),
         })
     }
 
-    async fn check_nvidia_via_command(&self) -> Result<Vec<HealthCheckResult>> {
-        use std::process::Command;
-
-        let output = Command::new("nvidia-smi")
-            .args(&["--query-gpu=index,name,utilization.gpu,utilization.memory,memory.total,memory.used,temperature.gpu,power.draw,power.limit", "--format=csv,noheader,nounits"])
-            .output();
+    #[cfg(feature = "nvidia")]
+    fn collect_nvml_reliability(device: &nvml_wrapper::Device) -> reliability::GpuReliability {
+        use nvml_wrapper::bitmasks::device::ThrottleReasons;
+        use nvml_wrapper::enum_wrappers::device::{EccCounter, MemoryError, RetirementCause};
 
-        match output {
-            Ok(result) if result.status.success() => {
-                let output_str = String::from_utf8_lossy(&result.stdout);
-                let mut health_results = Vec::new();
+        let retired_pages: Vec<u64> = [RetirementCause::MultipleSingleBitEccErrors, RetirementCause::DoubleBitEccError]
+            .into_iter()
+            .filter_map(|cause| device.retired_pages(cause).ok())
+            .map(|pages| pages.len() as u64)
+            .collect();
 
-                for (_line_idx, line) in output_str.lines().enumerate() {
-                    if line.trim().is_empty() {
-                        continue;
-                    }
+        let throttle_reasons = device.current_throttle_reasons()
+            .map(|reasons| {
+                [
+                    (ThrottleReasons::GPU_IDLE, "gpu_idle"),
+                    (ThrottleReasons::APPLICATIONS_CLOCKS_SETTING, "applications_clocks_setting"),
+                    (ThrottleReasons::SW_POWER_CAP, "sw_power_cap"),
+                    (ThrottleReasons::HW_SLOWDOWN, "hw_slowdown"),
+                    (ThrottleReasons::SYNC_BOOST, "sync_boost"),
+                    (ThrottleReasons::SW_THERMAL_SLOWDOWN, "sw_thermal_slowdown"),
+                    (ThrottleReasons::HW_THERMAL_SLOWDOWN, "hw_thermal_slowdown"),
+                    (ThrottleReasons::HW_POWER_BRAKE_SLOWDOWN, "hw_power_brake_slowdown"),
+                    (ThrottleReasons::DISPLAY_CLOCK_SETTING, "display_clocks_setting"),
+                ]
+                .into_iter()
+                .filter(|(reason, _)| reasons.contains(*reason))
+                .map(|(_, name)| name.to_string())
+                .collect()
+            })
+            .unwrap_or_default();
 
-                    let fields: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
-                    if fields.len() >= 9 {
-                        let gpu_util: u32 = fields[2].parse().unwrap_or(0);
-                        let mem_util: u32 = fields[3].parse().unwrap_or(0);
-                        let temp: u32 = fields[6].parse().unwrap_or(0);
-                        let power_draw: f32 = fields[7].parse().unwrap_or(0.0);
-                        
-                        let (status, details) = self.evaluate_gpu_metrics(gpu_util, mem_util, temp, power_draw);
-                        
-                        health_results.push(HealthCheckResult {
-                            service_name: format!("NVIDIA GPU {} ({})", fields[0], fields[1]),
-                            status,
-                            response_time_ms: 0,
-                            details,
-                            timestamp: Utc::now(),
-                            error_message: None,
-                        });
-                    }
-                }
+        // nvml-wrapper does not expose nvmlDeviceGetRemappedRows, so row remapping is only
+        // reported by the nvidia-smi path
+        reliability::GpuReliability {
+            ecc_enabled: device.is_ecc_enabled().ok().map(|mode| mode.currently_enabled),
+            ecc_volatile_corrected: device.total_ecc_errors(MemoryError::Corrected, EccCounter::Volatile).ok(),
+            ecc_volatile_uncorrected: device.total_ecc_errors(MemoryError::Uncorrected, EccCounter::Volatile).ok(),
+            ecc_aggregate_uncorrected: device.total_ecc_errors(MemoryError::Uncorrected, EccCounter::Aggregate).ok(),
+            retired_pages: (!retired_pages.is_empty()).then(|| retired_pages.iter().sum()),
+            retired_pages_pending: device.are_pages_pending_retired().ok(),
+            throttle_reasons,
+            ..reliability::GpuReliability::default()
+        }
+    }
 
-                Ok(health_results)
+    async fn collect_via_nvidia_smi(&self) -> Result<Vec<GpuInfo>> {
+        let args = ["-q", "-x"];
+        match tool_output("nvidia-smi", &args, self.timeout()).await {
+            Ok(Some(result)) if result.status.success() => {
+                nvidia_smi::parse_gpu_infos(&String::from_utf8_lossy(&result.stdout))
             }
-            Ok(result) => {
+            Ok(Some(result)) => {
                 let error_msg = String::from_utf8_lossy(&result.stderr);
                 Err(anyhow!("nvidia-smi failed: {}", error_msg))
             }
+            Ok(None) => Err(anyhow!(unresponsive("nvidia-smi", &args, self.timeout()))),
             Err(e) => Err(anyhow!("Failed to execute nvidia-smi: {}", e)),
         }
     }
 
-    async fn check_apple_silicon_gpu(&self) -> Result<HealthCheckResult> {
-        let start_time = Instant::now();
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_secs)
+    }
+
+    fn check_amd_gpus(&self, gpu_infos: &[GpuInfo], start_time: Instant, window: Option<&GpuSampleWindow>) -> Vec<HealthCheckResult> {
+        gpu_infos.iter().map(|gpu_info| {
+            let (status, details) = self.evaluate_sampled_gpu(gpu_info, window);
+
+            HealthCheckResult {
+                service_name: format!("AMD GPU {} ({})", gpu_info.id, gpu_info.name),
+                status,
+                response_time_ms: start_time.elapsed().as_millis() as u64,
+                details,
+                timestamp: Utc::now(),
+                error_message: None,
+            }
+        }).collect()
+    }
+
+    /// Owners of GPU processes and the kernel containers each GPU is allocated to.
+    /// NVIDIA only: allocations are matched by NVIDIA device requests and `NVIDIA_VISIBLE_DEVICES`,
+    /// whose indexes would be mistaken for AMD GPUs, and rocm-smi lists no processes.
+    fn attach_process_owners(gpu_infos: &mut [GpuInfo], containers: Option<&sessions::ContainerDirectory>) {
+        for gpu_info in gpu_infos {
+            sessions::attach_owners(Path::new("/proc"), &mut gpu_info.processes, containers);
+            for mig_device in &mut gpu_info.mig_devices {
+                sessions::attach_owners(Path::new("/proc"), &mut mig_device.processes, containers);
+            }
+            gpu_info.allocations = sessions::allocated_to(gpu_info, containers);
+        }
+    }
+
+    /// NVIDIA GPUs allocated to a session that stayed below the idle utilization for longer than
+    /// `[gpu.idle] after_secs`. Allocations come from the local Docker engine, so without it the result
+    /// is Unknown. Returns `None` when nvidia-smi found no GPUs; AMD GPUs have no known allocations.
+    fn check_idle_allocations(
+        &self,
+        nvidia_gpu_infos: Option<&[GpuInfo]>,
+        docker_available: bool,
+        window: &GpuSampleWindow,
+    ) -> Option<HealthCheckResult> {
+        let gpu_infos = nvidia_gpu_infos.filter(|gpu_infos| !gpu_infos.is_empty())?;
+        let idle_after = chrono::Duration::seconds(self.config.idle.after_secs as i64);
+        let allocated = gpu_infos.iter().filter(|gpu_info| !gpu_info.allocations.is_empty()).count();
+        let idle: Vec<String> = gpu_infos.iter().filter_map(|gpu_info| {
+            let (idle_for, allocations) = window.idle_for(gpu_info)?;
+            (idle_for > idle_after).then(|| format!(
+                "GPU {} ({}) below {}% for {}, allocated to {}",
+                gpu_info.id,
+                gpu_info.name,
+                self.config.idle.utilization,
+                crate::orphans::format_duration(idle_for.num_seconds() as u64),
+                allocations.iter().map(sessions::ProcessOwner::describe).collect::<Vec<_>>().join("; ")
+            ))
+        }).collect();
+
+        let (status, details) = if !idle.is_empty() {
+            (HealthStatus::Degraded, format!("{} of {} allocated GPUs idle: {}", idle.len(), allocated, idle.join(", ")))
+        } else if !docker_available {
+            (HealthStatus::Unknown, "Cannot tell which GPUs are allocated without the local Docker engine".to_string())
+        } else {
+            (HealthStatus::Healthy, format!(
+                "{} allocated GPUs, none idle for over {}",
+                allocated,
+                crate::orphans::format_duration(self.config.idle.after_secs)
+            ))
+        };
+
+        Some(HealthCheckResult {
+            service_name: "Idle GPU Allocations".to_string(),
+            status,
+            response_time_ms: 0,
+            details,
+            timestamp: Utc::now(),
+            error_message: None,
+        })
+    }
+
+    /// NVLink, PCIe link and NIC/NUMA affinity against the configured topology.
+    /// Returns `None` on hosts with no GPUs from either nvidia-smi or the PCI bus.
+    async fn check_topology(&self, nvidia_gpu_infos: &[GpuInfo], pci_gpus: &[sysfs::PciGpu]) -> Option<HealthCheckResult> {
+        if nvidia_gpu_infos.is_empty() && pci_gpus.is_empty() {
+            return None;
+        }
+
+        let (matrix, nvlinks) = if nvidia_gpu_infos.is_empty() {
+            (None, Vec::new())
+        } else {
+            (
+                self.nvidia_smi_output(&["topo", "-m"]).await.and_then(|output| topology::parse_topo_matrix(&output)),
+                self.nvidia_smi_output(&["nvlink", "-s"]).await
+                    .map(|output| topology::parse_nvlink_status(&output))
+                    .unwrap_or_default(),
+            )
+        };
+
+        // Topology and affinity settings use nvidia-smi indexes, so label PCI devices with them
+        // GPUs handed to VMs are outside this host's topology
+        let indexed_gpus: Vec<(Option<u32>, &sysfs::PciGpu)> = pci_gpus.iter()
+            .filter(|pci_gpu| !pci_gpu.reserved_for_passthrough())
+            .map(|pci_gpu| {
+                let address = sysfs::pci_address(&pci_gpu.bus_id);
+                let index = nvidia_gpu_infos.iter()
+                    .find(|gpu_info| gpu_info.pci_bus_id.as_deref().and_then(sysfs::pci_address) == address)
+                    .map(|gpu_info| gpu_info.id);
+                (index, pci_gpu)
+            })
+            .collect();
+
+        let (status, details) = topology::evaluate_topology(matrix.as_ref(), &nvlinks, &indexed_gpus, &self.config.topology);
+        Some(HealthCheckResult {
+            service_name: "GPU Topology".to_string(),
+            status,
+            response_time_ms: 0,
+            details,
+            timestamp: Utc::now(),
+            error_message: None,
+        })
+    }
+
+    async fn nvidia_smi_output(&self, args: &[&str]) -> Option<String> {
+        match tool_output("nvidia-smi", args, self.timeout()).await {
+            Ok(Some(output)) if output.status.success() => Some(String::from_utf8_lossy(&output.stdout).to_string()),
+            Ok(Some(output)) => {
+                info!("nvidia-smi {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr));
+                None
+            }
+            Ok(None) => {
+                info!("{}", unresponsive("nvidia-smi", args, self.timeout()));
+                None
+            }
+            Err(e) => {
+                info!("Failed to execute nvidia-smi {}: {}", args.join(" "), e);
+                None
+            }
+        }
+    }
 
+    fn reported_bus_ids(gpu_infos: &[GpuInfo]) -> sysfs::VendorToolStatus {
+        sysfs::VendorToolStatus::Reported(gpu_infos.iter().filter_map(|gpu_info| gpu_info.pci_bus_id.clone()).collect())
+    }
+
+    /// GPUs on the PCI bus that have no usable driver or are missing from their vendor tool,
+    /// so a broken driver is not mistaken for a host without GPUs
+    fn check_pci_gpus(
+        &self,
+        pci_gpus: &[sysfs::PciGpu],
+        vendor_tools: &HashMap<u16, (&str, sysfs::VendorToolStatus)>,
+    ) -> Vec<HealthCheckResult> {
+        let mut results: Vec<HealthCheckResult> = pci_gpus.iter()
+            .filter_map(|gpu| {
+                let (status, details) = sysfs::evaluate_pci_gpu(gpu, vendor_tools)?;
+                Some(HealthCheckResult {
+                    service_name: format!("GPU {} ({} {:04x}:{:04x})", gpu.bus_id, gpu.vendor_name(), gpu.vendor_id, gpu.device_id),
+                    status,
+                    response_time_ms: 0,
+                    details,
+                    timestamp: Utc::now(),
+                    error_message: None,
+                })
+            })
+            .collect();
+
+        if !pci_gpus.is_empty() {
+            let unusable = results.iter().filter(|result| result.status != HealthStatus::Healthy).count();
+            let passthrough = pci_gpus.iter().filter(|gpu| gpu.reserved_for_passthrough()).count();
+            let mut details = if unusable > 0 {
+                format!("{} GPUs on the PCI bus, {} present but unusable", pci_gpus.len(), unusable)
+            } else if passthrough > 0 {
+                format!("{} GPUs on the PCI bus, {} usable", pci_gpus.len(), pci_gpus.len() - passthrough)
+            } else {
+                format!("{} GPUs on the PCI bus, all usable", pci_gpus.len())
+            };
+            if passthrough > 0 {
+                details.push_str(&format!(", {} reserved for passthrough", passthrough));
+            }
+            results.insert(0, HealthCheckResult {
+                service_name: "GPU Discovery".to_string(),
+                status: if unusable > 0 { HealthStatus::Unhealthy } else { HealthStatus::Healthy },
+                response_time_ms: 0,
+                details,
+                timestamp: Utc::now(),
+                error_message: None,
+            });
+        }
+
+        results
+    }
+
+    async fn collect_amd_gpu_infos(&self) -> Result<Vec<GpuInfo>> {
+        let mut gpu_infos = match tool_output("rocm-smi", rocm_smi::ROCM_SMI_ARGS, self.timeout()).await {
+            Ok(Some(result)) if result.status.success() => {
+                rocm_smi::parse_gpu_infos(&String::from_utf8_lossy(&result.stdout))?
+            }
+            Ok(Some(result)) => {
+                let error_msg = String::from_utf8_lossy(&result.stderr);
+                return Err(anyhow!("rocm-smi failed: {}", error_msg));
+            }
+            Ok(None) => return Err(anyhow!(unresponsive("rocm-smi", rocm_smi::ROCM_SMI_ARGS, self.timeout()))),
+            Err(e) => return Err(anyhow!("Failed to execute rocm-smi: {}", e)),
+        };
+
+        // rocm-smi's RAS output differs between releases; the amdgpu sysfs counters do not
+        for gpu_info in &mut gpu_infos {
+            if let Some(pci_bus_id) = &gpu_info.pci_bus_id {
+                let device_dir = self.config.sysfs_root.join("bus/pci/devices").join(pci_bus_id.to_lowercase());
+                gpu_info.reliability = rocm_smi::read_ras_reliability(&device_dir);
+            }
+        }
+
+        Ok(gpu_infos)
+    }
+
+    async fn check_apple_silicon_gpu(&self) -> Result<HealthCheckResult> {
         #[cfg(target_os = "macos")]
         {
+            let start_time = Instant::now();
+
             match self.get_apple_gpu_metrics().await {
                 Ok(gpu_info) => {
                     let (status, details) = self.evaluate_apple_gpu_health(&gpu_info);
@@ -289,7 +876,7 @@ impl GpuMonitor {
 
         // Use powermetrics to get GPU information
         let output = Command::new("powermetrics")
-            .args(&["-n", "1", "-s", "gpu_power", "--format", "plist"])
+            .args(["-n", "1", "-s", "gpu_power", "--format", "plist"])
             .output()?;
 
         if !output.status.success() {
@@ -308,46 +895,103 @@ impl GpuMonitor {
         })
     }
 
-    fn evaluate_nvidia_gpu_health(&self, gpu_info: &GpuInfo) -> (HealthStatus, String) {
+    /// Evaluate thresholds against the GPU's samples in `window`, or against this sample alone
+    /// when the window holds fewer than two samples of it
+    fn evaluate_sampled_gpu(&self, gpu_info: &GpuInfo, window: Option<&GpuSampleWindow>) -> (HealthStatus, String) {
+        let thresholds = policy::thresholds_for(&self.config.policy, &gpu_info.name);
+        let sustain = std::time::Duration::from_secs(self.config.sampling.sustain_secs);
+        match window.and_then(|window| window.evaluate(gpu_info, &thresholds, sustain)) {
+            Some((status, issues)) => Self::finish_gpu_evaluation(gpu_info, status, issues),
+            None => Self::evaluate_gpu_health(&self.config.policy, gpu_info),
+        }
+    }
+
+    /// Evaluate a GPU against the thresholds `policy` sets for its model, plus reliability and leaked memory
+    fn evaluate_gpu_health(policy: &GpuHealthPolicy, gpu_info: &GpuInfo) -> (HealthStatus, String) {
         let mut issues = Vec::new();
         let mut status = HealthStatus::Healthy;
+        let thresholds = policy::thresholds_for(policy, &gpu_info.name);
 
-        // Temperature check
-        if gpu_info.temperature > 85 {
-            issues.push(format!("High temperature: {}°C", gpu_info.temperature));
-            status = HealthStatus::Degraded;
-        } else if gpu_info.temperature > 95 {
-            issues.push(format!("Critical temperature: {}°C", gpu_info.temperature));
-            status = HealthStatus::Unhealthy;
+        match policy::severity(gpu_info.temperature as f64, &thresholds.temperature) {
+            Some(HealthStatus::Unhealthy) => {
+                issues.push(format!("Critical temperature: {}°C", gpu_info.temperature));
+                status = HealthStatus::Unhealthy;
+            }
+            Some(severity) => {
+                issues.push(format!("High temperature: {}°C", gpu_info.temperature));
+                policy::escalate(&mut status, severity);
+            }
+            None => {}
         }
 
-        // Memory usage check
-        let memory_usage_percent = (gpu_info.memory_used as f64 / gpu_info.memory_total as f64) * 100.0;
-        if memory_usage_percent > 90.0 {
-            issues.push(format!("High memory usage: {:.1}%", memory_usage_percent));
-            if status == HealthStatus::Healthy {
-                status = HealthStatus::Degraded;
+        if let Some(junction) = gpu_info.temperature_junction {
+            if let Some(severity) = policy::severity(junction as f64, &thresholds.junction_temperature) {
+                let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+                issues.push(format!("{} junction temperature: {}°C", level, junction));
+                policy::escalate(&mut status, severity);
             }
         }
 
-        // Power usage check
-        let power_usage_percent = (gpu_info.power_usage / gpu_info.power_limit) * 100.0;
-        if power_usage_percent > 95.0 {
-            issues.push(format!("High power usage: {:.1}W ({:.1}%)", gpu_info.power_usage, power_usage_percent));
+        let memory_usage_percent = gpu_info.memory_usage_percent();
+        if let Some(severity) = policy::severity(memory_usage_percent, &thresholds.memory) {
+            let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+            issues.push(format!("{} memory usage: {:.1}%", level, memory_usage_percent));
+            policy::escalate(&mut status, severity);
+        }
+
+        let power_usage_percent = gpu_info.power_usage_percent();
+        if let Some(severity) = policy::severity(power_usage_percent, &thresholds.power) {
+            let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+            issues.push(format!("{} power usage: {:.1}W ({:.1}%)", level, gpu_info.power_usage, power_usage_percent));
+            policy::escalate(&mut status, severity);
+        }
+
+        if let Some(severity) = policy::severity(gpu_info.utilization_gpu as f64, &thresholds.utilization) {
+            let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+            issues.push(format!("{} utilization: {}%", level, gpu_info.utilization_gpu));
+            policy::escalate(&mut status, severity);
+        }
+
+        Self::finish_gpu_evaluation(gpu_info, status, issues)
+    }
+
+    /// Add leaked memory and reliability issues, which depend on the latest sample only,
+    /// and format the details
+    fn finish_gpu_evaluation(
+        gpu_info: &GpuInfo,
+        mut status: HealthStatus,
+        mut issues: Vec<String>,
+    ) -> (HealthStatus, String) {
+        if let Some(issue) = sessions::leaked_memory_issue(&gpu_info.processes) {
+            issues.push(issue);
             if status == HealthStatus::Healthy {
                 status = HealthStatus::Degraded;
             }
         }
 
+        let (reliability_status, reliability_issues) = reliability::evaluate_reliability(&gpu_info.reliability);
+        issues.extend(reliability_issues);
+        if reliability_status == HealthStatus::Unhealthy {
+            status = HealthStatus::Unhealthy;
+        } else if reliability_status == HealthStatus::Degraded && status == HealthStatus::Healthy {
+            status = HealthStatus::Degraded;
+        }
+
+        let temperature = match (gpu_info.temperature, gpu_info.temperature_junction) {
+            (0, Some(junction)) => format!("junction {}°C", junction),
+            (edge, Some(junction)) => format!("{}°C (junction {}°C)", edge, junction),
+            (edge, None) => format!("{}°C", edge),
+        };
+
         let details = if issues.is_empty() {
             format!(
-                "{} - GPU: {}%, Mem: {:.1}% ({}/{}MB), Temp: {}°C, Power: {:.1}W",
+                "{} - GPU: {}%, Mem: {:.1}% ({}/{}MB), Temp: {}, Power: {:.1}W",
                 gpu_info.name,
                 gpu_info.utilization_gpu,
-                memory_usage_percent,
+                gpu_info.memory_usage_percent(),
                 gpu_info.memory_used / 1024 / 1024,
                 gpu_info.memory_total / 1024 / 1024,
-                gpu_info.temperature,
+                temperature,
                 gpu_info.power_usage
             )
         } else {
@@ -357,20 +1001,28 @@ impl GpuMonitor {
         (status, details)
     }
 
+    #[cfg(target_os = "macos")]
     fn evaluate_apple_gpu_health(&self, gpu_info: &AppleGpuInfo) -> (HealthStatus, String) {
         let mut status = HealthStatus::Healthy;
         let mut issues = Vec::new();
+        let thresholds = policy::thresholds_for(&self.config.policy, &gpu_info.name);
 
-        if gpu_info.temp_gpu > 80.0 {
-            issues.push(format!("High GPU temperature: {:.1}°C", gpu_info.temp_gpu));
-            status = HealthStatus::Degraded;
+        if let Some(severity) = policy::severity(gpu_info.temp_gpu as f64, &thresholds.temperature) {
+            let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+            issues.push(format!("{} GPU temperature: {:.1}°C", level, gpu_info.temp_gpu));
+            policy::escalate(&mut status, severity);
         }
 
-        if gpu_info.memory_pressure > 0.8 {
-            issues.push(format!("High memory pressure: {:.1}%", gpu_info.memory_pressure * 100.0));
-            if status == HealthStatus::Healthy {
-                status = HealthStatus::Degraded;
-            }
+        if let Some(severity) = policy::severity(gpu_info.memory_pressure as f64 * 100.0, &thresholds.memory) {
+            let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+            issues.push(format!("{} memory pressure: {:.1}%", level, gpu_info.memory_pressure * 100.0));
+            policy::escalate(&mut status, severity);
+        }
+
+        if let Some(severity) = policy::severity(gpu_info.utilization as f64, &thresholds.utilization) {
+            let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+            issues.push(format!("{} utilization: {:.1}%", level, gpu_info.utilization));
+            policy::escalate(&mut status, severity);
         }
 
         let details = if issues.is_empty() {
@@ -389,37 +1041,20 @@ impl GpuMonitor {
         (status, details)
     }
 
-    fn evaluate_gpu_metrics(&self, gpu_util: u32, mem_util: u32, temp: u32, power_draw: f32) -> (HealthStatus, String) {
-        let mut status = HealthStatus::Healthy;
-        let mut issues = Vec::new();
-
-        if temp > 85 {
-            issues.push(format!("High temperature: {}°C", temp));
-            status = HealthStatus::Degraded;
-        }
-
-        if mem_util > 90 {
-            issues.push(format!("High memory utilization: {}%", mem_util));
-            if status == HealthStatus::Healthy {
-                status = HealthStatus::Degraded;
-            }
+    async fn check_nvidia_availability(timeout: Duration) -> bool {
+        match tool_output("nvidia-smi", &["--version"], timeout).await {
+            Ok(Some(output)) => output.status.success(),
+            // Installed but hanging: the check reports the unresponsive driver
+            Ok(None) => true,
+            Err(_) => false,
         }
-
-        let details = if issues.is_empty() {
-            format!("GPU: {}%, Memory: {}%, Temp: {}°C, Power: {:.1}W", gpu_util, mem_util, temp, power_draw)
-        } else {
-            format!("GPU: {}%, Memory: {}%, Temp: {}°C, Power: {:.1}W - Issues: {}", 
-                   gpu_util, mem_util, temp, power_draw, issues.join(", "))
-        };
-
-        (status, details)
     }
 
-    fn check_nvidia_availability() -> bool {
-        use std::process::Command;
-
-        match Command::new("nvidia-smi").arg("--version").output() {
-            Ok(output) => output.status.success(),
+    async fn check_rocm_availability(timeout: Duration) -> bool {
+        match tool_output("rocm-smi", &["--showdriverversion"], timeout).await {
+            Ok(Some(output)) => output.status.success(),
+            // Installed but hanging: the check reports the unresponsive driver
+            Ok(None) => true,
             Err(_) => false,
         }
     }
@@ -430,7 +1065,7 @@ impl GpuMonitor {
             use std::process::Command;
             
             // Check if we're on Apple Silicon by looking for Metal support
-            match Command::new("system_profiler").args(&["SPDisplaysDataType"]).output() {
+            match Command::new("system_profiler").args(["SPDisplaysDataType"]).output() {
                 Ok(output) => {
                     let output_str = String::from_utf8_lossy(&output.stdout);
                     output_str.contains("Apple") && (output_str.contains("M1") || output_str.contains("M2") || output_str.contains("M3") || output_str.contains("M4"))
@@ -445,37 +1080,204 @@ impl GpuMonitor {
         }
     }
 
-    pub async fn get_detailed_gpu_info(&self) -> Result<Vec<GpuInfo>> {
-        let gpu_infos = Vec::new();
+    pub async fn get_detailed_gpu_info(&self, containers: Option<&sessions::ContainerDirectory>) -> Result<Vec<GpuInfo>> {
+        let mut gpu_infos = self.sample_gpu_infos(containers).await?;
+        self.attach_xid_events(&mut gpu_infos);
+        Ok(gpu_infos)
+    }
+
+    /// Metrics of every GPU without the kernel log, for repeated samples
+    pub async fn sample_gpu_infos(&self, containers: Option<&sessions::ContainerDirectory>) -> Result<Vec<GpuInfo>> {
+        let mut gpu_infos = Vec::new();
 
         if self.nvidia_available {
-            #[cfg(feature = "nvidia")]
-            {
-                use nvml_wrapper::Nvml;
-                
-                let nvml = Nvml::init()?;
-                let device_count = nvml.device_count()?;
-
-                for i in 0..device_count {
-                    let device = nvml.device_by_index(i)?;
-                    let gpu_info = self.collect_nvidia_gpu_info(&device).await?;
-                    gpu_infos.push(gpu_info);
-                }
-            }
+            gpu_infos.extend(self.collect_nvidia_gpu_infos().await?);
+            Self::attach_process_owners(&mut gpu_infos, containers);
+        }
+        if self.rocm_available {
+            gpu_infos.extend(self.collect_amd_gpu_infos().await?);
         }
 
         Ok(gpu_infos)
     }
 
+    /// GPUs found on the PCI bus, regardless of driver state
+    pub fn get_pci_gpus(&self) -> Result<Vec<sysfs::PciGpu>> {
+        sysfs::discover_pci_gpus(&self.config.sysfs_root)
+    }
+
+    pub fn nvidia_available(&self) -> bool {
+        self.nvidia_available
+    }
+
     pub fn get_gpu_summary(&self) -> String {
-        if self.nvidia_available && self.apple_silicon_available {
-            "NVIDIA and Apple Silicon GPUs available".to_string()
-        } else if self.nvidia_available {
-            "NVIDIA GPUs available".to_string()
-        } else if self.apple_silicon_available {
-            "Apple Silicon GPU available".to_string()
-        } else {
-            "No supported GPU hardware detected".to_string()
+        let platforms: Vec<&str> = [
+            (self.nvidia_available, "NVIDIA"),
+            (self.rocm_available, "AMD ROCm"),
+            (self.apple_silicon_available, "Apple Silicon"),
+        ]
+        .into_iter()
+        .filter(|(available, _)| *available)
+        .map(|(_, platform)| platform)
+        .collect();
+
+        match platforms.as_slice() {
+            [] => "No supported GPU hardware detected".to_string(),
+            ["Apple Silicon"] => "Apple Silicon GPU available".to_string(),
+            [platform] => format!("{} GPUs available", platform),
+            [rest @ .., last] => format!("{} and {} GPUs available", rest.join(", "), last),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GpuPolicyOverride, Threshold};
+
+    const A100_DRIVER_535: &str = include_str!("../tests/fixtures/nvidia-smi/a100-driver-535.xml");
+
+    /// A healthy A100 with the given temperature, memory and power usage in percent
+    fn gpu(temperature: u32, memory_percent: f64, power_percent: f32) -> GpuInfo {
+        let mut gpu_info = nvidia_smi::parse_gpu_infos(A100_DRIVER_535).unwrap().remove(0);
+        gpu_info.temperature = temperature;
+        gpu_info.memory_total = 1000 * 1024 * 1024;
+        gpu_info.memory_used = (memory_percent * 10.0 * 1024.0 * 1024.0) as u64;
+        gpu_info.power_limit = 400.0;
+        gpu_info.power_usage = power_percent * 4.0;
+        gpu_info.utilization_gpu = 50;
+        gpu_info.processes.clear();
+        gpu_info
+    }
+
+    fn evaluate(gpu_info: &GpuInfo) -> (HealthStatus, String) {
+        GpuMonitor::evaluate_gpu_health(&GpuHealthPolicy::default(), gpu_info)
+    }
+
+    #[test]
+    fn temperature_boundaries() {
+        assert_eq!(evaluate(&gpu(85, 50.0, 50.0)).0, HealthStatus::Healthy);
+        assert_eq!(evaluate(&gpu(86, 50.0, 50.0)).0, HealthStatus::Degraded);
+        assert_eq!(evaluate(&gpu(95, 50.0, 50.0)).0, HealthStatus::Degraded);
+
+        let (status, details) = evaluate(&gpu(96, 50.0, 50.0));
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Critical temperature: 96°C"));
+    }
+
+    #[test]
+    fn memory_and_power_boundaries() {
+        assert_eq!(evaluate(&gpu(40, 90.0, 95.0)).0, HealthStatus::Healthy);
+
+        let (status, details) = evaluate(&gpu(40, 90.5, 50.0));
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: High memory usage: 90.5%"));
+
+        let (status, details) = evaluate(&gpu(40, 50.0, 95.5));
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: High power usage: 382.0W (95.5%)"));
+    }
+
+    #[test]
+    fn critical_metric_is_not_masked_by_a_degraded_one() {
+        let (status, details) = evaluate(&gpu(97, 95.0, 99.0));
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.contains("Critical temperature: 97°C, High memory usage: 95.0%, High power usage"));
+    }
+
+    #[test]
+    fn model_overrides_apply_by_name() {
+        let policy = GpuHealthPolicy {
+            memory: Threshold { degraded: Some(90.0), unhealthy: Some(98.0) },
+            utilization: Threshold { degraded: None, unhealthy: Some(99.0) },
+            overrides: vec![GpuPolicyOverride {
+                name: "*A100*".to_string(),
+                temperature: Some(Threshold { degraded: Some(75.0), unhealthy: Some(80.0) }),
+                junction_temperature: None,
+                memory: None,
+                power: None,
+                utilization: None,
+            }],
+            ..GpuHealthPolicy::default()
+        };
+
+        let (status, details) = GpuMonitor::evaluate_gpu_health(&policy, &gpu(81, 50.0, 50.0));
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Critical temperature: 81°C"));
+
+        let (status, details) = GpuMonitor::evaluate_gpu_health(&policy, &gpu(40, 98.5, 50.0));
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert!(details.ends_with("Issues: Critical memory usage: 98.5%"));
+
+        let mut busy = gpu(40, 50.0, 50.0);
+        busy.utilization_gpu = 100;
+        assert_eq!(GpuMonitor::evaluate_gpu_health(&policy, &busy).0, HealthStatus::Unhealthy);
+
+        let mut other = gpu(81, 50.0, 50.0);
+        other.name = "NVIDIA H100 80GB HBM3".to_string();
+        assert_eq!(GpuMonitor::evaluate_gpu_health(&policy, &other).0, HealthStatus::Healthy);
+    }
+
+    fn monitor() -> GpuMonitor {
+        GpuMonitor {
+            config: GpuConfig::default(),
+            nvidia_available: false,
+            rocm_available: false,
+            apple_silicon_available: false,
+            last_xid_changes: std::sync::Mutex::new(dcgm::LastXidChanges::default()),
+        }
+    }
+
+    #[test]
+    fn idle_allocations_need_nvidia_gpus() {
+        let monitor = monitor();
+        let window = GpuSampleWindow::new(std::time::Duration::from_secs(300), 5.0);
+
+        // Hosts with no NVIDIA GPUs, including AMD-only hosts, get no idle result
+        assert!(monitor.check_idle_allocations(None, true, &window).is_none());
+        assert!(monitor.check_idle_allocations(Some(&[]), true, &window).is_none());
+
+        let mut allocated = gpu(40, 50.0, 50.0);
+        allocated.allocations = vec![sessions::ProcessOwner {
+            container_id: "a".repeat(64),
+            container_name: Some("kernel.a".to_string()),
+            kernel_id: Some("0f6c2b8e-5d3a-4e21-9c7b-1a2b3c4d5e6f".to_string()),
+            session_id: None,
+            access_key: None,
+        }];
+        let result = monitor.check_idle_allocations(Some(std::slice::from_ref(&allocated)), true, &window).unwrap();
+        assert_eq!(result.status, HealthStatus::Healthy);
+        assert_eq!(result.details, "1 allocated GPUs, none idle for over 1h0m");
+
+        let result = monitor.check_idle_allocations(Some(std::slice::from_ref(&allocated)), false, &window).unwrap();
+        assert_eq!(result.status, HealthStatus::Unknown);
+    }
+
+    #[tokio::test]
+    async fn hung_gpu_tools_are_killed() {
+        let start = Instant::now();
+        let output = tool_output("sleep", &["10"], Duration::from_millis(100)).await.unwrap();
+        assert!(output.is_none());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            unresponsive("nvidia-smi", &["-q", "-x"], Duration::from_secs(30)),
+            "nvidia-smi -q -x did not finish within 30s, driver unresponsive"
+        );
+        assert!(tool_output("health-checker-missing-tool", &[], Duration::from_secs(5)).await.is_err());
+    }
+
+    #[test]
+    fn passthrough_gpus_do_not_make_discovery_unhealthy() {
+        let pci_gpus = sysfs::discover_pci_gpus(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysfs")).unwrap();
+        let usable: Vec<sysfs::PciGpu> = pci_gpus.into_iter().filter(|gpu| gpu.driver.is_some()).collect();
+        let vendor_tools = HashMap::from([
+            (sysfs::NVIDIA_VENDOR_ID, ("nvidia-smi", sysfs::VendorToolStatus::Reported(vec!["00000000:07:00.0".to_string()]))),
+            (sysfs::AMD_VENDOR_ID, ("rocm-smi", sysfs::VendorToolStatus::Reported(vec!["0000:83:00.0".to_string()]))),
+        ]);
+
+        let results = monitor().check_pci_gpus(&usable, &vendor_tools);
+        assert_eq!(results[0].service_name, "GPU Discovery");
+        assert_eq!(results[0].status, HealthStatus::Healthy);
+        assert_eq!(results[0].details, "3 GPUs on the PCI bus, 2 usable, 1 reserved for passthrough");
+    }
+}
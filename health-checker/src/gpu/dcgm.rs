@@ -0,0 +1,329 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use super::mig::{MigDevice, MigMode};
+use super::reliability::GpuReliability;
+use super::GpuInfo;
+use crate::prometheus::{parse_exposition, Sample};
+
+const MIB: u64 = 1024 * 1024;
+
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bits of `DCGM_FI_DEV_CLOCK_THROTTLE_REASONS`, named like the nvidia-smi throttle reasons
+const THROTTLE_REASON_BITS: &[(u64, &str)] = &[
+    (0x1, "gpu_idle"),
+    (0x2, "applications_clocks_setting"),
+    (0x4, "sw_power_cap"),
+    (0x8, "hw_slowdown"),
+    (0x10, "sync_boost"),
+    (0x20, "sw_thermal_slowdown"),
+    (0x40, "hw_thermal_slowdown"),
+    (0x80, "hw_power_brake_slowdown"),
+    (0x100, "display_clocks_setting"),
+];
+
+/// Build one `GpuInfo` per `gpu` label from the `DCGM_FI_*` series of dcgm-exporter.
+/// On MIG-enabled GPUs dcgm-exporter reports per GPU instance, labelled `GPU_I_ID`.
+pub fn parse_gpu_infos(text: &str) -> Result<Vec<GpuInfo>> {
+    let mut series_by_gpu: BTreeMap<u32, Vec<Sample>> = BTreeMap::new();
+    for sample in parse_exposition(text).into_iter().filter(|sample| sample.name.starts_with("DCGM_FI_")) {
+        let Some(gpu) = sample.label("gpu").and_then(|gpu| gpu.parse().ok()) else {
+            continue;
+        };
+        series_by_gpu.entry(gpu).or_default().push(sample);
+    }
+
+    if series_by_gpu.is_empty() {
+        return Err(anyhow!("No DCGM_FI_* series with a gpu label in dcgm-exporter metrics"));
+    }
+
+    Ok(series_by_gpu.into_iter().map(|(id, series)| gpu_info(id, &series)).collect())
+}
+
+fn gpu_info(id: u32, series: &[Sample]) -> GpuInfo {
+    let label = |name: &str| series.first().and_then(|sample| sample.label(name)).map(str::to_string);
+    let series = GpuSeries(series);
+
+    let memory_used = series.total("DCGM_FI_DEV_FB_USED").unwrap_or(0.0) as u64 * MIB;
+    let memory_free = series.total("DCGM_FI_DEV_FB_FREE").unwrap_or(0.0) as u64 * MIB;
+    let memory_reserved = series.total("DCGM_FI_DEV_FB_RESERVED").unwrap_or(0.0) as u64 * MIB;
+
+    let mig_devices: Vec<MigDevice> = series.instances().iter().enumerate().map(|(index, (gpu_instance_id, samples))| {
+        let value = |name: &str| samples.iter().find(|sample| sample.name == name).map(|sample| sample.value);
+        let memory_used = value("DCGM_FI_DEV_FB_USED").unwrap_or(0.0) as u64 * MIB;
+        let memory_free = value("DCGM_FI_DEV_FB_FREE").unwrap_or(0.0) as u64 * MIB;
+        MigDevice {
+            index: index as u32,
+            gpu_instance_id: *gpu_instance_id,
+            // dcgm-exporter reports per GPU instance, not per compute instance
+            compute_instance_id: 0,
+            profile: samples.first().and_then(|sample| sample.label("GPU_I_PROFILE")).map(str::to_string),
+            uuid: None,
+            multiprocessor_count: None,
+            memory_total: memory_used + memory_free,
+            memory_used,
+            memory_free,
+            // Profiling metrics are the only per-instance activity DCGM has
+            utilization_gpu: value("DCGM_FI_PROF_GR_ENGINE_ACTIVE").map(|ratio| (ratio * 100.0).round() as u32),
+            ecc_volatile_uncorrected: None,
+            processes: Vec::new(),
+        }
+    }).collect();
+
+    let count = |name: &str| series.value(name).map(|value| value as u64);
+    let flag = |name: &str| series.value(name).map(|value| value > 0.0);
+    let retired_pages: Vec<u64> = ["DCGM_FI_DEV_RETIRED_SBE", "DCGM_FI_DEV_RETIRED_DBE"].iter().filter_map(|name| count(name)).collect();
+    let throttle_reasons = series.value("DCGM_FI_DEV_CLOCK_THROTTLE_REASONS")
+        .or_else(|| series.value("DCGM_FI_DEV_CLOCKS_EVENT_REASONS"))
+        .map(|mask| {
+            THROTTLE_REASON_BITS.iter()
+                .filter(|(bit, _)| mask as u64 & bit != 0)
+                .map(|(_, name)| name.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    GpuInfo {
+        id,
+        name: label("modelName").unwrap_or_else(|| format!("NVIDIA GPU {}", id)),
+        uuid: label("UUID"),
+        // Only dcgm-exporter 3.3 and later label the PCI address
+        pci_bus_id: label("pci_bus_id"),
+        driver_version: label("DCGM_FI_DRIVER_VERSION").unwrap_or_default(),
+        cuda_version: None,
+        memory_total: memory_used + memory_free + memory_reserved,
+        memory_used,
+        memory_free,
+        utilization_gpu: series.value("DCGM_FI_DEV_GPU_UTIL").unwrap_or(0.0) as u32,
+        utilization_memory: series.value("DCGM_FI_DEV_MEM_COPY_UTIL").unwrap_or(0.0) as u32,
+        temperature: series.value("DCGM_FI_DEV_GPU_TEMP").unwrap_or(0.0) as u32,
+        temperature_junction: None,
+        temperature_memory: series.value("DCGM_FI_DEV_MEMORY_TEMP").map(|temp| temp as u32),
+        power_usage: series.value("DCGM_FI_DEV_POWER_USAGE").unwrap_or(0.0) as f32,
+        // Not among the default counters; without it power is not checked
+        power_limit: series.value("DCGM_FI_DEV_POWER_MGMT_LIMIT").unwrap_or(0.0) as f32,
+        fan_speed: series.value("DCGM_FI_DEV_FAN_SPEED").map(|speed| speed as u32),
+        processes: Vec::new(),
+        reliability: GpuReliability {
+            ecc_enabled: None,
+            ecc_volatile_corrected: count("DCGM_FI_DEV_ECC_SBE_VOL_TOTAL"),
+            ecc_volatile_uncorrected: count("DCGM_FI_DEV_ECC_DBE_VOL_TOTAL"),
+            ecc_aggregate_uncorrected: count("DCGM_FI_DEV_ECC_DBE_AGG_TOTAL"),
+            retired_pages: (!retired_pages.is_empty()).then(|| retired_pages.iter().sum()),
+            retired_pages_pending: flag("DCGM_FI_DEV_RETIRED_PENDING"),
+            remapped_rows_uncorrectable: count("DCGM_FI_DEV_UNCORRECTABLE_REMAPPED_ROWS"),
+            remapped_rows_pending: flag("DCGM_FI_DEV_ROW_REMAP_PENDING"),
+            remapped_rows_failure: flag("DCGM_FI_DEV_ROW_REMAP_FAILURE"),
+            throttle_reasons,
+            xid_events: Vec::new(),
+            last_xid: count("DCGM_FI_DEV_XID_ERRORS").map(|code| code as u32).filter(|code| *code != 0),
+        },
+        // Without MIG instances DCGM does not tell whether the GPU supports MIG
+        mig_mode: (!mig_devices.is_empty()).then_some(MigMode { current: true, pending: true }),
+        mig_devices,
+        allocations: Vec::new(),
+    }
+}
+
+/// When the `DCGM_FI_DEV_XID_ERRORS` value of each GPU last changed. The field keeps
+/// reporting the last XID until the driver is reloaded, so its age has to be tracked across
+/// scrapes; a value seen for the first time counts as new.
+#[derive(Debug, Default)]
+pub struct LastXidChanges {
+    changed: HashMap<u32, (u32, DateTime<Utc>)>,
+}
+
+impl LastXidChanges {
+    /// Clear `last_xid` of the GPUs whose value has not changed within `window_secs`;
+    /// 0 keeps every value
+    pub fn age_out(&mut self, gpu_infos: &mut [GpuInfo], now: DateTime<Utc>, window_secs: u64) {
+        for gpu_info in gpu_infos {
+            let Some(code) = gpu_info.reliability.last_xid else {
+                self.changed.remove(&gpu_info.id);
+                continue;
+            };
+
+            let (_, changed_at) = self.changed.entry(gpu_info.id)
+                .and_modify(|(last, changed_at)| {
+                    if *last != code {
+                        *last = code;
+                        *changed_at = now;
+                    }
+                })
+                .or_insert((code, now));
+            if window_secs > 0 && now - *changed_at > chrono::Duration::seconds(window_secs as i64) {
+                gpu_info.reliability.last_xid = None;
+            }
+        }
+    }
+}
+
+/// The series of one GPU; on MIG GPUs each is labelled with its GPU instance
+struct GpuSeries<'a>(&'a [Sample]);
+
+impl GpuSeries<'_> {
+    fn matching(&self, name: &str) -> Vec<&Sample> {
+        self.0.iter().filter(|sample| sample.name == name).collect()
+    }
+
+    /// GPU-wide value; on MIG GPUs every instance repeats it, so the first one is taken
+    fn value(&self, name: &str) -> Option<f64> {
+        let matching = self.matching(name);
+        matching.iter().find(|sample| sample.label("GPU_I_ID").is_none())
+            .or(matching.first())
+            .map(|sample| sample.value)
+            .filter(|value| value.is_finite())
+    }
+
+    /// GPU-wide value, or the sum over MIG instances for per-instance quantities such as memory
+    fn total(&self, name: &str) -> Option<f64> {
+        let matching = self.matching(name);
+        match matching.iter().find(|sample| sample.label("GPU_I_ID").is_none()) {
+            Some(sample) => Some(sample.value),
+            None if matching.is_empty() => None,
+            None => Some(matching.iter().map(|sample| sample.value).sum()),
+        }
+    }
+
+    fn instances(&self) -> BTreeMap<u32, Vec<&Sample>> {
+        let mut instances: BTreeMap<u32, Vec<&Sample>> = BTreeMap::new();
+        for sample in self.0 {
+            if let Some(instance) = sample.label("GPU_I_ID").and_then(|id| id.parse().ok()) {
+                instances.entry(instance).or_default().push(sample);
+            }
+        }
+        instances
+    }
+}
+
+/// Fetch and parse the metrics of the dcgm-exporter at `url`
+pub async fn scrape(url: &str) -> Result<Vec<GpuInfo>> {
+    let client = reqwest::Client::builder().timeout(SCRAPE_TIMEOUT).build()?;
+    let response = client.get(url).send().await
+        .with_context(|| format!("Cannot reach dcgm-exporter at {}", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("dcgm-exporter at {} returned {}", url, response.status()));
+    }
+
+    let text = response.text().await?;
+    parse_gpu_infos(&text).with_context(|| format!("Unexpected metrics from {}", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const DCGM_EXPORTER_3_3: &str = include_str!("../../tests/fixtures/dcgm-exporter/a100-dcgm-exporter-3.3.txt");
+
+    #[test]
+    fn maps_dcgm_fields_to_gpu_info() {
+        let gpus = parse_gpu_infos(DCGM_EXPORTER_3_3).unwrap();
+        assert_eq!(gpus.len(), 3);
+
+        let gpu = &gpus[0];
+        assert_eq!(gpu.name, "NVIDIA A100-SXM4-80GB");
+        assert_eq!(gpu.uuid.as_deref(), Some("GPU-1f2e3d4c-5b6a-7980-8a9b-0c1d2e3f4a5b"));
+        assert_eq!(gpu.pci_bus_id.as_deref(), Some("00000000:07:00.0"));
+        assert_eq!(gpu.driver_version, "535.129.03");
+        assert_eq!(gpu.memory_used, 40213 * MIB);
+        assert_eq!(gpu.memory_total, (40213 + 40321 + 616) * MIB);
+        assert_eq!((gpu.temperature, gpu.temperature_memory, gpu.utilization_gpu), (62, Some(70), 87));
+        assert_eq!((gpu.power_usage, gpu.power_limit), (312.5, 400.0));
+        assert_eq!(gpu.reliability.ecc_volatile_corrected, Some(3));
+        assert_eq!(gpu.reliability.throttle_reasons, vec!["sw_power_cap"]);
+        assert_eq!(gpu.reliability.last_xid, None);
+        assert!(gpu.mig_mode.is_none());
+
+        let failing = &gpus[1].reliability;
+        assert_eq!(failing.ecc_volatile_uncorrected, Some(2));
+        assert_eq!(failing.remapped_rows_uncorrectable, Some(2));
+        assert_eq!(failing.remapped_rows_pending, Some(true));
+        assert_eq!(failing.remapped_rows_failure, Some(false));
+        assert_eq!(failing.throttle_reasons, vec!["sw_power_cap", "hw_thermal_slowdown"]);
+        assert_eq!(failing.last_xid, Some(79));
+    }
+
+    #[test]
+    fn last_xid_ages_out_of_the_window() {
+        let start = Utc::now();
+        let hours = |hours: i64| start + chrono::Duration::hours(hours);
+        let mut changes = LastXidChanges::default();
+        let scrape = |changes: &mut LastXidChanges, last_xid: Option<u32>, at| {
+            let mut gpus = parse_gpu_infos(DCGM_EXPORTER_3_3).unwrap();
+            gpus[1].reliability.last_xid = last_xid;
+            changes.age_out(&mut gpus, at, 86400);
+            gpus[1].reliability.last_xid
+        };
+
+        assert_eq!(scrape(&mut changes, Some(79), start), Some(79));
+        assert_eq!(scrape(&mut changes, Some(79), hours(23)), Some(79));
+        assert_eq!(scrape(&mut changes, Some(79), hours(25)), None);
+
+        // A different XID restarts the window
+        assert_eq!(scrape(&mut changes, Some(48), hours(26)), Some(48));
+        assert_eq!(scrape(&mut changes, Some(48), hours(49)), Some(48));
+
+        // As does the same XID after the field was cleared
+        assert_eq!(scrape(&mut changes, None, hours(60)), None);
+        assert_eq!(scrape(&mut changes, Some(48), hours(61)), Some(48));
+
+        let mut gpus = parse_gpu_infos(DCGM_EXPORTER_3_3).unwrap();
+        changes.age_out(&mut gpus, hours(1000), 0);
+        assert_eq!(gpus[1].reliability.last_xid, Some(79));
+    }
+
+    #[test]
+    fn maps_mig_instances() {
+        let gpu = parse_gpu_infos(DCGM_EXPORTER_3_3).unwrap().remove(2);
+        assert_eq!(gpu.mig_mode, Some(MigMode { current: true, pending: true }));
+        assert_eq!(gpu.temperature, 48);
+        assert_eq!(gpu.memory_used, (20082 + 5) * MIB);
+
+        assert_eq!(gpu.mig_devices.len(), 2);
+        let device = &gpu.mig_devices[0];
+        assert_eq!((device.gpu_instance_id, device.profile.as_deref()), (1, Some("3g.40gb")));
+        assert_eq!(device.memory_total, (20082 + 20110) * MIB);
+        assert_eq!(device.utilization_gpu, Some(42));
+        assert_eq!(gpu.mig_devices[1].profile.as_deref(), Some("2g.20gb"));
+    }
+
+    #[test]
+    fn rejects_metrics_without_dcgm_series() {
+        assert!(parse_gpu_infos("# TYPE up gauge\nup 1\n").is_err());
+    }
+
+    /// Serve `body` once with `status` on a local port, like a dcgm-exporter would
+    async fn serve_once(status: &'static str, body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let _ = stream.read(&mut request).await;
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        format!("http://{}/metrics", address)
+    }
+
+    #[tokio::test]
+    async fn scrapes_a_stand_in_exporter() {
+        let url = serve_once("200 OK", DCGM_EXPORTER_3_3).await;
+        let gpus = scrape(&url).await.unwrap();
+        assert_eq!(gpus.len(), 3);
+        assert_eq!(gpus[1].temperature, 91);
+
+        let url = serve_once("503 Service Unavailable", "").await;
+        let error = scrape(&url).await.unwrap_err().to_string();
+        assert!(error.contains("returned 503"), "{}", error);
+    }
+}
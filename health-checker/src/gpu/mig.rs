@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::policy::{self, GpuThresholds};
+use super::GpuProcess;
+use crate::HealthStatus;
+
+/// Current and pending MIG mode; a difference means a GPU reset is outstanding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigMode {
+    pub current: bool,
+    pub pending: bool,
+}
+
+/// A MIG device: one compute instance inside a GPU instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigDevice {
+    /// MIG device index within the parent GPU
+    pub index: u32,
+    pub gpu_instance_id: u32,
+    pub compute_instance_id: u32,
+    /// Profile such as `3g.40gb`, from `nvidia-smi -L`
+    pub profile: Option<String>,
+    pub uuid: Option<String>,
+    pub multiprocessor_count: Option<u32>,
+    pub memory_total: u64,
+    pub memory_used: u64,
+    pub memory_free: u64,
+    /// nvidia-smi does not report per-instance utilization
+    pub utilization_gpu: Option<u32>,
+    pub ecc_volatile_uncorrected: Option<u64>,
+    pub processes: Vec<GpuProcess>,
+}
+
+/// One `MIG <profile> Device <n>: (UUID: ...)` line from `nvidia-smi -L`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigListing {
+    pub gpu: u32,
+    pub device: u32,
+    pub profile: String,
+    pub uuid: String,
+}
+
+/// Parse the MIG devices listed under each GPU by `nvidia-smi -L`
+pub fn parse_mig_listing(output: &str) -> Vec<MigListing> {
+    let mut listings = Vec::new();
+    let mut gpu = None;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("GPU ") {
+            gpu = rest.split(':').next().and_then(|index| index.trim().parse().ok());
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix("MIG ") else {
+            continue;
+        };
+        let (Some(gpu), Some((profile, rest))) = (gpu, rest.split_once("Device")) else {
+            continue;
+        };
+        let Some((device, rest)) = rest.split_once(':') else {
+            continue;
+        };
+        let uuid = rest.trim().trim_start_matches("(UUID:").trim_end_matches(')').trim();
+
+        if let Ok(device) = device.trim().parse() {
+            listings.push(MigListing {
+                gpu,
+                device,
+                profile: profile.trim().to_string(),
+                uuid: uuid.to_string(),
+            });
+        }
+    }
+
+    listings
+}
+
+/// Evaluate a MIG device against the memory and utilization thresholds of its parent GPU
+pub fn evaluate_mig_device(device: &MigDevice, thresholds: &GpuThresholds) -> (HealthStatus, String) {
+    let mut status = HealthStatus::Healthy;
+    let mut issues = Vec::new();
+
+    if let Some(count) = device.ecc_volatile_uncorrected.filter(|count| *count > 0) {
+        issues.push(format!("{} uncorrectable ECC errors since driver load", count));
+        status = HealthStatus::Unhealthy;
+    }
+
+    let memory_usage_percent = if device.memory_total > 0 {
+        (device.memory_used as f64 / device.memory_total as f64) * 100.0
+    } else {
+        0.0
+    };
+    if let Some(severity) = policy::severity(memory_usage_percent, &thresholds.memory) {
+        let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+        issues.push(format!("{} memory usage: {:.1}%", level, memory_usage_percent));
+        policy::escalate(&mut status, severity);
+    }
+    if let Some(severity) = device.utilization_gpu.and_then(|utilization| policy::severity(utilization as f64, &thresholds.utilization)) {
+        let level = if severity == HealthStatus::Unhealthy { "Critical" } else { "High" };
+        issues.push(format!("{} utilization: {}%", level, device.utilization_gpu.unwrap_or_default()));
+        policy::escalate(&mut status, severity);
+    }
+
+    let mut details = format!(
+        "GI {} CI {} - Mem: {:.1}% ({}/{}MB)",
+        device.gpu_instance_id,
+        device.compute_instance_id,
+        memory_usage_percent,
+        device.memory_used / 1024 / 1024,
+        device.memory_total / 1024 / 1024
+    );
+    if let Some(utilization) = device.utilization_gpu {
+        details.push_str(&format!(", GPU: {}%", utilization));
+    }
+    if let Some(sms) = device.multiprocessor_count {
+        details.push_str(&format!(", {} SMs", sms));
+    }
+    details.push_str(&format!(", {} processes", device.processes.len()));
+    if !issues.is_empty() {
+        details.push_str(&format!(" - Issues: {}", issues.join(", ")));
+    }
+
+    (status, details)
+}
+
+/// Compare the MIG profiles found on a GPU with the configured ones.
+/// Returns `None` when they match; an empty `expected` means MIG should be disabled.
+pub fn mig_layout_mismatch(mig_enabled: bool, expected: &[String], actual: &[String]) -> Option<String> {
+    if expected.is_empty() {
+        return mig_enabled.then(|| format!("MIG enabled with {}, expected MIG disabled", describe_profiles(actual)));
+    }
+    if !mig_enabled {
+        return Some(format!("MIG disabled, expected {}", describe_profiles(expected)));
+    }
+
+    let mut remaining = count_profiles(actual);
+    let mut missing = Vec::new();
+    for profile in expected {
+        match remaining.get_mut(profile.as_str()) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => missing.push(profile.clone()),
+        }
+    }
+    let unexpected: Vec<String> = remaining.into_iter()
+        .flat_map(|(profile, count)| std::iter::repeat_n(profile.to_string(), count))
+        .collect();
+
+    if missing.is_empty() && unexpected.is_empty() {
+        return None;
+    }
+
+    let mut differences = Vec::new();
+    if !missing.is_empty() {
+        differences.push(format!("missing {}", describe_profiles(&missing)));
+    }
+    if !unexpected.is_empty() {
+        differences.push(format!("unexpected {}", describe_profiles(&unexpected)));
+    }
+    Some(format!(
+        "Found {}, expected {}: {}",
+        describe_profiles(actual),
+        describe_profiles(expected),
+        differences.join("; ")
+    ))
+}
+
+fn count_profiles(profiles: &[String]) -> BTreeMap<&str, usize> {
+    let mut counts = BTreeMap::new();
+    for profile in profiles {
+        *counts.entry(profile.as_str()).or_default() += 1;
+    }
+    counts
+}
+
+/// Profile counts in name order, e.g. `2g.20gb x2, 3g.40gb`
+pub fn describe_profiles(profiles: &[String]) -> String {
+    if profiles.is_empty() {
+        return "no MIG devices".to_string();
+    }
+
+    count_profiles(profiles).into_iter()
+        .map(|(profile, count)| if count > 1 { format!("{} x{}", profile, count) } else { profile.to_string() })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A100_MIG_LIST: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-mig-list.txt");
+
+    fn profiles(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_mig_devices_from_gpu_listing() {
+        let listings = parse_mig_listing(A100_MIG_LIST);
+        assert_eq!(listings.len(), 3);
+        assert_eq!(listings[0], MigListing {
+            gpu: 0,
+            device: 0,
+            profile: "3g.40gb".to_string(),
+            uuid: "MIG-0b9c6f1e-4d2a-5e8b-a1c3-7f6e5d4c3b2a".to_string(),
+        });
+        assert_eq!(listings[2].device, 2);
+        assert_eq!(listings[2].profile, "2g.20gb");
+    }
+
+    #[test]
+    fn matching_layout_reports_nothing_regardless_of_order() {
+        let expected = profiles(&["3g.40gb", "2g.20gb", "2g.20gb"]);
+        let actual = profiles(&["2g.20gb", "3g.40gb", "2g.20gb"]);
+        assert_eq!(mig_layout_mismatch(true, &expected, &actual), None);
+        assert_eq!(mig_layout_mismatch(false, &[], &[]), None);
+    }
+
+    #[test]
+    fn describes_missing_and_unexpected_profiles() {
+        let expected = profiles(&["3g.40gb", "2g.20gb", "2g.20gb"]);
+        let actual = profiles(&["3g.40gb", "2g.20gb", "1g.10gb", "1g.10gb"]);
+        assert_eq!(
+            mig_layout_mismatch(true, &expected, &actual).unwrap(),
+            "Found 1g.10gb x2, 2g.20gb, 3g.40gb, expected 2g.20gb x2, 3g.40gb: missing 2g.20gb; unexpected 1g.10gb x2"
+        );
+    }
+
+    #[test]
+    fn reports_mig_mode_mismatch() {
+        let expected = profiles(&["7g.80gb"]);
+        assert_eq!(mig_layout_mismatch(false, &expected, &[]).unwrap(), "MIG disabled, expected 7g.80gb");
+        assert_eq!(
+            mig_layout_mismatch(true, &[], &expected).unwrap(),
+            "MIG enabled with 7g.80gb, expected MIG disabled"
+        );
+    }
+
+    #[test]
+    fn evaluates_mig_device_memory_and_ecc() {
+        let thresholds = policy::thresholds_for(&crate::config::GpuHealthPolicy::default(), "NVIDIA A100-SXM4-80GB");
+        let mut device = MigDevice {
+            index: 0,
+            gpu_instance_id: 2,
+            compute_instance_id: 0,
+            profile: Some("3g.40gb".to_string()),
+            uuid: None,
+            multiprocessor_count: Some(42),
+            memory_total: 40192 * 1024 * 1024,
+            memory_used: 20096 * 1024 * 1024,
+            memory_free: 20096 * 1024 * 1024,
+            utilization_gpu: None,
+            ecc_volatile_uncorrected: Some(0),
+            processes: Vec::new(),
+        };
+        assert_eq!(
+            evaluate_mig_device(&device, &thresholds),
+            (HealthStatus::Healthy, "GI 2 CI 0 - Mem: 50.0% (20096/40192MB), 42 SMs, 0 processes".to_string())
+        );
+
+        device.memory_used = 38011 * 1024 * 1024;
+        assert_eq!(evaluate_mig_device(&device, &thresholds).0, HealthStatus::Degraded);
+
+        device.ecc_volatile_uncorrected = Some(1);
+        assert_eq!(evaluate_mig_device(&device, &thresholds).0, HealthStatus::Unhealthy);
+    }
+}
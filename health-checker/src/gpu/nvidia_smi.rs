@@ -0,0 +1,402 @@
+use anyhow::{anyhow, Context, Result};
+use roxmltree::{Document, Node, ParsingOptions};
+
+use super::mig::{MigDevice, MigMode};
+use super::reliability::GpuReliability;
+use super::{GpuInfo, GpuProcess};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Parse the output of `nvidia-smi -q -x` into one `GpuInfo` per attached GPU.
+/// GPUs are numbered in document order, which matches the nvidia-smi index.
+pub fn parse_gpu_infos(xml: &str) -> Result<Vec<GpuInfo>> {
+    // nvidia-smi emits a DOCTYPE referencing its DTD, which roxmltree rejects by default
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let document = Document::parse_with_options(xml, options).context("Invalid nvidia-smi XML")?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "nvidia_smi_log" {
+        return Err(anyhow!("Unexpected nvidia-smi XML root <{}>", root.tag_name().name()));
+    }
+
+    let driver_version = text(root, &["driver_version"]).unwrap_or_default();
+    let cuda_version = text(root, &["cuda_version"]);
+
+    root.children()
+        .filter(|node| node.has_tag_name("gpu"))
+        .enumerate()
+        .map(|(index, gpu)| parse_gpu(index as u32, gpu, &driver_version, cuda_version.as_deref()))
+        .collect()
+}
+
+fn parse_gpu(id: u32, gpu: Node, driver_version: &str, cuda_version: Option<&str>) -> Result<GpuInfo> {
+    let name = text(gpu, &["product_name"])
+        .ok_or_else(|| anyhow!("GPU {} has no product name", id))?;
+
+    // Driver 535 renamed power_readings to gpu_power_readings and power_limit to current_power_limit
+    let power_usage = quantity(gpu, &["gpu_power_readings", "power_draw"], "W")
+        .or_else(|| quantity(gpu, &["power_readings", "power_draw"], "W"))
+        .unwrap_or(0.0) as f32;
+    let power_limit = quantity(gpu, &["gpu_power_readings", "current_power_limit"], "W")
+        .or_else(|| quantity(gpu, &["power_readings", "enforced_power_limit"], "W"))
+        .or_else(|| quantity(gpu, &["power_readings", "power_limit"], "W"))
+        .unwrap_or(0.0) as f32;
+
+    let processes = parse_processes(gpu);
+    let mig_mode = parse_mig_mode(gpu);
+    let mig_devices = parse_mig_devices(gpu, &processes);
+
+    Ok(GpuInfo {
+        id,
+        name,
+        uuid: text(gpu, &["uuid"]),
+        pci_bus_id: text(gpu, &["pci", "pci_bus_id"]).or_else(|| gpu.attribute("id").map(str::to_string)),
+        driver_version: driver_version.to_string(),
+        cuda_version: cuda_version.map(str::to_string),
+        memory_total: mebibytes(gpu, &["fb_memory_usage", "total"]),
+        memory_used: mebibytes(gpu, &["fb_memory_usage", "used"]),
+        memory_free: mebibytes(gpu, &["fb_memory_usage", "free"]),
+        utilization_gpu: quantity(gpu, &["utilization", "gpu_util"], "%").unwrap_or(0.0) as u32,
+        utilization_memory: quantity(gpu, &["utilization", "memory_util"], "%").unwrap_or(0.0) as u32,
+        temperature: quantity(gpu, &["temperature", "gpu_temp"], "C").unwrap_or(0.0) as u32,
+        temperature_junction: None,
+        temperature_memory: quantity(gpu, &["temperature", "memory_temp"], "C").map(|temp| temp as u32),
+        power_usage,
+        power_limit,
+        fan_speed: quantity(gpu, &["fan_speed"], "%").map(|speed| speed as u32),
+        processes,
+        reliability: parse_reliability(gpu),
+        mig_mode,
+        mig_devices,
+        allocations: Vec::new(),
+    })
+}
+
+fn parse_mig_mode(gpu: Node) -> Option<MigMode> {
+    Some(MigMode {
+        current: flag(gpu, &["mig_mode", "current_mig"])?,
+        pending: flag(gpu, &["mig_mode", "pending_mig"])?,
+    })
+}
+
+/// MIG devices with the processes running in their GPU/compute instance
+fn parse_mig_devices(gpu: Node, processes: &[GpuProcess]) -> Vec<MigDevice> {
+    let Some(devices) = child(gpu, &["mig_devices"]) else {
+        return Vec::new();
+    };
+
+    devices.children()
+        .filter(|node| node.has_tag_name("mig_device"))
+        .filter_map(|device| {
+            let gpu_instance_id = text(device, &["gpu_instance_id"])?.parse().ok()?;
+            let compute_instance_id = text(device, &["compute_instance_id"])?.parse().ok()?;
+            let uncorrectable: Vec<u64> = child(device, &["ecc_error_count", "volatile_count"])
+                .map(|counts| {
+                    counts.children()
+                        .filter(|node| node.tag_name().name().contains("uncorrectable"))
+                        .filter_map(|node| node.text()?.trim().parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Some(MigDevice {
+                index: text(device, &["index"])?.parse().ok()?,
+                gpu_instance_id,
+                compute_instance_id,
+                profile: None,
+                uuid: None,
+                multiprocessor_count: text(device, &["device_attributes", "shared", "multiprocessor_count"])
+                    .and_then(|count| count.parse().ok()),
+                memory_total: mebibytes(device, &["fb_memory_usage", "total"]),
+                memory_used: mebibytes(device, &["fb_memory_usage", "used"]),
+                memory_free: mebibytes(device, &["fb_memory_usage", "free"]),
+                utilization_gpu: None,
+                ecc_volatile_uncorrected: (!uncorrectable.is_empty()).then(|| uncorrectable.iter().sum()),
+                processes: processes.iter()
+                    .filter(|process| {
+                        process.gpu_instance_id == Some(gpu_instance_id)
+                            && process.compute_instance_id == Some(compute_instance_id)
+                    })
+                    .cloned()
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+fn parse_reliability(gpu: Node) -> GpuReliability {
+    let retired_counts: Vec<u64> = ["multiple_single_bit_retirement", "double_bit_retirement"]
+        .iter()
+        .filter_map(|cause| text(gpu, &["retired_pages", cause, "retired_count"])?.parse().ok())
+        .collect();
+
+    GpuReliability {
+        ecc_enabled: flag(gpu, &["ecc_mode", "current_ecc"]),
+        ecc_volatile_corrected: ecc_errors(gpu, "volatile", false),
+        ecc_volatile_uncorrected: ecc_errors(gpu, "volatile", true),
+        ecc_aggregate_uncorrected: ecc_errors(gpu, "aggregate", true),
+        retired_pages: (!retired_counts.is_empty()).then(|| retired_counts.iter().sum()),
+        retired_pages_pending: flag(gpu, &["retired_pages", "pending_retirement"])
+            .or_else(|| flag(gpu, &["retired_pages", "pending_blacklist"])),
+        remapped_rows_uncorrectable: text(gpu, &["remapped_rows", "remapped_row_unc"]).and_then(|count| count.parse().ok()),
+        remapped_rows_pending: flag(gpu, &["remapped_rows", "remapped_row_pending"]),
+        remapped_rows_failure: flag(gpu, &["remapped_rows", "remapped_row_failure"]),
+        throttle_reasons: active_throttle_reasons(gpu),
+        xid_events: Vec::new(),
+        last_xid: None,
+    }
+}
+
+/// Sum of the correctable or uncorrectable ECC counters in the `volatile` or `aggregate` section
+fn ecc_errors(gpu: Node, counter: &str, uncorrectable: bool) -> Option<u64> {
+    let errors = child(gpu, &["ecc_errors", counter])?;
+
+    // Drivers before 525 break the counts down by single/double bit and memory location
+    let legacy_section = if uncorrectable { "double_bit" } else { "single_bit" };
+    if child(errors, &[legacy_section]).is_some() {
+        return text(errors, &[legacy_section, "total"])?.parse().ok();
+    }
+
+    // Newer drivers report sram_/dram_ (un)correctable counters
+    let counts: Vec<u64> = errors.children()
+        .filter(|node| {
+            let name = node.tag_name().name();
+            if uncorrectable {
+                name.contains("uncorrectable")
+            } else {
+                name.ends_with("_correctable")
+            }
+        })
+        .filter_map(|node| node.text()?.trim().parse().ok())
+        .collect();
+
+    (!counts.is_empty()).then(|| counts.iter().sum())
+}
+
+/// Active reasons with the section prefix removed, e.g. `hw_thermal_slowdown`.
+/// Driver 535 renamed `clocks_throttle_reasons` to `clocks_event_reasons`.
+fn active_throttle_reasons(gpu: Node) -> Vec<String> {
+    [("clocks_event_reasons", "clocks_event_reason_"), ("clocks_throttle_reasons", "clocks_throttle_reason_")]
+        .iter()
+        .filter_map(|(section, prefix)| Some((child(gpu, &[section])?, prefix)))
+        .flat_map(|(reasons, prefix)| {
+            reasons.children()
+                .filter(|node| node.text().map(str::trim) == Some("Active"))
+                .filter_map(|node| node.tag_name().name().strip_prefix(prefix).map(str::to_string))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn parse_processes(gpu: Node) -> Vec<GpuProcess> {
+    let Some(processes) = child(gpu, &["processes"]) else {
+        return Vec::new();
+    };
+
+    processes.children()
+        .filter(|node| node.has_tag_name("process_info"))
+        .filter_map(|process| {
+            let pid = text(process, &["pid"])?.parse().ok()?;
+            Some(GpuProcess {
+                pid,
+                name: text(process, &["process_name"]).unwrap_or_default(),
+                memory_used: mebibytes(process, &["used_memory"]),
+                gpu_instance_id: text(process, &["gpu_instance_id"]).and_then(|id| id.parse().ok()),
+                compute_instance_id: text(process, &["compute_instance_id"]).and_then(|id| id.parse().ok()),
+                owner: None,
+                leaked: None,
+            })
+        })
+        .collect()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, tag| node.children().find(|child| child.has_tag_name(*tag)))
+}
+
+/// Trimmed element text, with nvidia-smi's `N/A` placeholders treated as missing
+fn text(node: Node, path: &[&str]) -> Option<String> {
+    let value = child(node, path)?.text()?.trim();
+    match value {
+        "" | "N/A" | "[N/A]" | "[Not Supported]" => None,
+        value => Some(value.to_string()),
+    }
+}
+
+fn flag(node: Node, path: &[&str]) -> Option<bool> {
+    match text(node, path)?.as_str() {
+        "Yes" | "Enabled" => Some(true),
+        "No" | "Disabled" => Some(false),
+        _ => None,
+    }
+}
+
+/// Numeric value of an element such as `312.45 W` or `87 %`
+fn quantity(node: Node, path: &[&str], unit: &str) -> Option<f64> {
+    text(node, path)?.strip_suffix(unit)?.trim().parse().ok()
+}
+
+fn mebibytes(node: Node, path: &[&str]) -> u64 {
+    quantity(node, path, "MiB").map(|mib| mib as u64 * MIB).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A100_DRIVER_535: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-driver-535.xml");
+    const A100_DRIVER_535_DEGRADED: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-driver-535-degraded.xml");
+    const A100_MIG_DRIVER_535: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-mig-driver-535.xml");
+    const RTX3090_DRIVER_470: &str = include_str!("../../tests/fixtures/nvidia-smi/rtx3090-driver-470.xml");
+
+    #[test]
+    fn parses_every_gpu_on_a_multi_gpu_node() {
+        let gpus = parse_gpu_infos(A100_DRIVER_535).unwrap();
+        assert_eq!(gpus.len(), 2);
+
+        let gpu = &gpus[0];
+        assert_eq!(gpu.id, 0);
+        assert_eq!(gpu.name, "NVIDIA A100-SXM4-80GB");
+        assert_eq!(gpu.uuid.as_deref(), Some("GPU-5f2e6b1c-8a3d-4e7f-9b21-0c4d5e6f7a81"));
+        assert_eq!(gpu.pci_bus_id.as_deref(), Some("00000000:07:00.0"));
+        assert_eq!(gpu.driver_version, "535.183.01");
+        assert_eq!(gpu.cuda_version.as_deref(), Some("12.2"));
+        assert_eq!(gpu.memory_total, 81920 * MIB);
+        assert_eq!(gpu.memory_used, 40132 * MIB);
+        assert_eq!(gpu.memory_free, 41221 * MIB);
+        assert_eq!(gpu.utilization_gpu, 87);
+        assert_eq!(gpu.utilization_memory, 45);
+        assert_eq!(gpu.temperature, 62);
+        assert_eq!(gpu.temperature_memory, Some(71));
+        assert_eq!(gpu.power_usage, 312.45);
+        assert_eq!(gpu.power_limit, 400.0);
+
+        assert_eq!(gpus[1].id, 1);
+        assert_eq!(gpus[1].uuid.as_deref(), Some("GPU-9c1a7d3e-2b4f-4a6c-8e0d-1f2a3b4c5d62"));
+        assert_eq!(gpus[1].utilization_gpu, 0);
+        assert!(gpus[1].processes.is_empty());
+    }
+
+    #[test]
+    fn parses_process_names_and_memory() {
+        let gpus = parse_gpu_infos(A100_DRIVER_535).unwrap();
+        let processes = &gpus[0].processes;
+
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0].pid, 48211);
+        assert_eq!(processes[0].name, "/opt/conda/bin/python3");
+        assert_eq!(processes[0].memory_used, 38912 * MIB);
+        assert_eq!(processes[1].pid, 48302);
+        assert_eq!(processes[1].name, "/usr/local/bin/torchrun");
+    }
+
+    #[test]
+    fn treats_unavailable_fan_speed_as_missing() {
+        let gpus = parse_gpu_infos(A100_DRIVER_535).unwrap();
+        assert_eq!(gpus[0].fan_speed, None);
+    }
+
+    #[test]
+    fn parses_pre_535_power_readings_and_fan_speed() {
+        let gpus = parse_gpu_infos(RTX3090_DRIVER_470).unwrap();
+        assert_eq!(gpus.len(), 1);
+
+        let gpu = &gpus[0];
+        assert_eq!(gpu.name, "NVIDIA GeForce RTX 3090");
+        assert_eq!(gpu.driver_version, "470.223.02");
+        assert_eq!(gpu.cuda_version.as_deref(), Some("11.4"));
+        assert_eq!(gpu.fan_speed, Some(41));
+        assert_eq!(gpu.power_usage, 348.71);
+        assert_eq!(gpu.power_limit, 350.0);
+        assert_eq!(gpu.memory_total, 24268 * MIB);
+        assert_eq!(gpu.temperature, 78);
+        assert_eq!(gpu.processes.len(), 2);
+        assert_eq!(gpu.processes[1].name, "python");
+        assert_eq!(gpu.processes[1].memory_used, 23089 * MIB);
+    }
+
+    #[test]
+    fn parses_clean_reliability_counters() {
+        let gpus = parse_gpu_infos(A100_DRIVER_535).unwrap();
+        let reliability = &gpus[0].reliability;
+
+        assert_eq!(reliability.ecc_enabled, Some(true));
+        assert_eq!(reliability.ecc_volatile_corrected, Some(0));
+        assert_eq!(reliability.ecc_volatile_uncorrected, Some(0));
+        assert_eq!(reliability.ecc_aggregate_uncorrected, Some(0));
+        assert_eq!(reliability.retired_pages, None);
+        assert_eq!(reliability.retired_pages_pending, None);
+        assert_eq!(reliability.remapped_rows_uncorrectable, Some(0));
+        assert_eq!(reliability.remapped_rows_pending, Some(false));
+        assert_eq!(reliability.remapped_rows_failure, Some(false));
+        assert!(reliability.throttle_reasons.is_empty());
+    }
+
+    #[test]
+    fn parses_ecc_errors_remapping_and_throttle_reasons() {
+        let gpus = parse_gpu_infos(A100_DRIVER_535_DEGRADED).unwrap();
+
+        let reliability = &gpus[0].reliability;
+        assert_eq!(reliability.ecc_volatile_corrected, Some(14));
+        assert_eq!(reliability.ecc_volatile_uncorrected, Some(2));
+        assert_eq!(reliability.ecc_aggregate_uncorrected, Some(3));
+        assert_eq!(reliability.remapped_rows_uncorrectable, Some(1));
+        assert_eq!(reliability.remapped_rows_pending, Some(true));
+
+        assert_eq!(gpus[1].reliability.throttle_reasons, vec!["sw_power_cap", "hw_thermal_slowdown"]);
+    }
+
+    #[test]
+    fn parses_pre_535_throttle_reasons_and_missing_ecc() {
+        let gpus = parse_gpu_infos(RTX3090_DRIVER_470).unwrap();
+        let reliability = &gpus[0].reliability;
+
+        assert_eq!(reliability.ecc_enabled, None);
+        assert_eq!(reliability.ecc_volatile_uncorrected, None);
+        assert_eq!(reliability.remapped_rows_pending, None);
+        assert_eq!(reliability.throttle_reasons, vec!["sw_power_cap"]);
+    }
+
+    #[test]
+    fn parses_mig_mode() {
+        let gpus = parse_gpu_infos(A100_DRIVER_535).unwrap();
+        assert_eq!(gpus[0].mig_mode, Some(MigMode { current: false, pending: false }));
+        assert!(gpus[0].mig_devices.is_empty());
+
+        let gpus = parse_gpu_infos(RTX3090_DRIVER_470).unwrap();
+        assert_eq!(gpus[0].mig_mode, None);
+    }
+
+    #[test]
+    fn parses_mig_devices_with_their_processes() {
+        let gpus = parse_gpu_infos(A100_MIG_DRIVER_535).unwrap();
+        assert_eq!(gpus[0].mig_mode, Some(MigMode { current: true, pending: true }));
+        assert_eq!(gpus[1].mig_mode, Some(MigMode { current: false, pending: true }));
+
+        let devices = &gpus[0].mig_devices;
+        assert_eq!(devices.len(), 3);
+        assert_eq!((devices[0].index, devices[0].gpu_instance_id, devices[0].compute_instance_id), (0, 2, 0));
+        assert_eq!(devices[0].multiprocessor_count, Some(42));
+        assert_eq!(devices[0].memory_total, 40192 * MIB);
+        assert_eq!(devices[0].memory_used, 38011 * MIB);
+        assert_eq!(devices[0].ecc_volatile_uncorrected, Some(0));
+        assert_eq!(devices[0].utilization_gpu, None);
+
+        let pids: Vec<Vec<u32>> = devices.iter()
+            .map(|device| device.processes.iter().map(|process| process.pid).collect())
+            .collect();
+        assert_eq!(pids, vec![vec![48211], vec![], vec![48302]]);
+    }
+
+    #[test]
+    fn rejects_output_that_is_not_nvidia_smi_xml() {
+        assert!(parse_gpu_infos("NVIDIA-SMI has failed because it couldn't communicate with the NVIDIA driver.").is_err());
+        assert!(parse_gpu_infos("<?xml version=\"1.0\" ?><something_else/>").is_err());
+    }
+
+    #[test]
+    fn returns_no_gpus_when_none_are_attached() {
+        let xml = "<?xml version=\"1.0\" ?>\n<nvidia_smi_log><driver_version>535.183.01</driver_version><attached_gpus>0</attached_gpus></nvidia_smi_log>";
+        assert!(parse_gpu_infos(xml).unwrap().is_empty());
+    }
+}
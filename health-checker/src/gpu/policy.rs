@@ -0,0 +1,117 @@
+use crate::config::{GpuHealthPolicy, Threshold};
+pub use crate::severity::{escalate, severity};
+
+/// Thresholds that apply to one GPU model after overrides
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuThresholds {
+    pub temperature: Threshold,
+    pub junction_temperature: Threshold,
+    pub memory: Threshold,
+    pub power: Threshold,
+    pub utilization: Threshold,
+}
+
+/// Resolve the thresholds for a GPU named `name`; each matching override replaces the metrics it sets
+pub fn thresholds_for(policy: &GpuHealthPolicy, name: &str) -> GpuThresholds {
+    let mut thresholds = GpuThresholds {
+        temperature: policy.temperature,
+        junction_temperature: policy.junction_temperature,
+        memory: policy.memory,
+        power: policy.power,
+        utilization: policy.utilization,
+    };
+
+    for policy_override in policy.overrides.iter().filter(|policy_override| name_matches(&policy_override.name, name)) {
+        thresholds.temperature = policy_override.temperature.unwrap_or(thresholds.temperature);
+        thresholds.junction_temperature = policy_override.junction_temperature.unwrap_or(thresholds.junction_temperature);
+        thresholds.memory = policy_override.memory.unwrap_or(thresholds.memory);
+        thresholds.power = policy_override.power.unwrap_or(thresholds.power);
+        thresholds.utilization = policy_override.utilization.unwrap_or(thresholds.utilization);
+    }
+
+    thresholds
+}
+
+/// Case-insensitive match where `*` stands for any run of characters
+fn name_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+
+    let (first, rest) = parts.split_first().expect("split yields at least one part");
+    let Some(mut remaining) = name.strip_prefix(first) else {
+        return false;
+    };
+    let Some((last, middle)) = rest.split_last() else {
+        return remaining.is_empty();
+    };
+
+    for part in middle {
+        match remaining.find(part) {
+            Some(position) => remaining = &remaining[position + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.len() >= last.len() && remaining.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GpuPolicyOverride;
+
+    fn threshold(degraded: f64, unhealthy: f64) -> Threshold {
+        Threshold { degraded: Some(degraded), unhealthy: Some(unhealthy) }
+    }
+
+    #[test]
+    fn matches_name_patterns() {
+        assert!(name_matches("*H100*", "NVIDIA H100 80GB HBM3"));
+        assert!(name_matches("nvidia a100*", "NVIDIA A100-SXM4-80GB"));
+        assert!(name_matches("*MI300X*", "AMD Instinct MI300X OAM"));
+        assert!(name_matches("NVIDIA GeForce RTX 4090", "NVIDIA GeForce RTX 4090"));
+        assert!(!name_matches("NVIDIA GeForce RTX 4090", "NVIDIA GeForce RTX 4090 D"));
+        assert!(!name_matches("*H100*", "NVIDIA A100-SXM4-80GB"));
+        assert!(!name_matches("*80GB*80GB", "NVIDIA A100 80GB"));
+    }
+
+    #[test]
+    fn later_overrides_replace_the_metrics_they_set() {
+        let policy = GpuHealthPolicy {
+            overrides: vec![
+                GpuPolicyOverride {
+                    name: "*MI300X*".to_string(),
+                    temperature: Some(threshold(100.0, 105.0)),
+                    junction_temperature: None,
+                    memory: None,
+                    power: Some(threshold(98.0, 110.0)),
+                    utilization: None,
+                },
+                GpuPolicyOverride {
+                    name: "*OAM".to_string(),
+                    temperature: Some(threshold(102.0, 108.0)),
+                    junction_temperature: None,
+                    memory: None,
+                    power: None,
+                    utilization: None,
+                },
+            ],
+            ..GpuHealthPolicy::default()
+        };
+
+        let thresholds = thresholds_for(&policy, "AMD Instinct MI300X OAM");
+        assert_eq!(thresholds.temperature, threshold(102.0, 108.0));
+        assert_eq!(thresholds.power, threshold(98.0, 110.0));
+        assert_eq!(thresholds.memory, policy.memory);
+
+        let thresholds = thresholds_for(&policy, "NVIDIA A100-SXM4-80GB");
+        assert_eq!(thresholds.temperature, threshold(85.0, 95.0));
+    }
+
+    #[test]
+    fn default_policy_keeps_apple_silicon_limits() {
+        let thresholds = thresholds_for(&GpuHealthPolicy::default(), "Apple M2 Max");
+        assert_eq!(thresholds.temperature.degraded, Some(80.0));
+        assert_eq!(thresholds.memory.degraded, Some(80.0));
+    }
+}
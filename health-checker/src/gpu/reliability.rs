@@ -0,0 +1,408 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use super::sysfs::pci_address;
+use crate::HealthStatus;
+
+/// NVIDIA recommends an RMA once this many pages have been retired
+const RETIRED_PAGES_RMA_THRESHOLD: u64 = 60;
+
+/// Clock throttle reasons that point at a hardware or cooling problem.
+/// `sw_power_cap` and `gpu_idle` are expected under normal load and are not reported.
+const HARDWARE_THROTTLE_REASONS: &[&str] = &[
+    "hw_slowdown",
+    "hw_thermal_slowdown",
+    "hw_power_brake_slowdown",
+    "sw_thermal_slowdown",
+];
+
+/// Error counters and events that predict or indicate GPU failure
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GpuReliability {
+    pub ecc_enabled: Option<bool>,
+    /// ECC error counts since the driver was loaded
+    pub ecc_volatile_corrected: Option<u64>,
+    pub ecc_volatile_uncorrected: Option<u64>,
+    /// ECC error counts over the lifetime of the board
+    pub ecc_aggregate_uncorrected: Option<u64>,
+    pub retired_pages: Option<u64>,
+    pub retired_pages_pending: Option<bool>,
+    /// Row remapping replaces page retirement from Ampere onwards
+    pub remapped_rows_uncorrectable: Option<u64>,
+    pub remapped_rows_pending: Option<bool>,
+    pub remapped_rows_failure: Option<bool>,
+    /// Active clock throttle reasons, e.g. `hw_thermal_slowdown`
+    pub throttle_reasons: Vec<String>,
+    pub xid_events: Vec<XidEvent>,
+    /// Most recent XID as reported by DCGM, which still sees it when the kernel log cannot be read
+    #[serde(default)]
+    pub last_xid: Option<u32>,
+}
+
+/// An `NVRM: Xid` line from the kernel log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct XidEvent {
+    /// `domain:bus:device` as printed by the driver, e.g. `0000:07:00`
+    pub pci_bus_id: String,
+    pub code: u32,
+    pub message: String,
+    /// Seconds since boot when the event was logged; `None` when the log has no timestamps
+    #[serde(default)]
+    pub uptime_secs: Option<f64>,
+}
+
+impl XidEvent {
+    /// Whether this event was raised by the GPU at `pci_bus_id` (e.g. `00000000:07:00.0`)
+    pub fn is_for_device(&self, pci_bus_id: &str) -> bool {
+        pci_address(&self.pci_bus_id).is_some_and(|address| pci_address(pci_bus_id) == Some(address))
+    }
+}
+
+/// Extract XID events from `dmesg` output or raw `/dev/kmsg` records
+pub fn parse_xid_events(log: &str) -> Vec<XidEvent> {
+    log.lines()
+        .filter_map(|line| {
+            let start = line.find("NVRM: Xid (")?;
+            let rest = &line[start + "NVRM: Xid (".len()..];
+            let (pci_bus_id, rest) = rest.split_once("): ")?;
+            let (code, message) = rest.split_once(',').unwrap_or((rest, ""));
+
+            Some(XidEvent {
+                pci_bus_id: pci_bus_id.trim_start_matches("PCI:").to_string(),
+                code: code.trim().parse().ok()?,
+                message: message.trim().to_string(),
+                uptime_secs: parse_log_timestamp(&line[..start]),
+            })
+        })
+        .collect()
+}
+
+/// Seconds since boot from a `[  812.553210] ` dmesg prefix or a `4,1187,812553210,-;` kmsg prefix
+fn parse_log_timestamp(prefix: &str) -> Option<f64> {
+    if let Some(rest) = prefix.trim_start().strip_prefix('[') {
+        return rest.split_once(']')?.0.trim().parse().ok();
+    }
+    let micros: u64 = prefix.split(',').nth(2)?.parse().ok()?;
+    Some(micros as f64 / 1_000_000.0)
+}
+
+/// Events logged within the last `window_secs`, given the current uptime.
+/// Events without a timestamp are kept; a window of 0 keeps every event since boot.
+pub fn recent_xid_events(events: Vec<XidEvent>, uptime_secs: Option<f64>, window_secs: u64) -> Vec<XidEvent> {
+    let Some(since) = uptime_secs.filter(|_| window_secs > 0).map(|now| now - window_secs as f64) else {
+        return events;
+    };
+    events.into_iter()
+        .filter(|event| event.uptime_secs.is_none_or(|logged| logged >= since))
+        .collect()
+}
+
+/// Seconds since boot, from `/proc/uptime`
+pub fn read_uptime() -> Option<f64> {
+    std::fs::read_to_string("/proc/uptime").ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Kernel ring buffer contents, from `dmesg` or `/dev/kmsg` when dmesg is restricted
+pub fn read_kernel_log() -> Result<String> {
+    use std::process::Command;
+
+    if let Ok(output) = Command::new("dmesg").output() {
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+
+    read_kmsg()
+}
+
+fn read_kmsg() -> Result<String> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // Non-blocking, otherwise the read waits for new records once the buffer is drained
+    let mut kmsg = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open("/dev/kmsg")?;
+
+    let mut log = String::new();
+    let mut record = vec![0u8; 8192];
+    loop {
+        match kmsg.read(&mut record) {
+            Ok(0) => break,
+            Ok(len) => log.push_str(&String::from_utf8_lossy(&record[..len])),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+            // The record was overwritten while reading; the next read continues with the oldest one
+            Err(e) if e.raw_os_error() == Some(libc::EPIPE) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(log)
+}
+
+/// Severity of an XID code, or `None` for errors caused by the application rather than the GPU
+pub fn xid_severity(code: u32) -> Option<HealthStatus> {
+    match code {
+        // Application faults: illegal addresses, MMU faults, timeouts and preemption
+        13 | 31 | 43 | 45 | 68 | 69 => None,
+        // Double bit ECC, microcontroller halts, NVLink failure, GPU off the bus,
+        // uncontained ECC and GSP errors all need a reset or a replacement
+        48 | 61 | 62 | 74 | 79 | 92 | 95 | 119 | 120 | 140 => Some(HealthStatus::Unhealthy),
+        _ => Some(HealthStatus::Degraded),
+    }
+}
+
+fn xid_description(code: u32) -> &'static str {
+    match code {
+        13 => "graphics engine exception",
+        31 => "GPU memory page fault",
+        43 => "GPU stopped processing",
+        45 => "preemptive cleanup",
+        48 => "double bit ECC error",
+        61 | 62 => "internal micro-controller error",
+        63 => "ECC page retirement or row remapping recorded",
+        64 => "ECC page retirement or row remapper failure",
+        74 => "NVLink error",
+        79 => "GPU has fallen off the bus",
+        92 => "high single-bit ECC error rate",
+        94 => "contained ECC error",
+        95 => "uncontained ECC error",
+        119 | 120 => "GSP error",
+        140 => "unrecovered ECC error",
+        _ => "driver error",
+    }
+}
+
+/// Judge the reliability signals of one GPU, returning the status and a reason for each problem
+pub fn evaluate_reliability(reliability: &GpuReliability) -> (HealthStatus, Vec<String>) {
+    let mut status = HealthStatus::Healthy;
+    let mut issues = Vec::new();
+
+    if let Some(count) = reliability.ecc_volatile_uncorrected.filter(|count| *count > 0) {
+        issues.push(format!("{} uncorrectable ECC errors since driver load", count));
+        status = HealthStatus::Unhealthy;
+    }
+
+    if reliability.remapped_rows_failure == Some(true) {
+        issues.push("Row remapping failed, GPU needs replacement".to_string());
+        status = HealthStatus::Unhealthy;
+    }
+
+    if let Some(count) = reliability.retired_pages.filter(|count| *count >= RETIRED_PAGES_RMA_THRESHOLD) {
+        issues.push(format!("{} retired pages, at or above the RMA threshold of {}", count, RETIRED_PAGES_RMA_THRESHOLD));
+        status = HealthStatus::Unhealthy;
+    }
+
+    if reliability.remapped_rows_pending == Some(true) {
+        issues.push("Row remapping pending, GPU reset required".to_string());
+        if status == HealthStatus::Healthy {
+            status = HealthStatus::Degraded;
+        }
+    }
+
+    if reliability.retired_pages_pending == Some(true) {
+        issues.push("Page retirement pending, GPU reset required".to_string());
+        if status == HealthStatus::Healthy {
+            status = HealthStatus::Degraded;
+        }
+    }
+
+    let throttled: Vec<&str> = reliability.throttle_reasons.iter()
+        .map(String::as_str)
+        .filter(|reason| HARDWARE_THROTTLE_REASONS.contains(reason))
+        .collect();
+    if !throttled.is_empty() {
+        issues.push(format!("Clocks throttled: {}", throttled.join(", ")));
+        if status == HealthStatus::Healthy {
+            status = HealthStatus::Degraded;
+        }
+    }
+
+    let mut xid_counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for event in &reliability.xid_events {
+        *xid_counts.entry(event.code).or_default() += 1;
+    }
+    for (&code, &count) in &xid_counts {
+        let Some(severity) = xid_severity(code) else {
+            continue;
+        };
+
+        issues.push(format!("XID {} ({}) x{} in kernel log", code, xid_description(code), count));
+        if severity == HealthStatus::Unhealthy {
+            status = HealthStatus::Unhealthy;
+        } else if status == HealthStatus::Healthy {
+            status = HealthStatus::Degraded;
+        }
+    }
+
+    // Already counted when the same XID is in the kernel log
+    let last_xid = reliability.last_xid.filter(|code| !xid_counts.contains_key(code));
+    if let Some((code, severity)) = last_xid.and_then(|code| Some((code, xid_severity(code)?))) {
+        issues.push(format!("XID {} ({}) last reported by DCGM", code, xid_description(code)));
+        if severity == HealthStatus::Unhealthy {
+            status = HealthStatus::Unhealthy;
+        } else if status == HealthStatus::Healthy {
+            status = HealthStatus::Degraded;
+        }
+    }
+
+    (status, issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DMESG_XID: &str = include_str!("../../tests/fixtures/kernel-log/dmesg-xid.txt");
+    const KMSG_XID: &str = include_str!("../../tests/fixtures/kernel-log/kmsg-xid.txt");
+
+    #[test]
+    fn parses_xid_lines_from_dmesg() {
+        let events = parse_xid_events(DMESG_XID);
+        let codes: Vec<u32> = events.iter().map(|event| event.code).collect();
+        assert_eq!(codes, vec![13, 13, 63, 94, 79]);
+
+        assert_eq!(events[4].pci_bus_id, "0000:0f:00");
+        assert_eq!(events[4].message, "pid='<unknown>', name=<unknown>, GPU has fallen off the bus.");
+    }
+
+    #[test]
+    fn parses_xid_records_from_kmsg() {
+        let events = parse_xid_events(KMSG_XID);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].code, 48);
+        assert_eq!(events[1].pci_bus_id, "0000:07:00");
+    }
+
+    #[test]
+    fn parses_xid_timestamps() {
+        let dmesg = parse_xid_events(DMESG_XID);
+        assert_eq!(dmesg[0].uptime_secs, Some(812.55321));
+        assert_eq!(dmesg[4].uptime_secs, Some(93410.770102));
+
+        let kmsg = parse_xid_events(KMSG_XID);
+        assert_eq!(kmsg[1].uptime_secs, Some(91234.00452));
+
+        let untimed = parse_xid_events("NVRM: Xid (PCI:0000:07:00): 79, GPU has fallen off the bus.");
+        assert_eq!(untimed[0].uptime_secs, None);
+    }
+
+    #[test]
+    fn only_recent_xid_events_are_kept() {
+        let codes = |events: Vec<XidEvent>| events.iter().map(|event| event.code).collect::<Vec<u32>>();
+
+        // Within an hour, the application faults shortly after boot are no longer counted
+        let recent = recent_xid_events(parse_xid_events(DMESG_XID), Some(93500.0), 3600);
+        assert_eq!(codes(recent), vec![63, 94, 79]);
+
+        let recent = recent_xid_events(parse_xid_events(DMESG_XID), Some(93500.0), 120);
+        assert_eq!(codes(recent), vec![79]);
+
+        // No window, or no uptime to measure it from, keeps everything since boot
+        assert_eq!(recent_xid_events(parse_xid_events(DMESG_XID), Some(93500.0), 0).len(), 5);
+        assert_eq!(recent_xid_events(parse_xid_events(DMESG_XID), None, 60).len(), 5);
+    }
+
+    #[test]
+    fn matches_xid_events_to_nvidia_smi_bus_ids() {
+        let events = parse_xid_events(DMESG_XID);
+        assert!(events[0].is_for_device("00000000:07:00.0"));
+        assert!(!events[0].is_for_device("00000000:0F:00.0"));
+        assert!(events[4].is_for_device("00000000:0F:00.0"));
+    }
+
+    #[test]
+    fn clean_gpu_is_healthy() {
+        let reliability = GpuReliability {
+            ecc_enabled: Some(true),
+            ecc_volatile_corrected: Some(3),
+            ecc_volatile_uncorrected: Some(0),
+            retired_pages: Some(2),
+            retired_pages_pending: Some(false),
+            remapped_rows_pending: Some(false),
+            remapped_rows_failure: Some(false),
+            throttle_reasons: vec!["sw_power_cap".to_string()],
+            ..GpuReliability::default()
+        };
+
+        assert_eq!(evaluate_reliability(&reliability), (HealthStatus::Healthy, Vec::new()));
+    }
+
+    #[test]
+    fn uncorrectable_ecc_errors_are_unhealthy() {
+        let reliability = GpuReliability {
+            ecc_volatile_uncorrected: Some(2),
+            ..GpuReliability::default()
+        };
+
+        let (status, issues) = evaluate_reliability(&reliability);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert_eq!(issues, vec!["2 uncorrectable ECC errors since driver load"]);
+    }
+
+    #[test]
+    fn pending_remap_and_hardware_throttling_are_degraded() {
+        let reliability = GpuReliability {
+            remapped_rows_pending: Some(true),
+            throttle_reasons: vec!["sw_power_cap".to_string(), "hw_thermal_slowdown".to_string()],
+            ..GpuReliability::default()
+        };
+
+        let (status, issues) = evaluate_reliability(&reliability);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert_eq!(issues, vec![
+            "Row remapping pending, GPU reset required",
+            "Clocks throttled: hw_thermal_slowdown",
+        ]);
+    }
+
+    #[test]
+    fn retired_pages_at_rma_threshold_are_unhealthy() {
+        let below = GpuReliability { retired_pages: Some(59), ..GpuReliability::default() };
+        let at = GpuReliability { retired_pages: Some(60), ..GpuReliability::default() };
+
+        assert_eq!(evaluate_reliability(&below).0, HealthStatus::Healthy);
+        assert_eq!(evaluate_reliability(&at).0, HealthStatus::Unhealthy);
+    }
+
+    #[test]
+    fn xid_severity_follows_the_error_code() {
+        let events = parse_xid_events(DMESG_XID);
+
+        let application_faults = GpuReliability { xid_events: events[..2].to_vec(), ..GpuReliability::default() };
+        assert_eq!(evaluate_reliability(&application_faults), (HealthStatus::Healthy, Vec::new()));
+
+        let contained = GpuReliability { xid_events: events[2..4].to_vec(), ..GpuReliability::default() };
+        let (status, issues) = evaluate_reliability(&contained);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert_eq!(issues, vec![
+            "XID 63 (ECC page retirement or row remapping recorded) x1 in kernel log",
+            "XID 94 (contained ECC error) x1 in kernel log",
+        ]);
+
+        let fallen_off = GpuReliability { xid_events: events[4..].to_vec(), ..GpuReliability::default() };
+        let (status, issues) = evaluate_reliability(&fallen_off);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert_eq!(issues, vec!["XID 79 (GPU has fallen off the bus) x1 in kernel log"]);
+    }
+
+    #[test]
+    fn dcgm_last_xid_counts_unless_already_in_kernel_log() {
+        let (status, issues) = evaluate_reliability(&GpuReliability { last_xid: Some(79), ..GpuReliability::default() });
+        assert_eq!(status, HealthStatus::Unhealthy);
+        assert_eq!(issues, vec!["XID 79 (GPU has fallen off the bus) last reported by DCGM"]);
+
+        let events = parse_xid_events(DMESG_XID);
+        let both = GpuReliability { xid_events: events[4..].to_vec(), last_xid: Some(79), ..GpuReliability::default() };
+        assert_eq!(evaluate_reliability(&both).1.len(), 1);
+
+        let application_fault = GpuReliability { last_xid: Some(13), ..GpuReliability::default() };
+        assert_eq!(evaluate_reliability(&application_fault).0, HealthStatus::Healthy);
+    }
+}
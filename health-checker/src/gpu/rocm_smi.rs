@@ -0,0 +1,251 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use super::reliability::GpuReliability;
+use super::GpuInfo;
+
+/// Flags requesting every field `parse_gpu_infos` reads, for `rocm-smi --json`
+pub const ROCM_SMI_ARGS: &[&str] = &[
+    "--showproductname",
+    "--showuniqueid",
+    "--showbus",
+    "--showuse",
+    "--showmemuse",
+    "--showmeminfo",
+    "vram",
+    "--showtemp",
+    "--showpower",
+    "--showmaxpower",
+    "--showfan",
+    "--showdriverversion",
+    "--json",
+];
+
+/// Parse `rocm-smi --json` output into one `GpuInfo` per `cardN` entry, ordered by card index.
+/// Key names differ between ROCm releases, so each field accepts every known spelling.
+pub fn parse_gpu_infos(json: &str) -> Result<Vec<GpuInfo>> {
+    let output: Map<String, Value> = serde_json::from_str(json).context("Invalid rocm-smi JSON")?;
+
+    let driver_version = output.get("system")
+        .and_then(|system| system.get("Driver version"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut cards: Vec<(u32, &Map<String, Value>)> = output.iter()
+        .filter_map(|(key, card)| Some((key.strip_prefix("card")?.parse().ok()?, card.as_object()?)))
+        .collect();
+    cards.sort_by_key(|(index, _)| *index);
+
+    cards.into_iter()
+        .map(|(index, card)| parse_card(index, card, &driver_version))
+        .collect()
+}
+
+fn parse_card(id: u32, card: &Map<String, Value>, driver_version: &str) -> Result<GpuInfo> {
+    let name = field(card, &["Card Series", "Card series", "Device Name", "Card SKU"])
+        .ok_or_else(|| anyhow!("card{} has no product name", id))?;
+
+    let temperature_edge = number(card, &["Temperature (Sensor edge) (C)"]);
+    let temperature_junction = number(card, &["Temperature (Sensor junction) (C)"]);
+    let memory_total = number(card, &["VRAM Total Memory (B)"]).unwrap_or(0.0) as u64;
+    let memory_used = number(card, &["VRAM Total Used Memory (B)"]).unwrap_or(0.0) as u64;
+
+    Ok(GpuInfo {
+        id,
+        name,
+        uuid: field(card, &["Unique ID"]),
+        pci_bus_id: field(card, &["PCI Bus"]),
+        driver_version: driver_version.to_string(),
+        cuda_version: None,
+        memory_total,
+        memory_used,
+        memory_free: memory_total.saturating_sub(memory_used),
+        utilization_gpu: number(card, &["GPU use (%)"]).unwrap_or(0.0) as u32,
+        utilization_memory: number(card, &["GPU Memory Read/Write Activity (%)", "GPU memory use (%)"])
+            .unwrap_or(0.0) as u32,
+        // MI300 parts have no edge sensor and report only junction and memory temperatures
+        temperature: temperature_edge.unwrap_or(0.0) as u32,
+        temperature_junction: temperature_junction.map(|temp| temp as u32),
+        temperature_memory: number(card, &["Temperature (Sensor memory) (C)"]).map(|temp| temp as u32),
+        power_usage: number(card, &["Average Graphics Package Power (W)", "Current Socket Graphics Package Power (W)"])
+            .unwrap_or(0.0) as f32,
+        power_limit: number(card, &["Max Graphics Package Power (W)"]).unwrap_or(0.0) as f32,
+        fan_speed: number(card, &["Fan speed (%)"]).map(|speed| speed as u32),
+        processes: Vec::new(),
+        reliability: GpuReliability::default(),
+        mig_mode: None,
+        mig_devices: Vec::new(),
+        allocations: Vec::new(),
+    })
+}
+
+/// First of `keys` present with a value other than `N/A`
+fn field(card: &Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| card.get(*key)?.as_str())
+        .map(str::trim)
+        .find(|value| !value.is_empty() && *value != "N/A")
+        .map(str::to_string)
+}
+
+fn number(card: &Map<String, Value>, keys: &[&str]) -> Option<f64> {
+    keys.iter()
+        .filter_map(|key| field(card, &[key]))
+        .find_map(|value| value.parse().ok())
+}
+
+/// ECC counts from the amdgpu RAS interface under `device_dir` (e.g. `/sys/bus/pci/devices/0000:03:00.0`).
+/// Each `ras/<block>_err_count` file holds `ue: <n>` and `ce: <n>` lines counted since boot.
+pub fn read_ras_reliability(device_dir: &Path) -> GpuReliability {
+    let Ok(entries) = std::fs::read_dir(device_dir.join("ras")) else {
+        return GpuReliability::default();
+    };
+
+    let mut corrected = 0;
+    let mut uncorrected = 0;
+    let mut found = false;
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if !entry.file_name().to_string_lossy().ends_with("_err_count") {
+            continue;
+        }
+        let Ok(contents) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+
+        for line in contents.lines() {
+            let Some((kind, count)) = line.split_once(':') else {
+                continue;
+            };
+            let Ok(count) = count.trim().parse::<u64>() else {
+                continue;
+            };
+            match kind.trim() {
+                "ue" => uncorrected += count,
+                "ce" => corrected += count,
+                _ => continue,
+            }
+            found = true;
+        }
+    }
+
+    if !found {
+        return GpuReliability::default();
+    }
+
+    GpuReliability {
+        ecc_enabled: Some(true),
+        ecc_volatile_corrected: Some(corrected),
+        ecc_volatile_uncorrected: Some(uncorrected),
+        ..GpuReliability::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GpuHealthPolicy;
+    use crate::gpu::GpuMonitor;
+    use crate::HealthStatus;
+
+    const MI210_ROCM_57: &str = include_str!("../../tests/fixtures/rocm-smi/mi210-rocm-5.7.json");
+    const MI300X_ROCM_61: &str = include_str!("../../tests/fixtures/rocm-smi/mi300x-rocm-6.1.json");
+
+    #[test]
+    fn parses_every_card_in_index_order() {
+        let gpus = parse_gpu_infos(MI210_ROCM_57).unwrap();
+        assert_eq!(gpus.len(), 2);
+
+        let gpu = &gpus[0];
+        assert_eq!(gpu.id, 0);
+        assert_eq!(gpu.name, "AMD Instinct MI210");
+        assert_eq!(gpu.uuid.as_deref(), Some("0x8d3a2c9f4b1e7a05"));
+        assert_eq!(gpu.pci_bus_id.as_deref(), Some("0000:03:00.0"));
+        assert_eq!(gpu.driver_version, "6.2.4");
+        assert_eq!(gpu.memory_total, 68702699520);
+        assert_eq!(gpu.memory_used, 52613349376);
+        assert_eq!(gpu.memory_free, 68702699520 - 52613349376);
+        assert_eq!(gpu.utilization_gpu, 96);
+        assert_eq!(gpu.utilization_memory, 38);
+        assert_eq!(gpu.temperature, 47);
+        assert_eq!(gpu.temperature_junction, Some(63));
+        assert_eq!(gpu.temperature_memory, Some(58));
+        assert_eq!(gpu.power_usage, 241.0);
+        assert_eq!(gpu.power_limit, 300.0);
+        assert_eq!(gpu.fan_speed, None);
+
+        assert_eq!(gpus[1].id, 1);
+        assert_eq!(gpus[1].pci_bus_id.as_deref(), Some("0000:83:00.0"));
+    }
+
+    #[test]
+    fn parses_rocm_6_keys_without_edge_sensor() {
+        let gpus = parse_gpu_infos(MI300X_ROCM_61).unwrap();
+        assert_eq!(gpus.len(), 1);
+
+        let gpu = &gpus[0];
+        assert_eq!(gpu.name, "AMD Instinct MI300X OAM");
+        assert_eq!(gpu.driver_version, "6.7.0");
+        assert_eq!(gpu.temperature, 0);
+        assert_eq!(gpu.temperature_junction, Some(97));
+        assert_eq!(gpu.power_usage, 731.0);
+        assert_eq!(gpu.power_limit, 750.0);
+        assert_eq!(gpu.utilization_memory, 64);
+        assert_eq!(gpu.memory_total, 206141652992);
+    }
+
+    #[test]
+    fn junction_temperature_has_its_own_thresholds() {
+        let policy = GpuHealthPolicy::default();
+        let mut gpu = parse_gpu_infos(MI300X_ROCM_61).unwrap().remove(0);
+
+        // 97°C is a normal junction reading under load; only memory and power stand out
+        let (status, details) = GpuMonitor::evaluate_gpu_health(&policy, &gpu);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(!details.contains("temperature"), "{}", details);
+
+        gpu.temperature_junction = Some(104);
+        gpu.memory_used = gpu.memory_total / 2;
+        gpu.power_usage = 500.0;
+        let (status, details) = GpuMonitor::evaluate_gpu_health(&policy, &gpu);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: High junction temperature: 104°C"), "{}", details);
+
+        gpu.temperature_junction = Some(112);
+        assert_eq!(GpuMonitor::evaluate_gpu_health(&policy, &gpu).0, HealthStatus::Unhealthy);
+    }
+
+    #[test]
+    fn edge_temperature_ignores_the_junction_reading() {
+        let mut gpu = parse_gpu_infos(MI210_ROCM_57).unwrap().remove(0);
+        gpu.temperature_junction = Some(98);
+
+        let (status, details) = GpuMonitor::evaluate_gpu_health(&GpuHealthPolicy::default(), &gpu);
+        assert_eq!(status, HealthStatus::Healthy, "{}", details);
+        assert!(details.contains("Temp: 47°C (junction 98°C)"), "{}", details);
+    }
+
+    #[test]
+    fn rejects_non_json_output() {
+        assert!(parse_gpu_infos("ERROR: GPU[0] : Unable to read sysfs").is_err());
+    }
+
+    #[test]
+    fn sums_ras_error_counts_across_blocks() {
+        let device_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/amdgpu-sysfs/0000:03:00.0");
+        let reliability = read_ras_reliability(&device_dir);
+
+        assert_eq!(reliability.ecc_enabled, Some(true));
+        assert_eq!(reliability.ecc_volatile_corrected, Some(120));
+        assert_eq!(reliability.ecc_volatile_uncorrected, Some(2));
+    }
+
+    #[test]
+    fn missing_ras_interface_reports_no_ecc_data() {
+        let reliability = read_ras_reliability(Path::new("/nonexistent"));
+        assert_eq!(reliability.ecc_enabled, None);
+        assert_eq!(reliability.ecc_volatile_uncorrected, None);
+    }
+}
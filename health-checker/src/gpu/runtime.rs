@@ -0,0 +1,600 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::info;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::{policy, tool_output, GpuInfo};
+use crate::docker::DockerRuntimes;
+use crate::{Backend, HealthCheckResult, HealthStatus};
+
+const CONTAINER_CLI: &str = "nvidia-container-cli";
+/// `nvidia-container-cli info` opens every GPU and hangs with the driver, so it is killed after this
+const CONTAINER_CLI_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What stands between GPUs that look healthy on the host and containers that can use them
+pub struct ContainerRuntime {
+    /// Runtimes of the local Docker engine; `None` when Docker is disabled or not installed
+    pub docker: Option<Result<DockerRuntimes>>,
+    pub container_cli: Result<ContainerCli, ContainerCliError>,
+    /// NVIDIA CDI specs; specs of other vendors are skipped
+    pub cdi_specs: Vec<CdiSpec>,
+    /// GPU UUIDs listed inside the probe container, when probing is enabled
+    pub probe: Option<Result<Vec<String>>>,
+    pub collection_time: Duration,
+}
+
+/// `nvidia-container-cli` from libnvidia-container, which every NVIDIA container runtime mode calls
+#[derive(Debug, Clone)]
+pub struct ContainerCli {
+    pub version: Option<String>,
+    /// GPUs the library can open, as listed by `nvidia-container-cli info`
+    pub gpu_uuids: Vec<String>,
+}
+
+/// Why `nvidia-container-cli` gave no GPU list
+#[derive(Debug)]
+pub enum ContainerCliError {
+    /// Missing, failing or hung, which breaks containers the same way
+    Broken(String),
+    /// The checker itself could not run it, e.g. without permission to open the driver
+    Unchecked(String),
+}
+
+/// A CDI spec file and what it declares
+#[derive(Debug)]
+pub struct CdiSpec {
+    pub path: PathBuf,
+    pub contents: Result<CdiSpecContents>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CdiSpecContents {
+    /// e.g. `nvidia.com/gpu`
+    pub kind: String,
+    /// Device names such as `0`, `GPU-<uuid>`, `0:1` or `all`
+    pub devices: Vec<String>,
+    /// Driver versions of the `libcuda.so.<version>` mounts, which go stale on driver upgrades
+    pub driver_versions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawCdiSpec {
+    kind: String,
+    #[serde(default)]
+    devices: Vec<RawCdiDevice>,
+    container_edits: Option<RawContainerEdits>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawCdiDevice {
+    name: String,
+    container_edits: Option<RawContainerEdits>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawContainerEdits {
+    #[serde(default)]
+    mounts: Vec<RawMount>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMount {
+    host_path: String,
+}
+
+/// `cli-version: 1.14.6`, or `version: 1.0.0` from libnvidia-container before 1.1
+pub fn parse_container_cli_version(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        matches!(key.trim(), "cli-version" | "version").then(|| value.trim().to_string())
+    })
+}
+
+/// GPU UUIDs from `GPU UUID:` lines of `nvidia-container-cli info`
+pub fn parse_container_cli_info(output: &str) -> Vec<String> {
+    output.lines()
+        .filter_map(|line| line.trim().strip_prefix("GPU UUID:"))
+        .map(|uuid| uuid.trim().to_string())
+        .filter(|uuid| uuid.starts_with("GPU-"))
+        .collect()
+}
+
+/// GPU UUIDs from `nvidia-smi -L`, e.g. `GPU 0: NVIDIA A100-SXM4-80GB (UUID: GPU-...)`; MIG devices are skipped
+pub fn parse_gpu_list(output: &str) -> Vec<String> {
+    output.lines()
+        .filter_map(|line| line.split_once("(UUID: "))
+        .map(|(_, rest)| rest.trim_end().trim_end_matches(')').to_string())
+        .filter(|uuid| uuid.starts_with("GPU-"))
+        .collect()
+}
+
+pub fn parse_cdi_spec(path: &Path, contents: &str) -> Result<CdiSpecContents> {
+    let raw: RawCdiSpec = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(contents)?
+    } else {
+        serde_yaml::from_str(contents)?
+    };
+
+    let mounts = raw.container_edits.iter()
+        .chain(raw.devices.iter().filter_map(|device| device.container_edits.as_ref()))
+        .flat_map(|edits| &edits.mounts);
+    let mut driver_versions: Vec<String> = mounts
+        .filter_map(|mount| mount.host_path.rsplit('/').next()?.strip_prefix("libcuda.so."))
+        .filter(|version| version.contains('.'))
+        .map(str::to_string)
+        .collect();
+    driver_versions.sort();
+    driver_versions.dedup();
+
+    Ok(CdiSpecContents {
+        kind: raw.kind,
+        devices: raw.devices.into_iter().map(|device| device.name).collect(),
+        driver_versions,
+    })
+}
+
+/// NVIDIA CDI specs (`*.yaml`, `*.yml`, `*.json`) in `dirs`; missing directories are skipped
+pub fn load_cdi_specs(dirs: &[PathBuf]) -> Vec<CdiSpec> {
+    let mut specs = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml" || ext == "json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    specs.push(CdiSpec { path, contents: Err(e.into()) });
+                    continue;
+                }
+            };
+            let contents = parse_cdi_spec(&path, &text);
+            // An unparsable spec cannot be attributed to a vendor, so it is kept if it mentions NVIDIA
+            let keep = match &contents {
+                Ok(spec) => spec.kind.starts_with("nvidia.com/"),
+                Err(_) => text.contains("nvidia.com/"),
+            };
+            if keep {
+                specs.push(CdiSpec { path, contents });
+            }
+        }
+    }
+    specs
+}
+
+/// Errors of an unprivileged checker, which say nothing about the toolkit
+fn is_permission_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("permission denied") || message.contains("operation not permitted")
+}
+
+async fn collect_container_cli() -> Result<ContainerCli, ContainerCliError> {
+    let timed_out = |args: &str| {
+        ContainerCliError::Broken(format!("{} did not finish within {}s", args, CONTAINER_CLI_TIMEOUT.as_secs()))
+    };
+
+    let version = match tool_output(CONTAINER_CLI, &["--version"], CONTAINER_CLI_TIMEOUT).await {
+        Ok(Some(output)) => parse_container_cli_version(&String::from_utf8_lossy(&output.stdout)),
+        Ok(None) => return Err(timed_out("--version")),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(ContainerCliError::Broken("not found, nvidia-container-toolkit is not installed".to_string()));
+        }
+        Err(e) => return Err(ContainerCliError::Unchecked(format!("cannot be executed: {}", e))),
+    };
+
+    let output = tool_output(CONTAINER_CLI, &["info"], CONTAINER_CLI_TIMEOUT)
+        .await
+        .map_err(|e| ContainerCliError::Unchecked(format!("info cannot be executed: {}", e)))?
+        .ok_or_else(|| timed_out("info"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = format!("info failed: {}", stderr.lines().next().unwrap_or("no output").trim());
+        return Err(if is_permission_error(&stderr) {
+            ContainerCliError::Unchecked(message)
+        } else {
+            ContainerCliError::Broken(message)
+        });
+    }
+
+    Ok(ContainerCli {
+        version,
+        gpu_uuids: parse_container_cli_info(&String::from_utf8_lossy(&output.stdout)),
+    })
+}
+
+fn describe_gpu(gpu_info: &GpuInfo) -> String {
+    match &gpu_info.uuid {
+        Some(uuid) => format!("GPU {} ({})", gpu_info.id, uuid),
+        None => format!("GPU {}", gpu_info.id),
+    }
+}
+
+/// GPUs in `gpu_infos` whose UUID is not in `seen`; GPUs without a known UUID cannot be compared
+fn unseen_gpus<'a>(gpu_infos: &'a [GpuInfo], seen: &[String]) -> Vec<&'a GpuInfo> {
+    gpu_infos.iter()
+        .filter(|gpu_info| gpu_info.uuid.as_ref().is_some_and(|uuid| !seen.contains(uuid)))
+        .collect()
+}
+
+/// Problems that make `spec` unusable for the detected GPUs, e.g. devices of a replaced GPU or
+/// libraries of the previous driver
+pub fn cdi_spec_differences(spec: &CdiSpecContents, gpu_infos: &[GpuInfo]) -> Vec<String> {
+    let mut differences = Vec::new();
+    if spec.kind != "nvidia.com/gpu" {
+        return differences;
+    }
+
+    for gpu_info in gpu_infos {
+        let by_index = spec.devices.contains(&gpu_info.id.to_string());
+        let by_uuid = gpu_info.uuid.as_ref().is_some_and(|uuid| spec.devices.contains(uuid));
+        if !by_index && !by_uuid {
+            differences.push(format!("no device for {}", describe_gpu(gpu_info)));
+        }
+    }
+
+    for device in &spec.devices {
+        let installed = if device.starts_with("GPU-") {
+            gpu_infos.iter().any(|gpu_info| gpu_info.uuid.as_ref() == Some(device))
+        } else if let Ok(index) = device.parse::<u32>() {
+            gpu_infos.iter().any(|gpu_info| gpu_info.id == index)
+        } else {
+            // `all`, MIG devices and custom names are not compared
+            true
+        };
+        if !installed {
+            differences.push(format!("device {} is not installed", device));
+        }
+    }
+
+    // Empty when the source does not report it, e.g. dcgm-exporter without a driver version label
+    let driver_version = gpu_infos.first().map(|gpu_info| &gpu_info.driver_version).filter(|version| !version.is_empty());
+    if let Some(driver_version) = driver_version {
+        for version in spec.driver_versions.iter().filter(|version| *version != driver_version) {
+            differences.push(format!("generated for driver {}, installed is {}", version, driver_version));
+        }
+    }
+
+    differences
+}
+
+/// Whether containers can use the NVIDIA GPUs: Docker must have the nvidia runtime (or CDI specs
+/// must exist), nvidia-container-cli must see every GPU, CDI specs must match the installed GPUs
+/// and driver, and the probe container, when enabled, must list every GPU.
+/// `gpu_infos` is `None` when the GPUs could not be listed, which skips the comparisons.
+pub fn check_container_runtime(runtime: &ContainerRuntime, gpu_infos: Option<&[GpuInfo]>) -> HealthCheckResult {
+    let mut status = HealthStatus::Healthy;
+    let mut issues = Vec::new();
+    let mut summary = Vec::new();
+
+    let mut cdi_regenerate = false;
+    let mut usable_cdi = false;
+    for spec in &runtime.cdi_specs {
+        match &spec.contents {
+            Ok(contents) => {
+                let differences = gpu_infos
+                    .map(|gpu_infos| cdi_spec_differences(contents, gpu_infos))
+                    .unwrap_or_default();
+                usable_cdi |= contents.kind == "nvidia.com/gpu" && differences.is_empty();
+                if !differences.is_empty() {
+                    issues.push(format!("CDI spec {}: {}", spec.path.display(), differences.join(", ")));
+                    policy::escalate(&mut status, HealthStatus::Unhealthy);
+                    cdi_regenerate = true;
+                }
+                summary.push(format!("CDI {} ({} devices)", spec.path.display(), contents.devices.len()));
+            }
+            Err(e) => {
+                issues.push(format!("Cannot parse CDI spec {}: {}", spec.path.display(), e));
+                policy::escalate(&mut status, HealthStatus::Degraded);
+            }
+        }
+    }
+    if runtime.cdi_specs.is_empty() {
+        summary.push("no CDI specs".to_string());
+    }
+
+    match &runtime.docker {
+        Some(Ok(docker)) => {
+            summary.insert(0, format!(
+                "Docker runtimes: {} (default {})",
+                docker.runtimes.join(", "),
+                docker.default_runtime.as_deref().unwrap_or("unknown")
+            ));
+            if !docker.runtimes.iter().any(|name| name == "nvidia") {
+                if usable_cdi {
+                    issues.push("nvidia runtime not registered with Docker, only CDI device requests can use GPUs".to_string());
+                    policy::escalate(&mut status, HealthStatus::Degraded);
+                } else {
+                    issues.push("nvidia runtime not registered with Docker".to_string());
+                    policy::escalate(&mut status, HealthStatus::Unhealthy);
+                }
+            }
+        }
+        Some(Err(e)) => summary.insert(0, format!("Docker runtimes unknown: {}", e)),
+        None => summary.insert(0, "Docker not checked".to_string()),
+    }
+
+    match &runtime.container_cli {
+        Ok(cli) => {
+            summary.insert(1, format!(
+                "{} {} sees {} GPUs",
+                CONTAINER_CLI,
+                cli.version.as_deref().unwrap_or("unknown version"),
+                cli.gpu_uuids.len()
+            ));
+            for gpu_info in unseen_gpus(gpu_infos.unwrap_or_default(), &cli.gpu_uuids) {
+                issues.push(format!("{} does not see {}", CONTAINER_CLI, describe_gpu(gpu_info)));
+                policy::escalate(&mut status, HealthStatus::Unhealthy);
+            }
+        }
+        Err(ContainerCliError::Broken(e)) => {
+            issues.push(format!("{} {}", CONTAINER_CLI, e));
+            policy::escalate(&mut status, HealthStatus::Unhealthy);
+        }
+        Err(ContainerCliError::Unchecked(e)) => {
+            issues.push(format!("{} {}", CONTAINER_CLI, e));
+            if status == HealthStatus::Healthy {
+                status = HealthStatus::Unknown;
+            }
+        }
+    }
+
+    match &runtime.probe {
+        Some(Ok(uuids)) => {
+            summary.push(format!("probe container sees {} GPUs", uuids.len()));
+            for gpu_info in unseen_gpus(gpu_infos.unwrap_or_default(), uuids) {
+                issues.push(format!("Probe container does not see {}", describe_gpu(gpu_info)));
+                policy::escalate(&mut status, HealthStatus::Unhealthy);
+            }
+        }
+        Some(Err(e)) => {
+            issues.push(format!("Probe container failed: {}", e));
+            policy::escalate(&mut status, HealthStatus::Unhealthy);
+        }
+        None => {}
+    }
+
+    let mut details = summary.join(", ");
+    if !issues.is_empty() {
+        details.push_str(&format!(" - Issues: {}", issues.join("; ")));
+    }
+    if cdi_regenerate {
+        details.push_str(" (regenerate with `nvidia-ctk cdi generate`)");
+    }
+
+    HealthCheckResult {
+        service_name: "NVIDIA Container Runtime".to_string(),
+        status,
+        response_time_ms: runtime.collection_time.as_millis() as u64,
+        details,
+        timestamp: Utc::now(),
+        error_message: None,
+    }
+}
+
+impl crate::HealthChecker {
+    /// Container runtime state on this host, or `None` when there are no NVIDIA GPUs to run containers on
+    pub(crate) async fn container_runtime(&self) -> Option<ContainerRuntime> {
+        let Backend::Available(gpu_monitor) = self.gpu_monitor().await else {
+            return None;
+        };
+        if !gpu_monitor.nvidia_available() {
+            return None;
+        }
+        let start_time = Instant::now();
+        let config = &self.config.gpu.container_runtime;
+
+        // Runtimes and the toolkit are only meaningful for the engine on this machine
+        let local_client = self.local_docker_client().await;
+        let docker = match local_client {
+            Some(client) => Some(client.runtimes().await),
+            None => None,
+        };
+
+        let probe = match (config.probe, local_client) {
+            (true, Some(client)) => {
+                let nvidia_runtime = docker.as_ref()
+                    .and_then(|docker| docker.as_ref().ok())
+                    .is_some_and(|docker| docker.runtimes.iter().any(|name| name == "nvidia"));
+                info!("Starting GPU probe container from {}", config.probe_image);
+                let output = client.run_gpu_probe(
+                    &config.probe_image,
+                    &["nvidia-smi", "-L"],
+                    nvidia_runtime.then_some("nvidia"),
+                    Duration::from_secs(config.probe_timeout_secs),
+                ).await;
+                Some(output.map(|output| parse_gpu_list(&output)))
+            }
+            (true, None) => Some(Err(anyhow!("no local Docker engine to start it on"))),
+            (false, _) => None,
+        };
+
+        let container_cli = collect_container_cli().await;
+        let cdi_spec_dirs = config.cdi_spec_dirs.clone();
+        let cdi_specs = tokio::task::spawn_blocking(move || load_cdi_specs(&cdi_spec_dirs))
+            .await
+            .unwrap_or_default();
+
+        Some(ContainerRuntime {
+            docker,
+            container_cli,
+            cdi_specs,
+            probe,
+            collection_time: start_time.elapsed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A100_DRIVER_535: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-driver-535.xml");
+    const CLI_VERSION: &str = include_str!("../../tests/fixtures/container-runtime/nvidia-container-cli-version-1.14.txt");
+    const CLI_INFO: &str = include_str!("../../tests/fixtures/container-runtime/nvidia-container-cli-info-1.14.txt");
+    const PROBE_OUTPUT: &str = include_str!("../../tests/fixtures/container-runtime/nvidia-smi-L-probe.txt");
+
+    fn fixture_dir(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/container-runtime").join(name)
+    }
+
+    fn gpu_infos() -> Vec<GpuInfo> {
+        super::super::nvidia_smi::parse_gpu_infos(A100_DRIVER_535).unwrap()
+    }
+
+    fn ready_runtime() -> ContainerRuntime {
+        ContainerRuntime {
+            docker: Some(Ok(DockerRuntimes {
+                default_runtime: Some("runc".to_string()),
+                runtimes: vec!["io.containerd.runc.v2".to_string(), "nvidia".to_string(), "runc".to_string()],
+            })),
+            container_cli: Ok(ContainerCli {
+                version: parse_container_cli_version(CLI_VERSION),
+                gpu_uuids: parse_container_cli_info(CLI_INFO),
+            }),
+            cdi_specs: load_cdi_specs(&[fixture_dir("cdi")]),
+            probe: None,
+            collection_time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn parses_container_cli_output() {
+        assert_eq!(parse_container_cli_version(CLI_VERSION).as_deref(), Some("1.14.6"));
+        assert_eq!(parse_container_cli_version("version: 1.0.0\nbuild date: 2019-01-01"), Some("1.0.0".to_string()));
+        assert_eq!(parse_container_cli_info(CLI_INFO), vec![
+            "GPU-5f2e6b1c-8a3d-4e7f-9b21-0c4d5e6f7a81",
+            "GPU-9c1a7d3e-2b4f-4a6c-8e0d-1f2a3b4c5d62",
+        ]);
+        assert_eq!(parse_gpu_list(PROBE_OUTPUT), vec!["GPU-5f2e6b1c-8a3d-4e7f-9b21-0c4d5e6f7a81"]);
+    }
+
+    #[test]
+    fn loads_nvidia_cdi_specs_only() {
+        let specs = load_cdi_specs(&[fixture_dir("cdi"), fixture_dir("missing")]);
+        assert_eq!(specs.len(), 1);
+        let contents = specs[0].contents.as_ref().unwrap();
+        assert_eq!(contents.kind, "nvidia.com/gpu");
+        assert_eq!(contents.devices, vec![
+            "0",
+            "1",
+            "GPU-5f2e6b1c-8a3d-4e7f-9b21-0c4d5e6f7a81",
+            "GPU-9c1a7d3e-2b4f-4a6c-8e0d-1f2a3b4c5d62",
+            "all",
+        ]);
+        assert_eq!(contents.driver_versions, vec!["535.183.01"]);
+        assert!(cdi_spec_differences(contents, &gpu_infos()).is_empty());
+    }
+
+    #[test]
+    fn stale_cdi_spec_lists_replaced_gpu_and_old_driver() {
+        let specs = load_cdi_specs(&[fixture_dir("cdi-stale")]);
+        assert_eq!(specs.len(), 2);
+        let stale = specs.iter().find(|spec| spec.path.ends_with("nvidia.yaml")).unwrap();
+        assert_eq!(cdi_spec_differences(stale.contents.as_ref().unwrap(), &gpu_infos()), vec![
+            "device 2 is not installed",
+            "device GPU-1e4b8f20-6d7a-4c3b-9a5e-7f8091a2b3c4 is not installed",
+            "generated for driver 535.104.05, installed is 535.183.01",
+        ]);
+        let truncated = specs.iter().find(|spec| spec.path.ends_with("nvidia-truncated.json")).unwrap();
+        assert!(truncated.contents.is_err());
+
+        let result = check_container_runtime(&ContainerRuntime { cdi_specs: specs, ..ready_runtime() }, Some(&gpu_infos()));
+        assert_eq!(result.status, HealthStatus::Unhealthy);
+        assert!(result.details.contains("Cannot parse CDI spec"), "{}", result.details);
+        assert!(result.details.ends_with("(regenerate with `nvidia-ctk cdi generate`)"), "{}", result.details);
+    }
+
+    #[test]
+    fn ready_runtime_is_healthy() {
+        let result = check_container_runtime(&ready_runtime(), Some(&gpu_infos()));
+        assert_eq!(result.status, HealthStatus::Healthy, "{}", result.details);
+        assert!(result.details.starts_with(
+            "Docker runtimes: io.containerd.runc.v2, nvidia, runc (default runc), nvidia-container-cli 1.14.6 sees 2 GPUs"
+        ), "{}", result.details);
+    }
+
+    #[test]
+    fn missing_nvidia_runtime_is_degraded_only_with_cdi() {
+        let without_runtime = || Some(Ok(DockerRuntimes {
+            default_runtime: Some("runc".to_string()),
+            runtimes: vec!["runc".to_string()],
+        }));
+
+        let runtime = ContainerRuntime { docker: without_runtime(), ..ready_runtime() };
+        assert_eq!(check_container_runtime(&runtime, Some(&gpu_infos())).status, HealthStatus::Degraded);
+
+        let runtime = ContainerRuntime { docker: without_runtime(), cdi_specs: Vec::new(), ..ready_runtime() };
+        let result = check_container_runtime(&runtime, Some(&gpu_infos()));
+        assert_eq!(result.status, HealthStatus::Unhealthy);
+        assert!(result.details.contains("nvidia runtime not registered with Docker"), "{}", result.details);
+    }
+
+    #[test]
+    fn missing_toolkit_and_unseen_gpus_are_unhealthy() {
+        let runtime = ContainerRuntime {
+            container_cli: Err(ContainerCliError::Broken("not found, nvidia-container-toolkit is not installed".to_string())),
+            ..ready_runtime()
+        };
+        let result = check_container_runtime(&runtime, Some(&gpu_infos()));
+        assert_eq!(result.status, HealthStatus::Unhealthy);
+        assert!(result.details.contains("nvidia-container-cli not found"), "{}", result.details);
+
+        let runtime = ContainerRuntime { probe: Some(Ok(parse_gpu_list(PROBE_OUTPUT))), ..ready_runtime() };
+        let result = check_container_runtime(&runtime, Some(&gpu_infos()));
+        assert_eq!(result.status, HealthStatus::Unhealthy);
+        assert!(result.details.contains(
+            "Probe container does not see GPU 1 (GPU-9c1a7d3e-2b4f-4a6c-8e0d-1f2a3b4c5d62)"
+        ), "{}", result.details);
+    }
+
+    #[test]
+    fn unknown_driver_version_is_not_compared() {
+        let specs = load_cdi_specs(&[fixture_dir("cdi-stale")]);
+        let stale = specs.iter().find(|spec| spec.path.ends_with("nvidia.yaml")).unwrap();
+        let mut gpu_infos = gpu_infos();
+        for gpu_info in &mut gpu_infos {
+            gpu_info.driver_version.clear();
+        }
+        let differences = cdi_spec_differences(stale.contents.as_ref().unwrap(), &gpu_infos);
+        assert!(differences.iter().all(|difference| !difference.starts_with("generated for driver")), "{:?}", differences);
+    }
+
+    #[test]
+    fn container_cli_unusable_by_checker_is_unknown() {
+        assert!(is_permission_error("nvidia-container-cli: initialization error: open failed: /dev/nvidiactl: permission denied"));
+        assert!(!is_permission_error("nvidia-container-cli: initialization error: nvml error: driver not loaded"));
+
+        let runtime = ContainerRuntime {
+            container_cli: Err(ContainerCliError::Unchecked("info failed: permission denied".to_string())),
+            ..ready_runtime()
+        };
+        let result = check_container_runtime(&runtime, Some(&gpu_infos()));
+        assert_eq!(result.status, HealthStatus::Unknown, "{}", result.details);
+
+        let runtime = ContainerRuntime {
+            container_cli: Err(ContainerCliError::Broken("info did not finish within 30s".to_string())),
+            ..ready_runtime()
+        };
+        assert_eq!(check_container_runtime(&runtime, Some(&gpu_infos())).status, HealthStatus::Unhealthy);
+    }
+
+    #[test]
+    fn comparisons_are_skipped_without_gpu_list() {
+        let runtime = ContainerRuntime { cdi_specs: load_cdi_specs(&[fixture_dir("cdi-stale")]), ..ready_runtime() };
+        let result = check_container_runtime(&runtime, None);
+        // Only the truncated spec remains a problem
+        assert_eq!(result.status, HealthStatus::Degraded, "{}", result.details);
+    }
+}
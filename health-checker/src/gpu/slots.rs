@@ -0,0 +1,366 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::error;
+use std::collections::{BTreeMap, HashMap};
+
+use super::GpuInfo;
+use crate::{HealthCheckResult, HealthStatus};
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Resource slots the manager has recorded for this host's agent
+pub struct AdvertisedSlots {
+    pub agent_id: String,
+    /// Slot name to amount, e.g. `cuda.device` to `8`; an error when the manager could not be asked
+    pub slots: Result<HashMap<String, f64>>,
+}
+
+/// Slots an accelerator plugin with slot prefix `prefix` (`cuda`, `rocm`) should advertise for `gpu_infos`.
+/// MIG-enabled GPUs are advertised per profile as `<prefix>.device:<profile>-mig` instead of whole devices.
+pub fn detected_slots(prefix: &str, gpu_infos: &[GpuInfo], shares_per_device: f64) -> BTreeMap<String, f64> {
+    let whole_gpus: Vec<&GpuInfo> = gpu_infos.iter()
+        .filter(|gpu_info| !gpu_info.mig_mode.as_ref().is_some_and(|mode| mode.current))
+        .collect();
+
+    let mut slots = BTreeMap::from([
+        (format!("{}.device", prefix), whole_gpus.len() as f64),
+        (format!("{}.shares", prefix), whole_gpus.len() as f64 * shares_per_device),
+        (format!("{}.mem", prefix), whole_gpus.iter().map(|gpu_info| gpu_info.memory_total as f64).sum()),
+    ]);
+    for mig_device in gpu_infos.iter().flat_map(|gpu_info| &gpu_info.mig_devices) {
+        let profile = mig_device.profile.as_deref().unwrap_or("unknown");
+        *slots.entry(format!("{}.device:{}-mig", prefix, profile)).or_default() += 1.0;
+    }
+    slots
+}
+
+fn is_device_slot(prefix: &str, slot: &str) -> bool {
+    slot == format!("{}.device", prefix) || (slot.starts_with(&format!("{}.device:", prefix)) && slot.ends_with("-mig"))
+}
+
+fn format_amount(slot: &str, amount: f64) -> String {
+    if slot.ends_with(".mem") {
+        format!("{}MiB", (amount / MIB).floor())
+    } else if amount.fract() == 0.0 {
+        format!("{:.0}", amount)
+    } else {
+        format!("{}", amount)
+    }
+}
+
+fn amounts_match(slot: &str, advertised: f64, detected: f64) -> bool {
+    if slot.ends_with(".mem") {
+        (advertised / MIB).floor() == (detected / MIB).floor()
+    } else {
+        (advertised - detected).abs() < 1e-6
+    }
+}
+
+/// Differences between the `prefix` slots the agent advertises and the detected ones, e.g.
+/// `cuda.device: agent advertises 8, detected 7`. Only slot kinds the agent advertises are compared,
+/// so an fGPU agent advertising `cuda.shares` alone is not expected to advertise `cuda.device`.
+pub fn slot_differences(prefix: &str, advertised: &HashMap<String, f64>, detected: &BTreeMap<String, f64>) -> Vec<String> {
+    let slot_prefix = format!("{}.", prefix);
+    let mut advertised: Vec<(&String, f64)> = advertised.iter()
+        .filter(|(slot, _)| slot.starts_with(&slot_prefix))
+        .map(|(slot, amount)| (slot, *amount))
+        .collect();
+    advertised.sort_by(|a, b| a.0.cmp(b.0));
+
+    let detected_devices: f64 = detected.iter()
+        .filter(|(slot, _)| is_device_slot(prefix, slot))
+        .map(|(_, amount)| amount)
+        .sum();
+    if advertised.is_empty() {
+        return if detected_devices > 0.0 {
+            vec![format!("agent advertises no {} slots, detected {}", prefix, describe_devices(prefix, detected))]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let mut differences = Vec::new();
+    for (slot, amount) in &advertised {
+        let known = is_device_slot(prefix, slot) || detected.contains_key(*slot);
+        let detected_amount = detected.get(*slot).copied().unwrap_or(0.0);
+        if known && !amounts_match(slot, *amount, detected_amount) {
+            differences.push(format!(
+                "{}: agent advertises {}, detected {}",
+                slot,
+                format_amount(slot, *amount),
+                format_amount(slot, detected_amount)
+            ));
+        }
+    }
+
+    // Devices the agent does not advertise at all, e.g. a MIG profile created after the agent started
+    if advertised.iter().any(|(slot, _)| is_device_slot(prefix, slot)) {
+        for (slot, amount) in detected {
+            if is_device_slot(prefix, slot) && *amount > 0.0 && !advertised.iter().any(|(advertised, _)| *advertised == slot) {
+                differences.push(format!("{}: agent advertises 0, detected {}", slot, format_amount(slot, *amount)));
+            }
+        }
+    }
+
+    differences
+}
+
+fn describe_devices(prefix: &str, detected: &BTreeMap<String, f64>) -> String {
+    detected.iter()
+        .filter(|(slot, amount)| is_device_slot(prefix, slot) && **amount > 0.0)
+        .map(|(slot, amount)| format!("{} {}", slot, format_amount(slot, *amount)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Compare the agent's advertised slots with the GPUs found on this host.
+/// `detected` holds `(slot prefix, GPUs)` per vendor, with `None` when the vendor tool failed.
+pub fn check_resource_slots(
+    advertised: &AdvertisedSlots,
+    detected: &[(&str, Option<&[GpuInfo]>)],
+    shares_per_device: f64,
+) -> HealthCheckResult {
+    let (status, details, error_message) = match &advertised.slots {
+        Err(e) => (
+            HealthStatus::Unknown,
+            format!("Cannot read slots of agent {}: {}", advertised.agent_id, e),
+            Some(e.to_string()),
+        ),
+        Ok(slots) => {
+            let mut differences = Vec::new();
+            let mut matched = Vec::new();
+            let mut unknown = Vec::new();
+
+            for (prefix, gpu_infos) in detected {
+                let Some(gpu_infos) = gpu_infos else {
+                    unknown.push(*prefix);
+                    continue;
+                };
+                let detected_slots = detected_slots(prefix, gpu_infos, shares_per_device);
+                differences.extend(slot_differences(prefix, slots, &detected_slots));
+
+                let mut advertised: Vec<String> = slots.iter()
+                    .filter(|(slot, _)| slot.starts_with(&format!("{}.", prefix)))
+                    .map(|(slot, amount)| format!("{} {}", slot, format_amount(slot, *amount)))
+                    .collect();
+                advertised.sort();
+                matched.extend(advertised);
+            }
+
+            if !differences.is_empty() {
+                error!("Agent {} resource slots differ from detected GPUs: {}", advertised.agent_id, differences.join("; "));
+                (
+                    HealthStatus::Unhealthy,
+                    format!("Agent {} slots differ from detected GPUs: {}", advertised.agent_id, differences.join("; ")),
+                    None,
+                )
+            } else if !unknown.is_empty() {
+                (
+                    HealthStatus::Unknown,
+                    format!("Agent {} slots not compared for {}: GPU query failed", advertised.agent_id, unknown.join(", ")),
+                    None,
+                )
+            } else if matched.is_empty() {
+                (HealthStatus::Healthy, format!("Agent {} advertises no GPU slots and none were detected", advertised.agent_id), None)
+            } else {
+                (
+                    HealthStatus::Healthy,
+                    format!("Agent {} slots match detected GPUs: {}", advertised.agent_id, matched.join(", ")),
+                    None,
+                )
+            }
+        }
+    };
+
+    HealthCheckResult {
+        service_name: "Agent Resource Slots".to_string(),
+        status,
+        response_time_ms: 0,
+        details,
+        timestamp: Utc::now(),
+        error_message,
+    }
+}
+
+/// Parse the manager's `available_slots` JSON, where amounts are decimal strings or numbers
+pub fn parse_available_slots(json: &str) -> Result<HashMap<String, f64>> {
+    let slots: HashMap<String, serde_json::Value> = serde_json::from_str(json)?;
+    slots.into_iter()
+        .map(|(slot, amount)| {
+            let parsed = match &amount {
+                serde_json::Value::String(amount) => amount.parse().ok(),
+                serde_json::Value::Number(amount) => amount.as_f64(),
+                _ => None,
+            };
+            parsed
+                .map(|parsed| (slot.clone(), parsed))
+                .ok_or_else(|| anyhow!("Invalid amount {} for slot {}", amount, slot))
+        })
+        .collect()
+}
+
+impl crate::HealthChecker {
+    /// Agent ID from the config, or `i-<hostname>` as the agent picks by default
+    fn agent_id(&self) -> Option<String> {
+        if let Some(agent_id) = &self.config.gpu.agent_id {
+            return Some(agent_id.clone());
+        }
+        let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").ok()?;
+        Some(format!("i-{}", hostname.trim()))
+    }
+
+    /// Slots the manager database records for this host's agent, or `None` when the
+    /// manager database is disabled in the config
+    pub(crate) async fn fetch_advertised_slots(&self) -> Option<AdvertisedSlots> {
+        use tokio_postgres::NoTls;
+
+        if !self.config.infrastructure.enabled {
+            return None;
+        }
+        let Some(agent_id) = self.agent_id() else {
+            return Some(AdvertisedSlots {
+                agent_id: "unknown".to_string(),
+                slots: Err(anyhow!("Hostname unavailable; set agent_id under [gpu]")),
+            });
+        };
+
+        let query = async {
+            let (client, connection) = tokio_postgres::connect(&self.config.infrastructure.postgres_dsn, NoTls).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    error!("PostgreSQL connection error: {}", e);
+                }
+            });
+
+            let row = client
+                .query_opt("SELECT available_slots::text FROM agents WHERE id = $1", &[&agent_id])
+                .await?
+                .ok_or_else(|| anyhow!("Agent {} not found in the manager database", agent_id))?;
+            parse_available_slots(&row.get::<_, String>(0))
+        };
+
+        let slots = match tokio::time::timeout(self.timeout, query).await {
+            Ok(slots) => slots,
+            Err(_) => Err(anyhow!("Manager database query timed out")),
+        };
+        Some(AdvertisedSlots { agent_id, slots })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A100_DRIVER_535: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-driver-535.xml");
+    const A100_MIG_DRIVER_535: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-mig-driver-535.xml");
+    const A100_MIG_LIST: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-mig-list.txt");
+
+    fn slots(json: &str) -> HashMap<String, f64> {
+        parse_available_slots(json).unwrap()
+    }
+
+    fn mig_gpu_infos() -> Vec<GpuInfo> {
+        let mut gpu_infos = super::super::nvidia_smi::parse_gpu_infos(A100_MIG_DRIVER_535).unwrap();
+        for listing in super::super::mig::parse_mig_listing(A100_MIG_LIST) {
+            let device = gpu_infos[listing.gpu as usize].mig_devices.iter_mut()
+                .find(|device| device.index == listing.device)
+                .unwrap();
+            device.profile = Some(listing.profile);
+        }
+        gpu_infos
+    }
+
+    #[test]
+    fn parses_decimal_strings_and_numbers() {
+        let slots = slots(r#"{"cpu": "64", "mem": "540662337536", "cuda.device": "2", "cuda.shares": 1.5}"#);
+        assert_eq!(slots["cuda.device"], 2.0);
+        assert_eq!(slots["cuda.shares"], 1.5);
+        assert!(parse_available_slots(r#"{"cuda.device": null}"#).is_err());
+    }
+
+    #[test]
+    fn matching_device_count_has_no_differences() {
+        let gpu_infos = super::super::nvidia_smi::parse_gpu_infos(A100_DRIVER_535).unwrap();
+        let detected = detected_slots("cuda", &gpu_infos, 1.0);
+        assert!(slot_differences("cuda", &slots(r#"{"cpu": "64", "cuda.device": "2"}"#), &detected).is_empty());
+    }
+
+    #[test]
+    fn reports_missing_device_precisely() {
+        let gpu_infos = super::super::nvidia_smi::parse_gpu_infos(A100_DRIVER_535).unwrap();
+        let detected = detected_slots("cuda", &gpu_infos[..1], 1.0);
+
+        assert_eq!(
+            slot_differences("cuda", &slots(r#"{"cuda.device": "2", "cuda.mem": "171798691840"}"#), &detected),
+            vec![
+                "cuda.device: agent advertises 2, detected 1".to_string(),
+                "cuda.mem: agent advertises 163840MiB, detected 81920MiB".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_mig_profiles_instead_of_mig_enabled_gpus() {
+        let detected = detected_slots("cuda", &mig_gpu_infos(), 1.0);
+        assert_eq!(detected["cuda.device"], 1.0);
+        assert_eq!(detected["cuda.device:3g.40gb-mig"], 1.0);
+        assert_eq!(detected["cuda.device:2g.20gb-mig"], 2.0);
+
+        let advertised = slots(r#"{"cuda.device": "1", "cuda.device:3g.40gb-mig": "1", "cuda.device:2g.20gb-mig": "2"}"#);
+        assert!(slot_differences("cuda", &advertised, &detected).is_empty());
+
+        let advertised = slots(r#"{"cuda.device": "2", "cuda.device:1g.10gb-mig": "3"}"#);
+        assert_eq!(
+            slot_differences("cuda", &advertised, &detected),
+            vec![
+                "cuda.device: agent advertises 2, detected 1".to_string(),
+                "cuda.device:1g.10gb-mig: agent advertises 3, detected 0".to_string(),
+                "cuda.device:2g.20gb-mig: agent advertises 0, detected 2".to_string(),
+                "cuda.device:3g.40gb-mig: agent advertises 0, detected 1".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn fractional_agents_are_compared_by_shares() {
+        let gpu_infos = super::super::nvidia_smi::parse_gpu_infos(A100_DRIVER_535).unwrap();
+        let detected = detected_slots("cuda", &gpu_infos, 1.0);
+
+        assert!(slot_differences("cuda", &slots(r#"{"cuda.shares": "2"}"#), &detected).is_empty());
+        assert_eq!(
+            slot_differences("cuda", &slots(r#"{"cuda.shares": "8"}"#), &detected),
+            vec!["cuda.shares: agent advertises 8, detected 2".to_string()]
+        );
+    }
+
+    #[test]
+    fn unadvertised_gpus_are_a_difference() {
+        let gpu_infos = super::super::nvidia_smi::parse_gpu_infos(A100_DRIVER_535).unwrap();
+        let detected = detected_slots("cuda", &gpu_infos, 1.0);
+
+        assert_eq!(
+            slot_differences("cuda", &slots(r#"{"cpu": "64"}"#), &detected),
+            vec!["agent advertises no cuda slots, detected cuda.device 2".to_string()]
+        );
+        assert!(slot_differences("rocm", &slots(r#"{"cpu": "64"}"#), &detected_slots("rocm", &[], 1.0)).is_empty());
+    }
+
+    #[test]
+    fn result_is_unhealthy_on_difference_and_unknown_without_manager() {
+        let gpu_infos = super::super::nvidia_smi::parse_gpu_infos(A100_DRIVER_535).unwrap();
+        let advertised = AdvertisedSlots {
+            agent_id: "i-gpu01".to_string(),
+            slots: Ok(slots(r#"{"cuda.device": "8"}"#)),
+        };
+
+        let result = check_resource_slots(&advertised, &[("cuda", Some(&gpu_infos)), ("rocm", Some(&[]))], 1.0);
+        assert_eq!(result.status, HealthStatus::Unhealthy);
+        assert_eq!(result.details, "Agent i-gpu01 slots differ from detected GPUs: cuda.device: agent advertises 8, detected 2");
+
+        let advertised = AdvertisedSlots {
+            agent_id: "i-gpu01".to_string(),
+            slots: Err(anyhow!("connection refused")),
+        };
+        assert_eq!(check_resource_slots(&advertised, &[("cuda", Some(&gpu_infos))], 1.0).status, HealthStatus::Unknown);
+    }
+}
@@ -0,0 +1,400 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::HealthStatus;
+
+pub const NVIDIA_VENDOR_ID: u16 = 0x10de;
+pub const AMD_VENDOR_ID: u16 = 0x1002;
+pub const INTEL_VENDOR_ID: u16 = 0x8086;
+
+/// Drivers that reserve a GPU for passthrough to a VM, e.g. `vfio-pci.ids=10de:2236` for KubeVirt
+const PASSTHROUGH_DRIVERS: &[&str] = &["vfio-pci", "pci-stub"];
+
+/// PCI class codes (class and subclass) of GPUs: VGA, 3D controller and other display controller.
+/// AMD Instinct MI200 and later report themselves as "other display controller".
+const GPU_PCI_CLASSES: &[u32] = &[0x0300, 0x0302, 0x0380];
+
+/// A GPU found on the PCI bus, whether or not a driver or vendor tool can see it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PciGpu {
+    /// `domain:bus:device.function`, e.g. `0000:07:00.0`
+    pub bus_id: String,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    /// Kernel driver bound to the device, e.g. `nvidia` or `amdgpu`
+    pub driver: Option<String>,
+    pub numa_node: Option<u32>,
+    /// DRM card nodes such as `card1`
+    pub drm_cards: Vec<String>,
+    pub pcie_link: Option<PcieLink>,
+}
+
+/// Negotiated and maximum PCIe link of a device, from `current_link_*` and `max_link_*`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PcieLink {
+    pub current_generation: u32,
+    pub current_width: u32,
+    pub max_generation: u32,
+    pub max_width: u32,
+}
+
+impl PcieLink {
+    fn read(device_dir: &Path) -> Option<Self> {
+        let read = |name: &str| std::fs::read_to_string(device_dir.join(name)).ok();
+        Some(Self {
+            current_generation: pcie_generation(&read("current_link_speed")?)?,
+            current_width: read("current_link_width")?.trim().parse().ok()?,
+            max_generation: pcie_generation(&read("max_link_speed")?)?,
+            max_width: read("max_link_width")?.trim().parse().ok()?,
+        })
+    }
+}
+
+/// PCIe generation from a sysfs link speed such as `16.0 GT/s PCIe` (older kernels print `16 GT/s`)
+fn pcie_generation(speed: &str) -> Option<u32> {
+    let transfer_rate: f32 = speed.split_whitespace().next()?.parse().ok()?;
+    [2.5, 5.0, 8.0, 16.0, 32.0, 64.0].iter()
+        .position(|rate| (rate - transfer_rate).abs() < 0.1)
+        .map(|index| index as u32 + 1)
+}
+
+impl PciGpu {
+    pub fn vendor_name(&self) -> &'static str {
+        match self.vendor_id {
+            NVIDIA_VENDOR_ID => "NVIDIA",
+            AMD_VENDOR_ID => "AMD",
+            INTEL_VENDOR_ID => "Intel",
+            _ => "Unknown vendor",
+        }
+    }
+
+    /// Drivers that make the GPU usable for compute
+    fn expected_drivers(&self) -> &'static [&'static str] {
+        match self.vendor_id {
+            NVIDIA_VENDOR_ID => &["nvidia"],
+            AMD_VENDOR_ID => &["amdgpu"],
+            INTEL_VENDOR_ID => &["i915", "xe"],
+            _ => &[],
+        }
+    }
+
+    /// Bound to a passthrough driver, so the host's vendor tool is not expected to see it
+    pub fn reserved_for_passthrough(&self) -> bool {
+        self.driver.as_deref().is_some_and(|driver| PASSTHROUGH_DRIVERS.contains(&driver))
+    }
+
+    /// Vendor and device IDs with driver, NUMA node and DRM cards, e.g. `NVIDIA 10de:20b2, driver nvidia, NUMA node 0, card1`
+    pub fn describe(&self) -> String {
+        let mut description = format!("{} {:04x}:{:04x}", self.vendor_name(), self.vendor_id, self.device_id);
+        if let Some(driver) = &self.driver {
+            description.push_str(&format!(", driver {}", driver));
+        }
+        if let Some(numa_node) = self.numa_node {
+            description.push_str(&format!(", NUMA node {}", numa_node));
+        }
+        if !self.drm_cards.is_empty() {
+            description.push_str(&format!(", {}", self.drm_cards.join(", ")));
+        }
+        if let Some(link) = &self.pcie_link {
+            description.push_str(&format!(
+                ", PCIe Gen{} x{} (max Gen{} x{})",
+                link.current_generation, link.current_width, link.max_generation, link.max_width
+            ));
+        }
+        description
+    }
+}
+
+/// What a vendor tool (nvidia-smi, rocm-smi) reported, to cross-check against the PCI bus
+#[derive(Debug, Clone)]
+pub enum VendorToolStatus {
+    NotFound,
+    Failed(String),
+    /// PCI bus IDs of the GPUs the tool reported
+    Reported(Vec<String>),
+}
+
+/// `(domain, bus, device, function)` from `0000:07:00.0`, nvidia-smi's `00000000:07:00.0`
+/// or an XID's `PCI:0000:07:00`, where the function defaults to 0
+pub fn pci_address(id: &str) -> Option<(u32, u32, u32, u32)> {
+    let mut parts = id.trim().trim_start_matches("PCI:").split(':');
+    let domain = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let bus = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let slot = parts.next()?;
+    let (device, function) = slot.split_once('.').unwrap_or((slot, "0"));
+    Some((
+        domain,
+        bus,
+        u32::from_str_radix(device, 16).ok()?,
+        u32::from_str_radix(function, 16).ok()?,
+    ))
+}
+
+/// Enumerate GPUs under `<sysfs_root>/bus/pci/devices`, with DRM cards from `<sysfs_root>/class/drm`.
+/// Devices from vendors without compute drivers (e.g. BMC graphics) are skipped.
+pub fn discover_pci_gpus(sysfs_root: &Path) -> Result<Vec<PciGpu>> {
+    let devices_dir = sysfs_root.join("bus/pci/devices");
+    let entries = std::fs::read_dir(&devices_dir)
+        .with_context(|| format!("Failed to read {}", devices_dir.display()))?;
+
+    let drm_cards = drm_cards_by_bus_id(sysfs_root);
+    let mut gpus = Vec::new();
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let Some(class) = read_hex(&path.join("class")) else {
+            continue;
+        };
+        if !GPU_PCI_CLASSES.contains(&(class >> 8)) {
+            continue;
+        }
+
+        let (Some(vendor_id), Some(device_id)) = (read_hex(&path.join("vendor")), read_hex(&path.join("device"))) else {
+            continue;
+        };
+        let bus_id = entry.file_name().to_string_lossy().to_string();
+
+        let gpu = PciGpu {
+            driver: std::fs::read_link(path.join("driver")).ok()
+                .and_then(|target| Some(target.file_name()?.to_string_lossy().to_string())),
+            // -1 when the platform has no NUMA information
+            numa_node: std::fs::read_to_string(path.join("numa_node")).ok()
+                .and_then(|node| node.trim().parse().ok()),
+            drm_cards: drm_cards.get(&bus_id).cloned().unwrap_or_default(),
+            pcie_link: PcieLink::read(&path),
+            bus_id,
+            vendor_id: vendor_id as u16,
+            device_id: device_id as u16,
+        };
+
+        if !gpu.expected_drivers().is_empty() {
+            gpus.push(gpu);
+        }
+    }
+
+    gpus.sort_by(|a, b| a.bus_id.cmp(&b.bus_id));
+    Ok(gpus)
+}
+
+fn read_hex(path: &Path) -> Option<u32> {
+    let value = std::fs::read_to_string(path).ok()?;
+    u32::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()
+}
+
+/// `cardN` nodes keyed by the bus ID their `device` link points at; connectors and render nodes are skipped
+fn drm_cards_by_bus_id(sysfs_root: &Path) -> HashMap<String, Vec<String>> {
+    let mut cards: HashMap<String, Vec<String>> = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(sysfs_root.join("class/drm")) else {
+        return cards;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.strip_prefix("card").is_some_and(|index| index.chars().all(|c| c.is_ascii_digit())) {
+            continue;
+        }
+        if let Some(bus_id) = std::fs::read_link(entry.path().join("device")).ok()
+            .and_then(|target| Some(target.file_name()?.to_string_lossy().to_string()))
+        {
+            cards.entry(bus_id).or_default().push(name);
+        }
+    }
+
+    for names in cards.values_mut() {
+        names.sort();
+    }
+    cards
+}
+
+/// Check a GPU found on the PCI bus against its bound driver and the vendor tool's view.
+/// Returns the problem when the GPU is present but unusable, and a Healthy note for GPUs
+/// reserved for passthrough.
+pub fn evaluate_pci_gpu(gpu: &PciGpu, tools: &HashMap<u16, (&str, VendorToolStatus)>) -> Option<(HealthStatus, String)> {
+    let expected = gpu.expected_drivers();
+    if gpu.reserved_for_passthrough() {
+        return Some((
+            HealthStatus::Healthy,
+            format!("Reserved for passthrough (bound to {})", gpu.driver.as_deref().unwrap_or_default()),
+        ));
+    }
+
+    let driver = match &gpu.driver {
+        None => {
+            return Some((
+                HealthStatus::Unhealthy,
+                format!("GPU present but no kernel driver bound (expected {})", expected.join(" or ")),
+            ));
+        }
+        Some(driver) if !expected.contains(&driver.as_str()) => {
+            return Some((
+                HealthStatus::Unhealthy,
+                format!("GPU bound to {} instead of {}", driver, expected.join(" or ")),
+            ));
+        }
+        Some(driver) => driver,
+    };
+
+    let (tool, status) = tools.get(&gpu.vendor_id)?;
+    match status {
+        VendorToolStatus::NotFound => Some((
+            HealthStatus::Unhealthy,
+            format!("GPU present with {} driver but {} not found", driver, tool),
+        )),
+        VendorToolStatus::Failed(error) => Some((
+            HealthStatus::Unhealthy,
+            format!("GPU present with {} driver but {} failed: {}", driver, tool, error),
+        )),
+        VendorToolStatus::Reported(bus_ids) => {
+            let address = pci_address(&gpu.bus_id);
+            if bus_ids.iter().any(|bus_id| address.is_some() && pci_address(bus_id) == address) {
+                None
+            } else {
+                Some((HealthStatus::Unhealthy, format!("GPU present but not reported by {}", tool)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture_root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysfs")
+    }
+
+    fn tools(nvidia: VendorToolStatus, amd: VendorToolStatus) -> HashMap<u16, (&'static str, VendorToolStatus)> {
+        HashMap::from([(NVIDIA_VENDOR_ID, ("nvidia-smi", nvidia)), (AMD_VENDOR_ID, ("rocm-smi", amd))])
+    }
+
+    #[test]
+    fn discovers_gpus_and_skips_other_devices() {
+        let gpus = discover_pci_gpus(&fixture_root()).unwrap();
+        let bus_ids: Vec<&str> = gpus.iter().map(|gpu| gpu.bus_id.as_str()).collect();
+        assert_eq!(bus_ids, vec!["0000:07:00.0", "0000:0f:00.0", "0000:83:00.0", "0000:a1:00.0"]);
+
+        assert_eq!(gpus[0], PciGpu {
+            bus_id: "0000:07:00.0".to_string(),
+            vendor_id: NVIDIA_VENDOR_ID,
+            device_id: 0x20b2,
+            driver: Some("nvidia".to_string()),
+            numa_node: Some(0),
+            drm_cards: vec!["card1".to_string()],
+            pcie_link: Some(PcieLink { current_generation: 4, current_width: 16, max_generation: 4, max_width: 16 }),
+        });
+        assert_eq!(gpus[1].driver, None);
+        assert_eq!(gpus[2].vendor_id, AMD_VENDOR_ID);
+        assert_eq!(gpus[2].driver.as_deref(), Some("amdgpu"));
+        assert_eq!(gpus[2].numa_node, Some(1));
+        assert_eq!(gpus[1].pcie_link, None);
+        assert_eq!(
+            gpus[2].describe(),
+            "AMD 1002:740f, driver amdgpu, NUMA node 1, card2, PCIe Gen3 x8 (max Gen4 x16)"
+        );
+    }
+
+    #[test]
+    fn missing_sysfs_tree_is_an_error() {
+        assert!(discover_pci_gpus(Path::new("/nonexistent")).is_err());
+    }
+
+    #[test]
+    fn parses_pci_addresses_in_every_notation() {
+        assert_eq!(pci_address("0000:07:00.0"), Some((0, 7, 0, 0)));
+        assert_eq!(pci_address("00000000:07:00.0"), Some((0, 7, 0, 0)));
+        assert_eq!(pci_address("0000:0C:00.0"), Some((0, 0x0c, 0, 0)));
+        assert_eq!(pci_address("PCI:0000:0f:00"), Some((0, 0x0f, 0, 0)));
+        assert_eq!(pci_address("not a bus id"), None);
+    }
+
+    #[test]
+    fn maps_link_speeds_to_generations() {
+        assert_eq!(pcie_generation("2.5 GT/s PCIe"), Some(1));
+        assert_eq!(pcie_generation("16 GT/s"), Some(4));
+        assert_eq!(pcie_generation("32.0 GT/s PCIe\n"), Some(5));
+        assert_eq!(pcie_generation("Unknown"), None);
+    }
+
+    #[test]
+    fn gpu_without_driver_is_unhealthy() {
+        let gpus = discover_pci_gpus(&fixture_root()).unwrap();
+        let tools = tools(
+            VendorToolStatus::Reported(vec!["00000000:07:00.0".to_string()]),
+            VendorToolStatus::Reported(vec!["0000:83:00.0".to_string()]),
+        );
+
+        assert_eq!(evaluate_pci_gpu(&gpus[0], &tools), None);
+        assert_eq!(
+            evaluate_pci_gpu(&gpus[1], &tools),
+            Some((HealthStatus::Unhealthy, "GPU present but no kernel driver bound (expected nvidia)".to_string()))
+        );
+        assert_eq!(evaluate_pci_gpu(&gpus[2], &tools), None);
+    }
+
+    #[test]
+    fn gpu_with_driver_but_unusable_tool_is_unhealthy() {
+        let gpus = discover_pci_gpus(&fixture_root()).unwrap();
+        let tools = tools(
+            VendorToolStatus::NotFound,
+            VendorToolStatus::Failed("Unable to read sysfs".to_string()),
+        );
+
+        assert_eq!(
+            evaluate_pci_gpu(&gpus[0], &tools),
+            Some((HealthStatus::Unhealthy, "GPU present with nvidia driver but nvidia-smi not found".to_string()))
+        );
+        assert_eq!(
+            evaluate_pci_gpu(&gpus[2], &tools),
+            Some((HealthStatus::Unhealthy, "GPU present with amdgpu driver but rocm-smi failed: Unable to read sysfs".to_string()))
+        );
+    }
+
+    #[test]
+    fn gpu_missing_from_vendor_tool_is_unhealthy() {
+        let gpus = discover_pci_gpus(&fixture_root()).unwrap();
+        let tools = tools(VendorToolStatus::Reported(Vec::new()), VendorToolStatus::Reported(Vec::new()));
+
+        assert_eq!(
+            evaluate_pci_gpu(&gpus[0], &tools),
+            Some((HealthStatus::Unhealthy, "GPU present but not reported by nvidia-smi".to_string()))
+        );
+    }
+
+    #[test]
+    fn passthrough_gpu_is_reserved_not_unusable() {
+        let gpus = discover_pci_gpus(&fixture_root()).unwrap();
+        assert!(gpus[3].reserved_for_passthrough());
+        assert!(!gpus[0].reserved_for_passthrough());
+
+        // nvidia-smi does not list GPUs handed to VMs
+        let tools = tools(
+            VendorToolStatus::Reported(vec!["00000000:07:00.0".to_string()]),
+            VendorToolStatus::Reported(vec!["0000:83:00.0".to_string()]),
+        );
+        assert_eq!(
+            evaluate_pci_gpu(&gpus[3], &tools),
+            Some((HealthStatus::Healthy, "Reserved for passthrough (bound to vfio-pci)".to_string()))
+        );
+    }
+
+    #[test]
+    fn wrong_driver_is_unhealthy() {
+        let gpu = PciGpu {
+            bus_id: "0000:07:00.0".to_string(),
+            vendor_id: NVIDIA_VENDOR_ID,
+            device_id: 0x2204,
+            driver: Some("nouveau".to_string()),
+            numa_node: None,
+            drm_cards: Vec::new(),
+            pcie_link: None,
+        };
+
+        assert_eq!(
+            evaluate_pci_gpu(&gpu, &HashMap::new()),
+            Some((HealthStatus::Unhealthy, "GPU bound to nouveau instead of nvidia".to_string()))
+        );
+    }
+}
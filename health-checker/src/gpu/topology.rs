@@ -0,0 +1,425 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::sysfs::PciGpu;
+use crate::config::TopologyConfig;
+use crate::HealthStatus;
+
+/// One GPU row of `nvidia-smi topo -m`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopoGpu {
+    pub index: u32,
+    /// Connection (`NV12`, `PIX`, `SYS`, ...) to each other GPU (`GPU1`) and NIC (`mlx5_0`)
+    pub links: BTreeMap<String, String>,
+    pub cpu_affinity: Option<String>,
+    pub numa_affinity: Option<u32>,
+}
+
+/// GPU rows of the `nvidia-smi topo -m` matrix
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopoMatrix {
+    pub gpus: Vec<TopoGpu>,
+    /// NIC names, resolved through the NIC legend of newer drivers
+    pub nics: Vec<String>,
+}
+
+/// Per-link state from `nvidia-smi nvlink -s`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NvlinkStatus {
+    pub gpu: u32,
+    /// Link number and speed in GB/s, `None` for `<inactive>` links
+    pub links: Vec<(u32, Option<f32>)>,
+}
+
+impl NvlinkStatus {
+    fn inactive_links(&self) -> Vec<u32> {
+        self.links.iter().filter(|(_, speed)| speed.is_none()).map(|(link, _)| *link).collect()
+    }
+}
+
+/// Distance of a `topo -m` connection, from the GPU itself to across the SMP interconnect.
+/// `SOC` is what drivers before 384 called `SYS`.
+fn link_rank(link: &str) -> Option<u32> {
+    match link {
+        "X" => Some(0),
+        link if link.starts_with("NV") => Some(1),
+        "PIX" => Some(2),
+        "PXB" => Some(3),
+        "PHB" => Some(4),
+        "NODE" => Some(5),
+        "SYS" | "SOC" => Some(6),
+        _ => None,
+    }
+}
+
+/// Parse the matrix printed by `nvidia-smi topo -m`. Columns are tab separated; NICs are
+/// `NIC<n>` with a legend on drivers 510 and later, and RDMA device names before that.
+pub fn parse_topo_matrix(output: &str) -> Option<TopoMatrix> {
+    let cells = |line: &str| -> Vec<String> {
+        line.split('\t').map(str::trim).filter(|cell| !cell.is_empty()).map(str::to_string).collect()
+    };
+
+    let mut lines = output.lines();
+    let header = cells(lines.find(|line| line.trim_start().starts_with("GPU0"))?);
+
+    let nic_legend: HashMap<&str, &str> = output.lines()
+        .filter_map(|line| {
+            let (nic, name) = line.trim().split_once(':')?;
+            nic.starts_with("NIC").then(|| (nic, name.trim()))
+        })
+        .collect();
+    let column_name = |column: &str| nic_legend.get(column).copied().unwrap_or(column).to_string();
+    let is_device = |column: &str| !column.contains(' ') && !column.ends_with("Affinity");
+
+    let mut matrix = TopoMatrix {
+        gpus: Vec::new(),
+        nics: header.iter()
+            .filter(|column| is_device(column) && !is_gpu_column(column))
+            .map(|column| column_name(column))
+            .collect(),
+    };
+
+    for line in lines.take_while(|line| !line.trim().is_empty()) {
+        let row = cells(line);
+        let Some(index) = row.first().and_then(|name| name.strip_prefix("GPU")).and_then(|index| index.parse().ok()) else {
+            continue;
+        };
+
+        let mut gpu = TopoGpu { index, links: BTreeMap::new(), cpu_affinity: None, numa_affinity: None };
+        for (column, value) in header.iter().zip(row.iter().skip(1)) {
+            match column.as_str() {
+                "CPU Affinity" => gpu.cpu_affinity = Some(value.clone()).filter(|value| value != "N/A"),
+                "NUMA Affinity" => gpu.numa_affinity = value.parse().ok(),
+                column if is_device(column) && value != "X" => {
+                    gpu.links.insert(column_name(column), value.clone());
+                }
+                _ => {}
+            }
+        }
+        matrix.gpus.push(gpu);
+    }
+
+    Some(matrix)
+}
+
+fn is_gpu_column(column: &str) -> bool {
+    column.strip_prefix("GPU").is_some_and(|index| index.parse::<u32>().is_ok())
+}
+
+/// Parse `nvidia-smi nvlink -s`; GPUs without NVLink have no link lines
+pub fn parse_nvlink_status(output: &str) -> Vec<NvlinkStatus> {
+    let mut statuses: Vec<NvlinkStatus> = Vec::new();
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("GPU ") {
+            if let Some(gpu) = rest.split(':').next().and_then(|index| index.trim().parse().ok()) {
+                statuses.push(NvlinkStatus { gpu, links: Vec::new() });
+            }
+            continue;
+        }
+
+        let (Some(rest), Some(status)) = (line.strip_prefix("Link "), statuses.last_mut()) else {
+            continue;
+        };
+        let Some((link, state)) = rest.split_once(':') else {
+            continue;
+        };
+        if let Ok(link) = link.trim().parse() {
+            let speed = state.split_whitespace().next().and_then(|speed| speed.parse().ok());
+            status.links.push((link, speed));
+        }
+    }
+
+    statuses
+}
+
+fn gpu_label(index: Option<u32>, gpu: &PciGpu) -> String {
+    match index {
+        Some(index) => format!("GPU{}", index),
+        None => gpu.bus_id.clone(),
+    }
+}
+
+/// Compare the NVLink/PCIe topology with `expected`. `pci_gpus` pairs each GPU found on the PCI bus
+/// with its nvidia-smi index, when nvidia-smi reports it.
+/// A current PCIe generation below the maximum is not an issue: GPUs lower the link speed when idle.
+pub fn evaluate_topology(
+    matrix: Option<&TopoMatrix>,
+    nvlinks: &[NvlinkStatus],
+    pci_gpus: &[(Option<u32>, &PciGpu)],
+    expected: &TopologyConfig,
+) -> (HealthStatus, String) {
+    let mut issues = Vec::new();
+    let mut summary = Vec::new();
+
+    if let Some(matrix) = matrix {
+        let mut gpu_links = BTreeSet::new();
+        for gpu in &matrix.gpus {
+            for peer in &matrix.gpus {
+                if peer.index <= gpu.index {
+                    continue;
+                }
+                let Some(link) = gpu.links.get(&format!("GPU{}", peer.index)) else {
+                    continue;
+                };
+                gpu_links.insert(link.clone());
+                if let Some(expected_link) = expected.gpu_link.as_ref().filter(|expected| *expected != link) {
+                    issues.push(format!("GPU{}-GPU{}: {}, expected {}", gpu.index, peer.index, link, expected_link));
+                }
+            }
+        }
+        if !gpu_links.is_empty() {
+            summary.push(format!("GPU links: {}", gpu_links.into_iter().collect::<Vec<_>>().join(", ")));
+        }
+
+        let nearest_nics: Vec<String> = matrix.gpus.iter()
+            .filter_map(|gpu| {
+                let (nic, link) = matrix.nics.iter()
+                    .filter_map(|nic| Some((nic, gpu.links.get(nic)?)))
+                    .min_by_key(|(_, link)| link_rank(link).unwrap_or(u32::MAX))?;
+                Some(format!("GPU{} {} ({})", gpu.index, nic, link))
+            })
+            .collect();
+        if !nearest_nics.is_empty() {
+            summary.push(format!("nearest NICs: {}", nearest_nics.join(", ")));
+        }
+    }
+
+    let total_links: usize = nvlinks.iter().map(|status| status.links.len()).sum();
+    if total_links > 0 {
+        let inactive: usize = nvlinks.iter().map(|status| status.inactive_links().len()).sum();
+        summary.push(format!("{}/{} NVLink links active", total_links - inactive, total_links));
+    }
+    for status in nvlinks {
+        let inactive = status.inactive_links();
+        let active = status.links.len() - inactive.len();
+        match expected.nvlink_links {
+            Some(expected_links) if (active as u32) < expected_links => issues.push(format!(
+                "GPU{}: {} NVLink links active, expected {}",
+                status.gpu, active, expected_links
+            )),
+            _ if !inactive.is_empty() => {}
+            _ => continue,
+        }
+        if !inactive.is_empty() {
+            let links: Vec<String> = inactive.iter().map(|link| link.to_string()).collect();
+            issues.push(format!("GPU{}: NVLink links {} inactive", status.gpu, links.join(", ")));
+        }
+    }
+
+    let mut pcie_links = BTreeSet::new();
+    for (index, gpu) in pci_gpus {
+        let Some(link) = &gpu.pcie_link else {
+            continue;
+        };
+        let label = gpu_label(*index, gpu);
+        pcie_links.insert(format!("Gen{} x{}", link.current_generation, link.current_width));
+
+        if link.current_width < link.max_width {
+            issues.push(format!("{}: PCIe link x{}, capable of x{}", label, link.current_width, link.max_width));
+        }
+        if let Some(width) = expected.pcie_width.filter(|width| link.current_width >= link.max_width && link.max_width < *width) {
+            issues.push(format!("{}: PCIe link x{}, expected x{}", label, link.max_width, width));
+        }
+        if let Some(generation) = expected.pcie_generation.filter(|generation| link.max_generation < *generation) {
+            issues.push(format!("{}: PCIe Gen{} capable, expected Gen{}", label, link.max_generation, generation));
+        }
+    }
+    if !pcie_links.is_empty() {
+        summary.push(format!("PCIe {}", pcie_links.into_iter().collect::<Vec<_>>().join(", ")));
+    }
+
+    let nic_link = expected.nic_link.as_deref().unwrap_or("PXB");
+    let max_nic_rank = link_rank(nic_link);
+    if max_nic_rank.is_none() && expected.affinity.iter().any(|affinity| affinity.nic.is_some()) {
+        issues.push(format!("nic_link {} in config is not one of PIX, PXB, PHB, NODE, SYS", nic_link));
+    }
+    for affinity in &expected.affinity {
+        let topo_gpu = matrix.and_then(|matrix| matrix.gpus.iter().find(|gpu| gpu.index == affinity.gpu));
+
+        if let Some(nic) = &affinity.nic {
+            match topo_gpu.map(|gpu| gpu.links.get(nic)) {
+                None => issues.push(format!("GPU{} not in nvidia-smi topology", affinity.gpu)),
+                Some(None) => issues.push(format!("GPU{}: NIC {} not in nvidia-smi topology", affinity.gpu, nic)),
+                Some(Some(link)) => match (link_rank(link), max_nic_rank) {
+                    (Some(rank), Some(max_rank)) if rank > max_rank => issues.push(format!(
+                        "GPU{} to {}: {}, expected {} or closer",
+                        affinity.gpu, nic, link, nic_link
+                    )),
+                    (None, _) => issues.push(format!("GPU{} to {}: unrecognized connection {}", affinity.gpu, nic, link)),
+                    _ => {}
+                },
+            }
+        }
+
+        if let Some(numa_node) = affinity.numa_node {
+            let actual = pci_gpus.iter()
+                .find(|(index, _)| *index == Some(affinity.gpu))
+                .and_then(|(_, gpu)| gpu.numa_node)
+                .or_else(|| topo_gpu.and_then(|gpu| gpu.numa_affinity));
+            match actual {
+                Some(actual) if actual != numa_node => {
+                    issues.push(format!("GPU{}: NUMA node {}, expected {}", affinity.gpu, actual, numa_node))
+                }
+                Some(_) => {}
+                None => issues.push(format!("GPU{}: NUMA node unknown, expected {}", affinity.gpu, numa_node)),
+            }
+        }
+    }
+
+    let summary = if summary.is_empty() { "No topology information".to_string() } else { summary.join("; ") };
+    if issues.is_empty() {
+        (HealthStatus::Healthy, summary)
+    } else {
+        (HealthStatus::Degraded, format!("{} - Issues: {}", summary, issues.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GpuAffinity;
+    use std::path::Path;
+
+    const TOPO_DRIVER_535: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-topo-driver-535.txt");
+    const TOPO_DRIVER_470: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-topo-driver-470.txt");
+    const NVLINK_STATUS: &str = include_str!("../../tests/fixtures/nvidia-smi/a100-nvlink-status.txt");
+
+    fn expected() -> TopologyConfig {
+        TopologyConfig {
+            gpu_link: Some("NV12".to_string()),
+            nvlink_links: Some(12),
+            pcie_generation: Some(4),
+            pcie_width: Some(16),
+            nic_link: None,
+            affinity: vec![
+                GpuAffinity { gpu: 0, nic: Some("mlx5_0".to_string()), numa_node: Some(0) },
+                GpuAffinity { gpu: 2, nic: Some("mlx5_1".to_string()), numa_node: Some(1) },
+            ],
+        }
+    }
+
+    fn pci_gpus() -> Vec<PciGpu> {
+        super::super::sysfs::discover_pci_gpus(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysfs")).unwrap()
+    }
+
+    #[test]
+    fn parses_topology_matrix_with_nic_legend() {
+        let matrix = parse_topo_matrix(TOPO_DRIVER_535).unwrap();
+        assert_eq!(matrix.nics, vec!["mlx5_0", "mlx5_1"]);
+        assert_eq!(matrix.gpus.len(), 4);
+
+        let gpu = &matrix.gpus[2];
+        assert_eq!(gpu.index, 2);
+        assert_eq!(gpu.links["GPU0"], "NV12");
+        assert_eq!(gpu.links["mlx5_1"], "PXB");
+        assert_eq!(gpu.links["mlx5_0"], "SYS");
+        assert!(!gpu.links.contains_key("GPU2"));
+        assert_eq!(gpu.cpu_affinity.as_deref(), Some("32-63,96-127"));
+        assert_eq!(gpu.numa_affinity, Some(1));
+    }
+
+    #[test]
+    fn parses_topology_matrix_of_older_drivers() {
+        let matrix = parse_topo_matrix(TOPO_DRIVER_470).unwrap();
+        assert_eq!(matrix.nics, vec!["mlx5_0"]);
+        assert_eq!(matrix.gpus[1].links["GPU3"], "SYS");
+        assert_eq!(matrix.gpus[1].links["mlx5_0"], "PXB");
+        assert_eq!(matrix.gpus[3].numa_affinity, Some(1));
+        assert_eq!(parse_topo_matrix("NVML: Unable to determine the device handle"), None);
+    }
+
+    #[test]
+    fn parses_inactive_nvlink_links() {
+        let statuses = parse_nvlink_status(NVLINK_STATUS);
+        assert_eq!(statuses.len(), 4);
+        assert_eq!(statuses[0].links.len(), 12);
+        assert_eq!(statuses[0].links[0], (0, Some(25.0)));
+        assert_eq!(statuses[1].inactive_links(), vec![3, 4]);
+        assert!(statuses[2].inactive_links().is_empty());
+    }
+
+    #[test]
+    fn expected_topology_is_healthy() {
+        let matrix = parse_topo_matrix(TOPO_DRIVER_535).unwrap();
+        let pci_gpus = pci_gpus();
+        let (status, details) = evaluate_topology(Some(&matrix), &[], &[(Some(0), &pci_gpus[0])], &expected());
+
+        assert_eq!(status, HealthStatus::Healthy);
+        assert_eq!(
+            details,
+            "GPU links: NV12; nearest NICs: GPU0 mlx5_0 (PXB), GPU1 mlx5_0 (PXB), GPU2 mlx5_1 (PXB), GPU3 mlx5_1 (PXB); PCIe Gen4 x16"
+        );
+    }
+
+    #[test]
+    fn reports_degraded_links_and_affinity() {
+        let matrix = parse_topo_matrix(TOPO_DRIVER_470).unwrap();
+        let nvlinks = parse_nvlink_status(NVLINK_STATUS);
+        let pci_gpus = pci_gpus();
+        let (status, details) = evaluate_topology(
+            Some(&matrix),
+            &nvlinks,
+            &[(Some(0), &pci_gpus[0]), (None, &pci_gpus[2])],
+            &expected(),
+        );
+
+        assert_eq!(status, HealthStatus::Degraded);
+        let issues = details.split_once(" - Issues: ").unwrap().1;
+        assert_eq!(
+            issues,
+            [
+                "GPU1-GPU3: SYS, expected NV12",
+                "GPU1: 10 NVLink links active, expected 12",
+                "GPU1: NVLink links 3, 4 inactive",
+                "0000:83:00.0: PCIe link x8, capable of x16",
+                "GPU2: NIC mlx5_1 not in nvidia-smi topology",
+            ]
+            .join(", ")
+        );
+        assert!(details.starts_with("GPU links: NV12, SYS; "));
+        assert!(details.contains("46/48 NVLink links active"));
+    }
+
+    #[test]
+    fn reports_gpu_far_from_its_nic_and_wrong_numa_node() {
+        let matrix = parse_topo_matrix(TOPO_DRIVER_535).unwrap();
+        let pci_gpus = pci_gpus();
+        let config = TopologyConfig {
+            affinity: vec![GpuAffinity { gpu: 0, nic: Some("mlx5_1".to_string()), numa_node: Some(1) }],
+            ..TopologyConfig::default()
+        };
+        let (status, details) = evaluate_topology(Some(&matrix), &[], &[(Some(0), &pci_gpus[0])], &config);
+
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: GPU0 to mlx5_1: SYS, expected PXB or closer, GPU0: NUMA node 0, expected 1"));
+    }
+
+    #[test]
+    fn invalid_nic_link_is_reported_instead_of_flagging_every_nic() {
+        let matrix = parse_topo_matrix(TOPO_DRIVER_535).unwrap();
+        let config = TopologyConfig {
+            nic_link: Some("CLOSE".to_string()),
+            affinity: vec![GpuAffinity { gpu: 0, nic: Some("mlx5_0".to_string()), numa_node: None }],
+            ..TopologyConfig::default()
+        };
+        let (status, details) = evaluate_topology(Some(&matrix), &[], &[], &config);
+
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: nic_link CLOSE in config is not one of PIX, PXB, PHB, NODE, SYS"), "{}", details);
+    }
+
+    #[test]
+    fn unrecognized_nic_connection_is_reported() {
+        let mut matrix = parse_topo_matrix(TOPO_DRIVER_535).unwrap();
+        matrix.gpus[0].links.insert("mlx5_0".to_string(), "XYZ".to_string());
+        let config = TopologyConfig {
+            affinity: vec![GpuAffinity { gpu: 0, nic: Some("mlx5_0".to_string()), numa_node: None }],
+            ..TopologyConfig::default()
+        };
+        let (status, details) = evaluate_topology(Some(&matrix), &[], &[], &config);
+
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.ends_with("Issues: GPU0 to mlx5_0: unrecognized connection XYZ"), "{}", details);
+    }
+}
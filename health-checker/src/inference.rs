@@ -0,0 +1,455 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use log::{error, info};
+use std::time::{Duration, Instant};
+
+use crate::config::ModelServiceConfig;
+use crate::net::request_failure;
+use crate::prometheus::parse_exposition;
+use crate::severity::{escalate, severity};
+use crate::{HealthCheckResult, HealthStatus};
+
+/// Token timing of a streamed completion
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionTiming {
+    pub time_to_first_token: Duration,
+    pub tokens: u64,
+    /// Decode rate after the first token; `None` when fewer than two tokens were generated
+    pub tokens_per_sec: Option<f64>,
+}
+
+/// Reads a `text/event-stream` completion as its chunks arrive and times the generated tokens
+#[derive(Debug, Default)]
+pub struct StreamTimer {
+    buffer: Vec<u8>,
+    first_token: Option<Duration>,
+    last_token: Option<Duration>,
+    chunks: u64,
+    usage_tokens: Option<u64>,
+}
+
+impl StreamTimer {
+    /// Feed `bytes` received `at` after the request was sent
+    pub fn feed(&mut self, bytes: &[u8], at: Duration) -> Result<()> {
+        self.buffer.extend_from_slice(bytes);
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.event_line(&String::from_utf8_lossy(&line), at)?;
+        }
+        Ok(())
+    }
+
+    fn event_line(&mut self, line: &str, at: Duration) -> Result<()> {
+        let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+            return Ok(());
+        };
+        if data == "[DONE]" {
+            return Ok(());
+        }
+
+        let event: serde_json::Value = serde_json::from_str(data)
+            .with_context(|| format!("Unexpected stream event: {}", data))?;
+        if let Some(error) = event.get("error") {
+            let message = error.get("message").and_then(|m| m.as_str()).map(str::to_string);
+            return Err(anyhow!("Server error: {}", message.unwrap_or_else(|| error.to_string())));
+        }
+
+        let text = event.pointer("/choices/0/text").and_then(|text| text.as_str()).unwrap_or_default();
+        if !text.is_empty() {
+            self.first_token.get_or_insert(at);
+            self.last_token = Some(at);
+            self.chunks += 1;
+        }
+        if let Some(tokens) = event.pointer("/usage/completion_tokens").and_then(|tokens| tokens.as_u64()) {
+            self.usage_tokens = Some(tokens);
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<CompletionTiming> {
+        let (Some(first), Some(last)) = (self.first_token, self.last_token) else {
+            return Err(anyhow!("Completion returned no tokens"));
+        };
+        // A chunk can carry several tokens, so the reported usage wins when the server sends it
+        let tokens = self.usage_tokens.unwrap_or(self.chunks).max(1);
+        let decode_time = last.saturating_sub(first).as_secs_f64();
+        let tokens_per_sec = (tokens > 1 && decode_time > 0.0).then(|| (tokens - 1) as f64 / decode_time);
+
+        Ok(CompletionTiming { time_to_first_token: first, tokens, tokens_per_sec })
+    }
+}
+
+/// Scheduler state of a vLLM server for one model
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VllmMetrics {
+    pub running: Option<f64>,
+    pub waiting: Option<f64>,
+    /// GPU KV-cache usage in percent
+    pub kv_cache_usage: Option<f64>,
+}
+
+/// Model IDs from a `/v1/models` response
+pub fn parse_model_ids(json: &str) -> Result<Vec<String>> {
+    let models: serde_json::Value = serde_json::from_str(json)?;
+    let data = models.get("data")
+        .and_then(|data| data.as_array())
+        .ok_or_else(|| anyhow!("No data list in /v1/models response"))?;
+    Ok(data.iter()
+        .filter_map(|model| model.get("id").and_then(|id| id.as_str()).map(str::to_string))
+        .collect())
+}
+
+/// vLLM gauges for `model`; series labelled with another `model_name` are skipped.
+/// vLLM 0.7+ names the cache gauge `vllm:kv_cache_usage_perc` instead of `vllm:gpu_cache_usage_perc`.
+pub fn parse_vllm_metrics(text: &str, model: &str) -> VllmMetrics {
+    let samples: Vec<_> = parse_exposition(text)
+        .into_iter()
+        .filter(|sample| sample.label("model_name").is_none_or(|name| name == model))
+        .collect();
+    let total = |name: &str| {
+        let values: Vec<f64> = samples.iter().filter(|sample| sample.name == name).map(|sample| sample.value).collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>())
+    };
+
+    VllmMetrics {
+        running: total("vllm:num_requests_running"),
+        waiting: total("vllm:num_requests_waiting"),
+        kv_cache_usage: total("vllm:gpu_cache_usage_perc")
+            .or_else(|| total("vllm:kv_cache_usage_perc"))
+            .map(|usage| usage * 100.0),
+    }
+}
+
+/// Apply the service thresholds to the test completion and, when scraped, the vLLM metrics
+pub fn evaluate_model_service(
+    service: &ModelServiceConfig,
+    model: &str,
+    timing: &CompletionTiming,
+    metrics: Option<&Result<VllmMetrics>>,
+) -> (HealthStatus, String) {
+    let mut status = HealthStatus::Healthy;
+    let mut issues = Vec::new();
+
+    let ttft_ms = timing.time_to_first_token.as_secs_f64() * 1000.0;
+    if let Some(level) = severity(ttft_ms, &service.ttft_ms) {
+        issues.push(format!("Slow time to first token: {:.0}ms", ttft_ms));
+        escalate(&mut status, level);
+    }
+    if let (Some(minimum), Some(rate)) = (service.min_tokens_per_sec, timing.tokens_per_sec) {
+        if rate < minimum {
+            issues.push(format!("Slow generation: {:.1} tokens/s, expected at least {:.1}", rate, minimum));
+            escalate(&mut status, HealthStatus::Degraded);
+        }
+    }
+
+    let mut details = format!("model {}, TTFT {:.0}ms", model, ttft_ms);
+    match timing.tokens_per_sec {
+        Some(rate) => details.push_str(&format!(", {:.1} tokens/s ({} tokens)", rate, timing.tokens)),
+        None => details.push_str(&format!(", {} tokens", timing.tokens)),
+    }
+
+    match metrics {
+        Some(Ok(metrics)) => {
+            if let Some(running) = metrics.running {
+                details.push_str(&format!(", running {:.0}", running));
+            }
+            if let Some(waiting) = metrics.waiting {
+                details.push_str(&format!(", waiting {:.0}", waiting));
+                if let Some(level) = severity(waiting, &service.waiting_requests) {
+                    issues.push(format!("{:.0} requests waiting", waiting));
+                    escalate(&mut status, level);
+                }
+            }
+            if let Some(usage) = metrics.kv_cache_usage {
+                details.push_str(&format!(", KV cache {:.1}%", usage));
+                if let Some(level) = severity(usage, &service.kv_cache_usage) {
+                    issues.push(format!("KV cache nearly full: {:.1}%", usage));
+                    escalate(&mut status, level);
+                }
+            }
+        }
+        Some(Err(e)) => {
+            issues.push(format!("Cannot read metrics: {}", e));
+            escalate(&mut status, HealthStatus::Degraded);
+        }
+        None => {}
+    }
+
+    if !issues.is_empty() {
+        details.push_str(&format!(" - Issues: {}", issues.join(", ")));
+    }
+    (status, details)
+}
+
+fn authorized(request: reqwest::RequestBuilder, service: &ModelServiceConfig) -> reqwest::RequestBuilder {
+    match &service.api_key {
+        Some(api_key) => request.bearer_auth(api_key),
+        None => request,
+    }
+}
+
+async fn get_text(client: &reqwest::Client, service: &ModelServiceConfig, path: &str) -> Result<String> {
+    let url = format!("{}{}", service.url.trim_end_matches('/'), path);
+    let response = authorized(client.get(&url), service).send().await
+        .map_err(|e| anyhow!("Cannot reach {}: {}", url, request_failure(e)))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("GET {} returned {}", path, response.status()));
+    }
+    Ok(response.text().await?)
+}
+
+/// Send a small streamed completion and time its tokens
+async fn time_completion(client: &reqwest::Client, service: &ModelServiceConfig, model: &str) -> Result<CompletionTiming> {
+    let url = format!("{}/v1/completions", service.url.trim_end_matches('/'));
+    let body = serde_json::json!({
+        "model": model,
+        "prompt": service.prompt,
+        "max_tokens": service.max_tokens,
+        "temperature": 0,
+        "stream": true,
+    });
+
+    let start_time = Instant::now();
+    let mut response = authorized(client.post(&url), service).json(&body).send().await
+        .map_err(|e| anyhow!("POST /v1/completions failed: {}", request_failure(e)))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("POST /v1/completions returned {}: {}", status, text.trim()));
+    }
+
+    let mut timer = StreamTimer::default();
+    while let Some(chunk) = response.chunk().await.map_err(|e| anyhow!("Stream interrupted: {}", request_failure(e)))? {
+        timer.feed(&chunk, start_time.elapsed())?;
+    }
+    timer.finish()
+}
+
+async fn probe_model_service(service: &ModelServiceConfig) -> Result<(HealthStatus, String)> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(service.timeout_secs))
+        .build()?;
+
+    get_text(&client, service, "/health").await?;
+
+    let models = parse_model_ids(&get_text(&client, service, "/v1/models").await?)?;
+    let model = match &service.model {
+        Some(model) if models.contains(model) => model.clone(),
+        Some(model) => {
+            return Ok((HealthStatus::Unhealthy, format!("Model {} not loaded, serving: {}", model, models.join(", "))));
+        }
+        None => match models.first() {
+            Some(model) => model.clone(),
+            None => return Ok((HealthStatus::Unhealthy, "No models loaded".to_string())),
+        },
+    };
+
+    let timing = time_completion(&client, service, &model).await
+        .context("Test completion failed")?;
+
+    let metrics = if service.metrics {
+        Some(get_text(&client, service, "/metrics").await.map(|text| parse_vllm_metrics(&text, &model)))
+    } else {
+        None
+    };
+
+    Ok(evaluate_model_service(service, &model, &timing, metrics.as_ref()))
+}
+
+async fn check_model_service(service: &ModelServiceConfig) -> HealthCheckResult {
+    let start_time = Instant::now();
+    let service_name = format!("Model Service ({})", service.name);
+
+    match probe_model_service(service).await {
+        Ok((status, details)) => HealthCheckResult {
+            service_name,
+            status,
+            response_time_ms: start_time.elapsed().as_millis() as u64,
+            details,
+            timestamp: Utc::now(),
+            error_message: None,
+        },
+        Err(e) => {
+            error!("Model service {} check failed: {:#}", service.name, e);
+            HealthCheckResult {
+                service_name,
+                status: HealthStatus::Unhealthy,
+                response_time_ms: start_time.elapsed().as_millis() as u64,
+                details: format!("{:#}", e),
+                timestamp: Utc::now(),
+                error_message: Some(e.to_string()),
+            }
+        }
+    }
+}
+
+impl crate::HealthChecker {
+    /// Check every configured inference endpoint concurrently
+    pub async fn check_model_services(&self) -> Result<Vec<HealthCheckResult>> {
+        if self.config.model_services.is_empty() {
+            return Ok(vec![Self::backend_unavailable_result(
+                "Model Services",
+                &HealthStatus::Unknown,
+                "No model services configured",
+            )]);
+        }
+
+        info!("Checking {} model services", self.config.model_services.len());
+        Ok(futures::future::join_all(self.config.model_services.iter().map(check_model_service)).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Threshold;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const MODEL: &str = "meta-llama/Meta-Llama-3-8B-Instruct";
+    const MODELS: &str = include_str!("../tests/fixtures/vllm/models-0.6.json");
+    const COMPLETION_STREAM: &str = include_str!("../tests/fixtures/vllm/completions-stream-0.6.txt");
+    const METRICS: &str = include_str!("../tests/fixtures/vllm/metrics-0.6.txt");
+
+    fn timing(ttft_ms: u64, tokens_per_sec: Option<f64>) -> CompletionTiming {
+        CompletionTiming { time_to_first_token: Duration::from_millis(ttft_ms), tokens: 16, tokens_per_sec }
+    }
+
+    #[test]
+    fn parses_models_and_metrics() {
+        assert_eq!(parse_model_ids(MODELS).unwrap(), vec![MODEL]);
+        assert!(parse_model_ids("{\"object\":\"error\"}").is_err());
+
+        let metrics = parse_vllm_metrics(METRICS, MODEL);
+        assert_eq!(metrics.running, Some(31.0));
+        assert_eq!(metrics.waiting, Some(14.0));
+        assert!((metrics.kv_cache_usage.unwrap() - 93.72).abs() < 1e-9);
+        assert_eq!(parse_vllm_metrics(METRICS, "other-model"), VllmMetrics::default());
+    }
+
+    #[test]
+    fn times_tokens_across_split_chunks() {
+        let mut timer = StreamTimer::default();
+        // Split in the middle of an event to mimic TCP segmentation
+        let (head, tail) = COMPLETION_STREAM.split_at(100);
+        timer.feed(head.as_bytes(), Duration::from_millis(80)).unwrap();
+        timer.feed(tail.as_bytes(), Duration::from_millis(180)).unwrap();
+        let timing = timer.finish().unwrap();
+        assert_eq!(timing.time_to_first_token, Duration::from_millis(180));
+        assert_eq!(timing.tokens, 5);
+        assert_eq!(timing.tokens_per_sec, None);
+
+        let mut timer = StreamTimer::default();
+        for (i, event) in COMPLETION_STREAM.split_inclusive("\n\n").enumerate() {
+            timer.feed(event.as_bytes(), Duration::from_millis(100 + 25 * i as u64)).unwrap();
+        }
+        let timing = timer.finish().unwrap();
+        assert_eq!(timing.time_to_first_token, Duration::from_millis(100));
+        assert_eq!(timing.tokens_per_sec, Some(40.0));
+    }
+
+    #[test]
+    fn stream_errors_and_empty_completions_fail() {
+        let mut timer = StreamTimer::default();
+        let error = timer
+            .feed(b"data: {\"error\":{\"message\":\"CUDA out of memory\"}}\n\n", Duration::ZERO)
+            .unwrap_err();
+        assert!(error.to_string().contains("CUDA out of memory"));
+        assert!(StreamTimer::default().finish().is_err());
+    }
+
+    #[test]
+    fn thresholds_apply_to_timing_and_metrics() {
+        let service = ModelServiceConfig { min_tokens_per_sec: Some(20.0), ..Default::default() };
+
+        let (status, details) = evaluate_model_service(&service, MODEL, &timing(150, Some(45.2)), None);
+        assert_eq!(status, HealthStatus::Healthy);
+        assert_eq!(details, format!("model {}, TTFT 150ms, 45.2 tokens/s (16 tokens)", MODEL));
+
+        let (status, details) = evaluate_model_service(&service, MODEL, &timing(2500, Some(12.0)), None);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.contains("Slow time to first token: 2500ms, Slow generation: 12.0 tokens/s"), "{}", details);
+
+        assert_eq!(evaluate_model_service(&service, MODEL, &timing(12000, None), None).0, HealthStatus::Unhealthy);
+
+        let metrics = Ok(parse_vllm_metrics(METRICS, MODEL));
+        let (status, details) = evaluate_model_service(&service, MODEL, &timing(150, None), Some(&metrics));
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(details.contains("running 31, waiting 14, KV cache 93.7% - Issues: 14 requests waiting, KV cache nearly full: 93.7%"), "{}", details);
+
+        let lenient = ModelServiceConfig {
+            waiting_requests: Threshold::default(),
+            kv_cache_usage: Threshold { degraded: None, unhealthy: Some(99.0) },
+            ..Default::default()
+        };
+        assert_eq!(evaluate_model_service(&lenient, MODEL, &timing(150, None), Some(&metrics)).0, HealthStatus::Healthy);
+    }
+
+    /// A stand-in vLLM server answering every request on a local port until the test ends.
+    /// The completion stream is sent event by event so the first token arrives late.
+    async fn serve_vllm(health_status: &'static str, first_token_delay: Duration) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                tokio::spawn(async move {
+                    let mut request = vec![0u8; 8192];
+                    let read = stream.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]).to_string();
+                    let path = request.split_whitespace().nth(1).unwrap_or_default().to_string();
+
+                    let (status, content_type, body) = match path.as_str() {
+                        "/health" => (health_status, "text/plain", ""),
+                        "/v1/models" => ("200 OK", "application/json", MODELS),
+                        "/metrics" => ("200 OK", "text/plain; version=0.0.4", METRICS),
+                        "/v1/completions" => ("200 OK", "text/event-stream", ""),
+                        _ => ("404 Not Found", "text/plain", ""),
+                    };
+                    let header = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\nConnection: close\r\n\r\n", status, content_type);
+                    let _ = stream.write_all(header.as_bytes()).await;
+
+                    if path == "/v1/completions" {
+                        tokio::time::sleep(first_token_delay).await;
+                        for event in COMPLETION_STREAM.split_inclusive("\n\n") {
+                            let _ = stream.write_all(event.as_bytes()).await;
+                            let _ = stream.flush().await;
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                        }
+                    } else {
+                        let _ = stream.write_all(body.as_bytes()).await;
+                    }
+                });
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn checks_a_stand_in_vllm_server() {
+        let url = serve_vllm("200 OK", Duration::from_millis(150)).await;
+        let service = ModelServiceConfig {
+            url: url.clone(),
+            model: Some(MODEL.to_string()),
+            metrics: true,
+            ttft_ms: Threshold { degraded: Some(100.0), unhealthy: None },
+            ..Default::default()
+        };
+        let result = check_model_service(&service).await;
+        assert_eq!(result.status, HealthStatus::Degraded, "{}", result.details);
+        assert!(result.details.contains("Slow time to first token"), "{}", result.details);
+        assert!(result.details.contains("(5 tokens)"), "{}", result.details);
+        assert!(result.details.contains("KV cache 93.7%"), "{}", result.details);
+
+        let missing = ModelServiceConfig { url: url.clone(), model: Some("mistralai/Mistral-7B-v0.3".to_string()), ..Default::default() };
+        let result = check_model_service(&missing).await;
+        assert_eq!(result.status, HealthStatus::Unhealthy);
+        assert_eq!(result.details, format!("Model mistralai/Mistral-7B-v0.3 not loaded, serving: {}", MODEL));
+
+        let starting = serve_vllm("503 Service Unavailable", Duration::ZERO).await;
+        let result = check_model_service(&ModelServiceConfig { url: starting, ..Default::default() }).await;
+        assert_eq!(result.status, HealthStatus::Unhealthy);
+        assert!(result.details.contains("GET /health returned 503"), "{}", result.details);
+    }
+}
@@ -2,23 +2,39 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
 use colored::*;
-use log::info;
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tabled::{Table, Tabled};
 
 mod checks;
+mod clock;
+mod config;
 mod docker;
+mod events;
 mod services;
 mod gpu;
-
-use docker::DockerClient;
-use gpu::GpuMonitor;
+mod inference;
+mod net;
+mod orphans;
+mod prometheus;
+mod severity;
+
+use checks::{DiskTrend, SystemChecker};
+use config::Config;
+use docker::{DockerClient, DockerHost, LabeledContainer};
+use events::{ContainerEvent, EventTracker};
+use gpu::{GpuMonitor, GpuSampleWindow};
 
 #[derive(Parser)]
 #[command(name = "backend-ai-health-checker")]
 #[command(about = "Health check application for Backend.AI infrastructure")]
 struct Cli {
+    /// Path to a TOML config file (defaults to ./health-checker.toml if present)
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -52,6 +68,21 @@ enum Commands {
         #[arg(short, long, default_value = "table")]
         format: String,
     },
+    /// Check inference endpoints (vLLM, OpenAI-compatible) from [[model_services]]
+    Models {
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Check host memory, CPU, disk, configuration files and service ports
+    System {
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+    /// Check clock synchronization and skew to the NTP server and other hosts
+    Time {
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
     /// Check GPU hardware only
     Gpu {
         #[arg(short, long, default_value = "table")]
@@ -59,6 +90,26 @@ enum Commands {
         /// Show detailed GPU information
         #[arg(short, long)]
         detailed: bool,
+        /// Sample GPUs repeatedly for this long (e.g. 60s, 5m) and report per-metric statistics
+        #[arg(long, value_parser = parse_duration)]
+        sample_for: Option<Duration>,
+        /// Interval between samples with --sample-for [default: 5s]
+        #[arg(long, value_parser = parse_duration, requires = "sample_for")]
+        every: Option<Duration>,
+        /// Start a container that requests every GPU and check that it sees them all
+        #[arg(long)]
+        probe_container: bool,
+    },
+    /// Find kernel containers, scratch entries and krunner volumes with no live kernel
+    Orphans {
+        #[arg(short, long, default_value = "table")]
+        format: String,
+        /// Remove the orphaned resources that were found
+        #[arg(long)]
+        cleanup: bool,
+        /// With --cleanup, only list what would be removed
+        #[arg(long, requires = "cleanup")]
+        dry_run: bool,
     },
     /// Monitor services continuously
     Monitor {
@@ -117,24 +168,148 @@ pub struct HealthReport {
     pub degraded_count: usize,
     pub unknown_count: usize,
     pub checks: Vec<HealthCheckResult>,
+    /// Container events seen since the previous report (monitor mode only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent_events: Vec<ContainerEvent>,
     pub summary: String,
 }
 
+/// Outcome of lazily initializing an optional check backend
+pub enum Backend<T> {
+    Available(T),
+    Unavailable { status: HealthStatus, reason: String },
+}
+
 pub struct HealthChecker {
-    docker_client: DockerClient,
-    gpu_monitor: GpuMonitor,
+    config: Config,
+    docker_hosts: Vec<Arc<DockerHost>>,
+    gpu_monitor: tokio::sync::OnceCell<Backend<GpuMonitor>>,
+    /// GPU samples kept across checks, for `gpu --sample-for` and monitor mode
+    gpu_window: Option<tokio::sync::Mutex<GpuSampleWindow>>,
+    /// Free space history for time-to-full estimates, kept in monitor mode
+    disk_trend: Option<tokio::sync::Mutex<DiskTrend>>,
     timeout: Duration,
 }
 
 impl HealthChecker {
-    pub async fn new(timeout_secs: u64) -> Result<Self> {
-        let docker_client = DockerClient::new().await?;
-        let gpu_monitor = GpuMonitor::new();
-        Ok(Self {
-            docker_client,
-            gpu_monitor,
+    pub fn new(timeout_secs: u64, config: Config) -> Self {
+        Self {
+            docker_hosts: DockerHost::from_config(&config.docker).into_iter().map(Arc::new).collect(),
+            config,
+            gpu_monitor: tokio::sync::OnceCell::new(),
+            gpu_window: None,
+            disk_trend: None,
             timeout: Duration::from_secs(timeout_secs),
-        })
+        }
+    }
+
+    /// Keep GPU samples for `retention`, so GPU thresholds must be exceeded for a while to be reported
+    pub fn with_gpu_window(mut self, retention: Duration) -> Self {
+        let window = GpuSampleWindow::new(retention, self.config.gpu.idle.utilization);
+        self.gpu_window = Some(tokio::sync::Mutex::new(window));
+        self
+    }
+
+    /// Keep free space samples for `retention`, so disks report an estimated time to full
+    pub fn with_disk_trend(mut self, retention: Duration) -> Self {
+        self.disk_trend = Some(tokio::sync::Mutex::new(DiskTrend::new(retention)));
+        self
+    }
+
+    /// The configured Docker engines. Each connects on first use and
+    /// reconnects after a failure, see `DockerHost::client`.
+    async fn docker_hosts(&self) -> Backend<&[Arc<DockerHost>]> {
+        if !self.config.docker.enabled {
+            return Backend::Unavailable {
+                status: HealthStatus::Unknown,
+                reason: "Docker checks disabled in config".to_string(),
+            };
+        }
+
+        if self.config.docker.endpoints.is_empty() {
+            // Without a local engine there is nothing to report per host
+            if let Some(reason) = DockerClient::local_engine_missing() {
+                return Backend::Unavailable {
+                    status: HealthStatus::Unknown,
+                    reason,
+                };
+            }
+        }
+
+        Backend::Available(&self.docker_hosts)
+    }
+
+    async fn docker_client_for(&self, host_name: &str) -> Option<&DockerClient> {
+        let Backend::Available(hosts) = self.docker_hosts().await else {
+            return None;
+        };
+        let host = hosts.iter().find(|host| host.name == host_name)?;
+        host.client().await.ok()
+    }
+
+    /// The client of the engine on this machine, if it is reachable
+    async fn local_docker_client(&self) -> Option<&DockerClient> {
+        let Backend::Available(hosts) = self.docker_hosts().await else {
+            return None;
+        };
+        for host in hosts {
+            if let Ok(client) = host.client().await {
+                if client.is_local() {
+                    return Some(client);
+                }
+            }
+        }
+        None
+    }
+
+    /// Containers on the local Docker engine, or `None` when Docker cannot be asked.
+    /// Only the local engine is consulted since host processes and ports belong to this node.
+    async fn local_containers(&self) -> Option<Vec<LabeledContainer>> {
+        let client = self.local_docker_client().await?;
+
+        match client.list_labeled_containers().await {
+            Ok(containers) => Some(containers),
+            Err(e) => {
+                warn!("Cannot list containers of the local engine: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn gpu_monitor(&self) -> &Backend<GpuMonitor> {
+        self.gpu_monitor.get_or_init(|| async {
+            if self.config.gpu.enabled {
+                Backend::Available(GpuMonitor::new(self.config.gpu.clone()).await)
+            } else {
+                Backend::Unavailable {
+                    status: HealthStatus::Unknown,
+                    reason: "GPU checks disabled in config".to_string(),
+                }
+            }
+        }).await
+    }
+
+    fn backend_unavailable_result(service_name: &str, status: &HealthStatus, reason: &str) -> HealthCheckResult {
+        HealthCheckResult {
+            service_name: service_name.to_string(),
+            status: status.clone(),
+            response_time_ms: 0,
+            details: reason.to_string(),
+            timestamp: Utc::now(),
+            error_message: None,
+        }
+    }
+
+    fn category_failed_result(category: &str, error: &anyhow::Error) -> HealthCheckResult {
+        error!("{} checks failed: {}", category, error);
+        HealthCheckResult {
+            service_name: category.to_string(),
+            status: HealthStatus::Unhealthy,
+            response_time_ms: 0,
+            details: format!("{} checks failed: {}", category, error),
+            timestamp: Utc::now(),
+            error_message: Some(error.to_string()),
+        }
     }
 
     pub async fn run_all_checks(&self) -> Result<HealthReport> {
@@ -142,25 +317,60 @@ impl HealthChecker {
         let start_time = Instant::now();
         let mut results = Vec::new();
 
+        // A failing category is reported as a result so the remaining categories still run
+
         // Docker container checks
         info!("Checking Docker containers...");
-        let mut docker_results = self.check_docker_containers().await?;
-        results.append(&mut docker_results);
+        match self.check_docker_containers().await {
+            Ok(mut docker_results) => results.append(&mut docker_results),
+            Err(e) => results.push(Self::category_failed_result("Docker", &e)),
+        }
 
         // Infrastructure service checks
         info!("Checking infrastructure services...");
-        let mut infra_results = self.check_infrastructure_services().await?;
-        results.append(&mut infra_results);
+        match self.check_infrastructure_services().await {
+            Ok(mut infra_results) => results.append(&mut infra_results),
+            Err(e) => results.push(Self::category_failed_result("Infrastructure", &e)),
+        }
 
         // Backend.AI service checks
         info!("Checking Backend.AI services...");
-        let mut service_results = self.check_backend_ai_services().await?;
-        results.append(&mut service_results);
+        match self.check_backend_ai_services().await {
+            Ok(mut service_results) => results.append(&mut service_results),
+            Err(e) => results.push(Self::category_failed_result("Backend.AI Services", &e)),
+        }
 
         // GPU hardware checks
         info!("Checking GPU hardware...");
-        let mut gpu_results = self.gpu_monitor.get_gpu_health_checks().await?;
-        results.append(&mut gpu_results);
+        match self.check_gpu_hardware().await {
+            Ok(mut gpu_results) => results.append(&mut gpu_results),
+            Err(e) => results.push(Self::category_failed_result("GPU", &e)),
+        }
+
+        // Host resource checks
+        info!("Checking system resources...");
+        match self.check_system().await {
+            Ok(mut system_results) => results.append(&mut system_results),
+            Err(e) => results.push(Self::category_failed_result("System", &e)),
+        }
+
+        // Clock synchronization and skew between hosts
+        if self.config.time.enabled {
+            info!("Checking clock synchronization...");
+            match self.check_time().await {
+                Ok(mut time_results) => results.append(&mut time_results),
+                Err(e) => results.push(Self::category_failed_result("Time", &e)),
+            }
+        }
+
+        // Model service checks, only when endpoints are configured
+        if !self.config.model_services.is_empty() {
+            info!("Checking model services...");
+            match self.check_model_services().await {
+                Ok(mut model_results) => results.append(&mut model_results),
+                Err(e) => results.push(Self::category_failed_result("Model Services", &e)),
+            }
+        }
 
         let total_time = start_time.elapsed();
         info!("Health check completed in {:.2}s", total_time.as_secs_f64());
@@ -169,16 +379,76 @@ impl HealthChecker {
     }
 
     pub async fn check_docker_containers(&self) -> Result<Vec<HealthCheckResult>> {
+        let docker_hosts = match self.docker_hosts().await {
+            Backend::Available(hosts) => hosts,
+            Backend::Unavailable { status, reason } => {
+                return Ok(vec![Self::backend_unavailable_result("Docker", &status, &reason)]);
+            }
+        };
+
+        let host_results = futures::future::join_all(
+            docker_hosts.iter().map(|host| self.check_docker_host(host))
+        ).await;
+
+        Ok(host_results.into_iter().flatten().collect())
+    }
+
+    async fn check_docker_host(&self, host: &DockerHost) -> Vec<HealthCheckResult> {
+        let docker_client = match host.client().await {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Docker engine on {} is unreachable: {}", host.name, e);
+                return vec![HealthCheckResult {
+                    service_name: format!("Docker Engine ({})", host.name),
+                    status: HealthStatus::Unhealthy,
+                    response_time_ms: 0,
+                    details: format!("Unreachable: {}", e),
+                    timestamp: Utc::now(),
+                    error_message: Some(e.to_string()),
+                }];
+            }
+        };
+
         let mut results = Vec::new();
-        let containers = self.docker_client.list_backend_ai_containers().await?;
+
+        let start_time = Instant::now();
+        let (status, details) = SystemChecker::check_docker_daemon(docker_client, &self.config.docker.daemon)
+            .await
+            .unwrap_or_else(|e| (HealthStatus::Unhealthy, format!("Daemon check failed: {}", e)));
+        results.push(HealthCheckResult {
+            service_name: format!("Docker Daemon ({})", host.name),
+            status,
+            response_time_ms: start_time.elapsed().as_millis() as u64,
+            details,
+            timestamp: Utc::now(),
+            error_message: None,
+        });
+
+        let containers = match docker_client.list_backend_ai_containers().await {
+            Ok(containers) => containers,
+            Err(e) => {
+                error!("Failed to list containers on {}: {}", host.name, e);
+                results.push(HealthCheckResult {
+                    service_name: format!("Docker Containers ({})", host.name),
+                    status: HealthStatus::Unhealthy,
+                    response_time_ms: 0,
+                    details: format!("Container listing failed: {}", e),
+                    timestamp: Utc::now(),
+                    error_message: Some(e.to_string()),
+                });
+                return results;
+            }
+        };
 
         for container in containers {
             let start_time = Instant::now();
-            let (status, details) = self.docker_client.check_container_health(&container.id).await?;
+            let (status, details) = docker_client.check_container_health(&container.id)
+                .await
+                .unwrap_or_else(|e| (HealthStatus::Unknown, format!("Inspection failed: {}", e)));
             let response_time = start_time.elapsed().as_millis() as u64;
 
             results.push(HealthCheckResult {
-                service_name: container.name,
+                service_name: format!("{} ({})", container.name, container.host),
                 status,
                 response_time_ms: response_time,
                 details,
@@ -187,10 +457,18 @@ impl HealthChecker {
             });
         }
 
-        Ok(results)
+        results
     }
 
     pub async fn check_infrastructure_services(&self) -> Result<Vec<HealthCheckResult>> {
+        if !self.config.infrastructure.enabled {
+            return Ok(vec![Self::backend_unavailable_result(
+                "Infrastructure",
+                &HealthStatus::Unknown,
+                "Infrastructure checks disabled in config",
+            )]);
+        }
+
         let mut results = Vec::new();
 
         // PostgreSQL check
@@ -226,8 +504,38 @@ impl HealthChecker {
         Ok(results)
     }
 
+    pub async fn check_system(&self) -> Result<Vec<HealthCheckResult>> {
+        let mut disk_trend = match &self.disk_trend {
+            Some(trend) => Some(trend.lock().await),
+            None => None,
+        };
+        let containers = self.local_containers().await;
+        SystemChecker::comprehensive_system_check(&self.config.system, disk_trend.as_deref_mut(), containers.as_deref()).await
+    }
+
     pub async fn check_gpu_hardware(&self) -> Result<Vec<HealthCheckResult>> {
-        self.gpu_monitor.get_gpu_health_checks().await
+        match self.gpu_monitor().await {
+            Backend::Available(gpu_monitor) => {
+                let containers = self.gpu_container_directory().await;
+                let advertised_slots = self.fetch_advertised_slots().await;
+                let container_runtime = self.container_runtime().await;
+                let mut gpu_window = match &self.gpu_window {
+                    Some(window) => Some(window.lock().await),
+                    None => None,
+                };
+                gpu_monitor
+                    .get_gpu_health_checks(
+                        containers.as_ref(),
+                        advertised_slots.as_ref(),
+                        container_runtime.as_ref(),
+                        gpu_window.as_deref_mut(),
+                    )
+                    .await
+            }
+            Backend::Unavailable { status, reason } => {
+                Ok(vec![Self::backend_unavailable_result("GPU Hardware", status, reason)])
+            }
+        }
     }
 
     fn generate_report(&self, results: Vec<HealthCheckResult>) -> Result<HealthReport> {
@@ -260,32 +568,66 @@ impl HealthChecker {
             degraded_count,
             unknown_count,
             checks: results,
+            recent_events: Vec::new(),
             summary,
         })
     }
 
     pub async fn monitor(&self, interval_secs: u64, max_checks: u32) -> Result<()> {
+        let tracker = EventTracker::new(events::EVENT_HISTORY_RETENTION.max(Duration::from_secs(interval_secs)));
+        let mut events = self.spawn_event_watchers(&tracker).await;
         let mut check_count = 0;
-        
+        let mut window_start = Utc::now();
+
         loop {
             if max_checks > 0 && check_count >= max_checks {
                 break;
             }
 
-            let report = self.run_all_checks().await?;
+            let mut report = self.run_all_checks().await?;
+            // Events between samples make short outages visible even when the container recovered
+            report.recent_events = tracker.events_since(window_start);
+            window_start = report.timestamp;
             self.print_summary_report(&report);
 
             check_count += 1;
             
             if max_checks == 0 || check_count < max_checks {
                 info!("Waiting {} seconds for next check...", interval_secs);
-                tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+                let next_check = tokio::time::Instant::now() + Duration::from_secs(interval_secs);
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(next_check) => break,
+                        Some(event) = events.recv() => {
+                            let results = self.recheck_after_event(&event, &tracker).await;
+                            self.print_event_recheck(&event, &results);
+                        }
+                    }
+                }
             }
         }
 
         Ok(())
     }
 
+    fn print_event_recheck(&self, event: &ContainerEvent, results: &[HealthCheckResult]) {
+        println!("\n{} {}", "Container event:".bold(), event);
+        for result in results {
+            println!("  {}: {} - {}", result.service_name, result.status, result.details);
+        }
+    }
+
+    fn print_recent_events(&self, report: &HealthReport) {
+        if report.recent_events.is_empty() {
+            return;
+        }
+
+        println!("Recent container events:");
+        for event in &report.recent_events {
+            println!("  {}", event);
+        }
+    }
+
     fn print_table_report(&self, report: &HealthReport) {
         println!("\n{}", "Backend.AI Health Check Report".bold().underline());
         println!("Timestamp: {}", report.timestamp.format("%Y-%m-%d %H:%M:%S UTC"));
@@ -294,6 +636,7 @@ impl HealthChecker {
 
         let table = Table::new(&report.checks);
         println!("{}", table);
+        self.print_recent_events(report);
         println!("\n{}", report.summary);
     }
 
@@ -316,19 +659,39 @@ impl HealthChecker {
                 result.response_time_ms
             );
         }
-        
+
+        self.print_recent_events(report);
         println!("{}", report.summary);
     }
 }
 
+/// Parse durations such as `500ms`, `30s`, `5m` or `1h`; a bare number is in seconds
+fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().map_err(|_| format!("invalid duration: {}", value))?;
+    let duration = match unit.trim() {
+        "ms" => Duration::from_millis(number),
+        "" | "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number * 60),
+        "h" => Duration::from_secs(number * 3600),
+        unit => return Err(format!("unknown unit '{}' in duration {}, expected ms, s, m or h", unit, value)),
+    };
+    if duration.is_zero() {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(duration)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
 
     match cli.command {
         Commands::All { format, verbose: _, timeout } => {
-            let checker = HealthChecker::new(timeout).await?;
+            let checker = HealthChecker::new(timeout, config);
             let report = checker.run_all_checks().await?;
 
             match format.as_str() {
@@ -338,7 +701,7 @@ async fn main() -> Result<()> {
             }
         }
         Commands::Docker { format } => {
-            let checker = HealthChecker::new(30).await?;
+            let checker = HealthChecker::new(30, config);
             let results = checker.check_docker_containers().await?;
             let report = checker.generate_report(results)?;
 
@@ -349,7 +712,7 @@ async fn main() -> Result<()> {
             }
         }
         Commands::Services { format } => {
-            let checker = HealthChecker::new(30).await?;
+            let checker = HealthChecker::new(30, config);
             let results = checker.check_backend_ai_services().await?;
             let report = checker.generate_report(results)?;
 
@@ -360,7 +723,7 @@ async fn main() -> Result<()> {
             }
         }
         Commands::Infrastructure { format } => {
-            let checker = HealthChecker::new(30).await?;
+            let checker = HealthChecker::new(30, config);
             let results = checker.check_infrastructure_services().await?;
             let report = checker.generate_report(results)?;
 
@@ -370,18 +733,82 @@ async fn main() -> Result<()> {
                 _ => checker.print_table_report(&report),
             }
         }
-        Commands::Gpu { format, detailed } => {
-            let checker = HealthChecker::new(30).await?;
+        Commands::Models { format } => {
+            let checker = HealthChecker::new(30, config);
+            let results = checker.check_model_services().await?;
+            let report = checker.generate_report(results)?;
+
+            match format.as_str() {
+                "json" => checker.print_json_report(&report)?,
+                "summary" => checker.print_summary_report(&report),
+                _ => checker.print_table_report(&report),
+            }
+        }
+        Commands::System { format } => {
+            let checker = HealthChecker::new(30, config);
+            let results = checker.check_system().await?;
+            let report = checker.generate_report(results)?;
+
+            match format.as_str() {
+                "json" => checker.print_json_report(&report)?,
+                "summary" => checker.print_summary_report(&report),
+                _ => checker.print_table_report(&report),
+            }
+        }
+        Commands::Time { format } => {
+            let checker = HealthChecker::new(30, config);
+            let results = checker.check_time().await?;
+            let report = checker.generate_report(results)?;
+
+            match format.as_str() {
+                "json" => checker.print_json_report(&report)?,
+                "summary" => checker.print_summary_report(&report),
+                _ => checker.print_table_report(&report),
+            }
+        }
+        Commands::Gpu { format, detailed, sample_for, every, probe_container } => {
+            let mut config = config;
+            config.gpu.container_runtime.probe |= probe_container;
+            let mut checker = HealthChecker::new(30, config);
+            if let Some(sample_for) = sample_for {
+                let every = every.unwrap_or(Duration::from_secs(5));
+                // The retention has to cover the sample taken by the health check itself
+                checker = checker.with_gpu_window(sample_for + every);
+                checker.sample_gpus(sample_for, every).await;
+            }
             let results = checker.check_gpu_hardware().await?;
             let report = checker.generate_report(results)?;
 
-            if detailed {
+            let mut detailed_gpu_infos = None;
+            if let (true, Backend::Available(gpu_monitor)) = (detailed, checker.gpu_monitor().await) {
+                let containers = checker.gpu_container_directory().await;
+                detailed_gpu_infos = Some(gpu_monitor.get_detailed_gpu_info(containers.as_ref()).await?);
+            }
+
+            if let (Some(gpu_infos), Backend::Available(gpu_monitor), false) =
+                (&detailed_gpu_infos, checker.gpu_monitor().await, format == "json")
+            {
                 // Show detailed GPU information
-                let gpu_infos = checker.gpu_monitor.get_detailed_gpu_info().await?;
-                println!("GPU Summary: {}\n", checker.gpu_monitor.get_gpu_summary());
+                println!("GPU Summary: {}\n", gpu_monitor.get_gpu_summary());
+
+                if let Ok(pci_gpus) = gpu_monitor.get_pci_gpus() {
+                    if !pci_gpus.is_empty() {
+                        println!("PCI Devices:");
+                        for pci_gpu in &pci_gpus {
+                            println!("  {}: {}", pci_gpu.bus_id, pci_gpu.describe());
+                        }
+                        println!();
+                    }
+                }
                 
                 for gpu_info in gpu_infos {
                     println!("GPU {}: {}", gpu_info.id, gpu_info.name);
+                    if let Some(uuid) = &gpu_info.uuid {
+                        println!("  UUID: {}", uuid);
+                    }
+                    if let Some(pci_bus_id) = &gpu_info.pci_bus_id {
+                        println!("  PCI Bus: {}", pci_bus_id);
+                    }
                     println!("  Driver: {}", gpu_info.driver_version);
                     if let Some(cuda) = &gpu_info.cuda_version {
                         println!("  CUDA: {}", cuda);
@@ -389,32 +816,142 @@ async fn main() -> Result<()> {
                     println!("  Memory: {}/{} MB ({:.1}%)", 
                         gpu_info.memory_used / 1024 / 1024,
                         gpu_info.memory_total / 1024 / 1024,
-                        (gpu_info.memory_used as f64 / gpu_info.memory_total as f64) * 100.0
+                        (gpu_info.memory_used as f64 / gpu_info.memory_total.max(1) as f64) * 100.0
                     );
                     println!("  Utilization: GPU {}%, Memory {}%", 
                         gpu_info.utilization_gpu, gpu_info.utilization_memory);
-                    println!("  Temperature: {}°C", gpu_info.temperature);
+                    if gpu_info.temperature > 0 || gpu_info.temperature_junction.is_none() {
+                        println!("  Temperature: {}°C", gpu_info.temperature);
+                    }
+                    if let Some(junction) = gpu_info.temperature_junction {
+                        println!("  Junction Temperature: {}°C", junction);
+                    }
+                    if let Some(memory) = gpu_info.temperature_memory {
+                        println!("  Memory Temperature: {}°C", memory);
+                    }
                     println!("  Power: {:.1}W / {:.1}W", gpu_info.power_usage, gpu_info.power_limit);
+                    if let Some(fan_speed) = gpu_info.fan_speed {
+                        println!("  Fan: {}%", fan_speed);
+                    }
+                    let reliability = &gpu_info.reliability;
+                    if let Some(uncorrected) = reliability.ecc_volatile_uncorrected {
+                        println!("  ECC Errors: {} corrected, {} uncorrected since driver load",
+                            reliability.ecc_volatile_corrected.unwrap_or(0), uncorrected);
+                    }
+                    if reliability.remapped_rows_pending == Some(true) || reliability.retired_pages_pending == Some(true) {
+                        println!("  Memory Repair: pending, GPU reset required");
+                    }
+                    if !reliability.throttle_reasons.is_empty() {
+                        println!("  Throttle Reasons: {}", reliability.throttle_reasons.join(", "));
+                    }
+                    if !reliability.xid_events.is_empty() {
+                        println!("  XID Events:");
+                        for event in &reliability.xid_events {
+                            println!("    XID {}: {}", event.code, event.message);
+                        }
+                    }
                     
+                    if let Some(mig_mode) = &gpu_info.mig_mode {
+                        println!("  MIG: {}{}",
+                            if mig_mode.current { "enabled" } else { "disabled" },
+                            if mig_mode.pending != mig_mode.current { " (change pending reset)" } else { "" });
+                    }
+                    for mig_device in &gpu_info.mig_devices {
+                        println!("    MIG {} {} (GI {}, CI {}): {}/{} MB, {} processes",
+                            mig_device.index,
+                            mig_device.profile.as_deref().unwrap_or("unknown profile"),
+                            mig_device.gpu_instance_id,
+                            mig_device.compute_instance_id,
+                            mig_device.memory_used / 1024 / 1024,
+                            mig_device.memory_total / 1024 / 1024,
+                            mig_device.processes.len());
+                        if let Some(uuid) = &mig_device.uuid {
+                            println!("      UUID: {}", uuid);
+                        }
+                    }
+
                     if !gpu_info.processes.is_empty() {
                         println!("  Processes:");
                         for process in &gpu_info.processes {
                             println!("    PID {}: {} ({}MB)", 
                                 process.pid, process.name, process.memory_used / 1024 / 1024);
+                            if let Some(owner) = &process.owner {
+                                println!("      Owner: {}", owner.describe());
+                            }
+                            if let Some(reason) = &process.leaked {
+                                println!("      Leaked: {}", reason);
+                            }
                         }
                     }
                     println!();
                 }
             }
 
+            let window_stats = checker.gpu_window_stats().await;
+            if !window_stats.is_empty() && format != "json" {
+                println!("GPU Samples:");
+                for stats in &window_stats {
+                    println!("  GPU {} ({}): {} samples over {}s", stats.id, stats.name, stats.samples, stats.span_secs);
+                    for (label, metric, unit) in stats.metrics() {
+                        println!("    {:<12} min {:.1}{unit}, avg {:.1}{unit}, max {:.1}{unit}, p95 {:.1}{unit}",
+                            format!("{}:", label), metric.min, metric.avg, metric.max, metric.p95);
+                    }
+                }
+                println!();
+            }
+
             match format.as_str() {
+                // Per-process owners and sample statistics are not part of the report, so they go into one document
+                "json" if detailed_gpu_infos.is_some() || !window_stats.is_empty() => {
+                    let mut document = serde_json::json!({ "report": report });
+                    if let Some(gpu_infos) = &detailed_gpu_infos {
+                        document["gpus"] = serde_json::to_value(gpu_infos)?;
+                    }
+                    if !window_stats.is_empty() {
+                        document["samples"] = serde_json::to_value(&window_stats)?;
+                    }
+                    println!("{}", serde_json::to_string_pretty(&document)?);
+                }
                 "json" => checker.print_json_report(&report)?,
                 "summary" => checker.print_summary_report(&report),
                 _ => checker.print_table_report(&report),
             }
         }
+        Commands::Orphans { format, cleanup, dry_run } => {
+            let checker = HealthChecker::new(30, config);
+            let orphans = checker.find_orphans().await;
+            let report = checker.generate_report(HealthChecker::orphan_results(&orphans))?;
+
+            match format.as_str() {
+                "json" => checker.print_json_report(&report)?,
+                "summary" => checker.print_summary_report(&report),
+                _ => checker.print_table_report(&report),
+            }
+
+            if cleanup {
+                // Keep stdout parseable in JSON mode
+                let print = |line: &str| if format == "json" { eprintln!("{}", line) } else { println!("{}", line) };
+                match &orphans {
+                    Ok(scan) if scan.orphans.is_empty() => print("Nothing to clean up"),
+                    Ok(scan) => {
+                        for action in checker.cleanup_orphans(&scan.orphans, dry_run).await {
+                            print(&action);
+                        }
+                    }
+                    Err(e) => print(&format!("Cleanup skipped: {:#}", e)),
+                }
+            }
+        }
         Commands::Monitor { interval, max_checks } => {
-            let checker = HealthChecker::new(30).await?;
+            let window_secs = config.gpu.sampling.window_secs;
+            let trend_window_secs = config.system.trend_window_secs;
+            let mut checker = HealthChecker::new(30, config);
+            if window_secs > 0 {
+                checker = checker.with_gpu_window(Duration::from_secs(window_secs));
+            }
+            if trend_window_secs > 0 {
+                checker = checker.with_disk_trend(Duration::from_secs(trend_window_secs));
+            }
             checker.monitor(interval, max_checks).await?;
         }
     }
@@ -0,0 +1,10 @@
+/// Short reason for a failed request; reqwest repeats the whole cause chain in its message
+pub fn request_failure(e: reqwest::Error) -> String {
+    if e.is_connect() {
+        "connection refused".to_string()
+    } else if e.is_timeout() {
+        "timed out".to_string()
+    } else {
+        e.to_string()
+    }
+}
@@ -0,0 +1,549 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::docker::{KrunnerVolume, KRUNNER_VOLUME_PREFIX};
+use crate::{Backend, HealthCheckResult, HealthStatus};
+
+/// Kernel states in which the manager no longer expects a container to exist.
+/// `ERROR` is deliberately left out: the manager keeps an errored kernel's row until it is
+/// terminated, and its container is kept for inspection, so it is not treated as an orphan.
+const DEAD_KERNEL_STATUSES: &[&str] = &["TERMINATED", "CANCELLED"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OrphanKind {
+    KernelContainer,
+    ScratchDirectory,
+    ScratchImage,
+    KrunnerVolume,
+}
+
+impl std::fmt::Display for OrphanKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            OrphanKind::KernelContainer => "kernel container",
+            OrphanKind::ScratchDirectory => "scratch directory",
+            OrphanKind::ScratchImage => "scratch image",
+            OrphanKind::KrunnerVolume => "krunner volume",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A host, or the local scratch root, that orphan detection could not inspect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedScan {
+    pub host: String,
+    pub reason: String,
+}
+
+/// Orphans found on every host that could be inspected
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrphanScan {
+    pub orphans: Vec<OrphanResource>,
+    pub skipped: Vec<SkippedScan>,
+}
+
+impl OrphanScan {
+    fn skip(&mut self, host: &str, reason: String) {
+        warn!("Skipping orphan detection on {}: {}", host, reason);
+        self.skipped.push(SkippedScan { host: host.to_string(), reason });
+    }
+}
+
+/// A container, scratch path or volume left behind with no live kernel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanResource {
+    pub host: String,
+    pub kind: OrphanKind,
+    /// Container ID, scratch path or volume name
+    pub target: String,
+    pub display_name: String,
+    pub kernel_id: Option<String>,
+    pub session_id: Option<String>,
+    pub age_secs: Option<u64>,
+    pub resource_usage: String,
+}
+
+/// Human-readable duration such as `3d4h` or `12m30s`
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes, seconds) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+    if days > 0 {
+        format!("{}d{}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+fn age_secs(since: DateTime<Utc>) -> u64 {
+    (Utc::now() - since).num_seconds().max(0) as u64
+}
+
+fn looks_like_kernel_id(name: &str) -> bool {
+    name.len() == 36 && name.chars().all(|c| c.is_ascii_hexdigit() || c == '-') && name.matches('-').count() == 4
+}
+
+/// Scratch entries under the agent scratch root whose kernel is neither alive nor backed by a container.
+/// Entries are `<kernel_id>`, `<kernel_id>_tmp` directories and `<kernel_id>.img` loop images.
+pub fn find_orphaned_scratch(
+    scratch_root: &Path,
+    known_kernel_ids: &HashSet<String>,
+    grace_period_secs: u64,
+) -> Result<Vec<OrphanResource>> {
+    let mut orphans = Vec::new();
+
+    for entry in std::fs::read_dir(scratch_root)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let metadata = entry.metadata()?;
+
+        let (kernel_id, kind) = if let Some(id) = file_name.strip_suffix(".img") {
+            (id, OrphanKind::ScratchImage)
+        } else if let Some(id) = file_name.strip_suffix("_tmp") {
+            (id, OrphanKind::ScratchDirectory)
+        } else {
+            (file_name.as_str(), OrphanKind::ScratchDirectory)
+        };
+
+        if !looks_like_kernel_id(kernel_id) || known_kernel_ids.contains(kernel_id) {
+            continue;
+        }
+
+        let age = metadata.modified().ok()
+            .map(|modified| age_secs(DateTime::<Utc>::from(modified)));
+        if age.is_some_and(|age| age < grace_period_secs) {
+            continue;
+        }
+
+        let size = if metadata.is_dir() {
+            directory_size(&entry.path())
+        } else {
+            metadata.len()
+        };
+
+        orphans.push(OrphanResource {
+            host: crate::docker::LOCAL_DOCKER_HOST.to_string(),
+            kind,
+            target: entry.path().display().to_string(),
+            display_name: file_name.clone(),
+            kernel_id: Some(kernel_id.to_string()),
+            session_id: None,
+            age_secs: age,
+            resource_usage: format!("{}MB on disk", size / 1024 / 1024),
+        });
+    }
+
+    Ok(orphans)
+}
+
+fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Unused krunner volumes superseded by a newer version for the same arch and distro.
+/// Volume names follow `backendai-krunner.v{version}.{arch}.{distro}`.
+pub fn find_orphaned_krunner_volumes(host: &str, volumes: &[KrunnerVolume]) -> Vec<OrphanResource> {
+    fn parse(name: &str) -> Option<(u32, String)> {
+        let rest = name.strip_prefix(KRUNNER_VOLUME_PREFIX)?;
+        let (version, platform) = rest.split_once('.')?;
+        Some((version.strip_prefix('v')?.parse().ok()?, platform.to_string()))
+    }
+
+    let mut latest: HashMap<String, u32> = HashMap::new();
+    for (version, platform) in volumes.iter().filter_map(|v| parse(&v.name)) {
+        let entry = latest.entry(platform).or_insert(version);
+        *entry = (*entry).max(version);
+    }
+
+    volumes.iter()
+        .filter(|volume| !volume.in_use)
+        .filter_map(|volume| {
+            let (version, platform) = parse(&volume.name)?;
+            (version < latest[&platform]).then(|| OrphanResource {
+                host: host.to_string(),
+                kind: OrphanKind::KrunnerVolume,
+                target: volume.name.clone(),
+                display_name: volume.name.clone(),
+                kernel_id: None,
+                session_id: None,
+                age_secs: None,
+                resource_usage: volume.size_bytes
+                    .map(|size| format!("{}MB", size / 1024 / 1024))
+                    .unwrap_or_else(|| "size unknown".to_string()),
+            })
+        })
+        .collect()
+}
+
+impl crate::HealthChecker {
+    /// Kernel IDs the manager database still considers alive
+    pub(crate) async fn fetch_alive_kernel_ids(&self) -> Result<HashSet<String>> {
+        use tokio_postgres::NoTls;
+
+        let (client, connection) = tokio_postgres::connect(&self.config.infrastructure.postgres_dsn, NoTls)
+            .await
+            .context("Cannot determine alive kernels: manager database unreachable")?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                error!("PostgreSQL connection error: {}", e);
+            }
+        });
+
+        let dead_statuses: Vec<String> = DEAD_KERNEL_STATUSES.iter().map(|s| s.to_string()).collect();
+        let rows = client
+            .query(
+                "SELECT id::text FROM kernels WHERE status::text <> ALL($1)",
+                &[&dead_statuses],
+            )
+            .await
+            .context("Cannot determine alive kernels: kernels query failed")?;
+
+        Ok(rows.iter().map(|row| row.get::<_, String>(0)).collect())
+    }
+
+    /// Find kernel containers, scratch entries and krunner volumes with no live kernel.
+    /// Fails when the manager database is unreachable, so nothing is reported as orphaned by mistake.
+    /// Hosts that cannot be inspected are recorded in `OrphanScan::skipped`.
+    pub async fn find_orphans(&self) -> Result<OrphanScan> {
+        let alive = self.fetch_alive_kernel_ids().await?;
+        info!("Manager database reports {} alive kernels", alive.len());
+
+        let grace_period = self.config.orphans.grace_period_secs;
+        let mut scan = OrphanScan::default();
+        let mut known_kernel_ids = alive.clone();
+
+        if let Backend::Available(hosts) = self.docker_hosts().await {
+            for host in hosts {
+                let client = match host.client().await {
+                    Ok(client) => client,
+                    Err(e) => {
+                        scan.skip(&host.name, format!("Docker engine unreachable: {}", e));
+                        continue;
+                    }
+                };
+
+                match client.list_kernel_containers().await {
+                    Ok(containers) => {
+                        for container in containers {
+                            known_kernel_ids.insert(container.kernel_id.clone());
+
+                            let age = age_secs(container.created);
+                            if alive.contains(&container.kernel_id) || age < grace_period {
+                                continue;
+                            }
+
+                            let resource_usage = if container.state == "running" {
+                                client.get_container_stats(&container.id).await.unwrap_or_else(|e| {
+                                    warn!("Cannot read stats of container {}: {}", container.id, e);
+                                    format!("Stats unavailable: {}", e)
+                                })
+                            } else {
+                                format!("Not running ({})", container.state)
+                            };
+
+                            scan.orphans.push(OrphanResource {
+                                host: container.host.clone(),
+                                kind: OrphanKind::KernelContainer,
+                                target: container.id.clone(),
+                                display_name: container.name.clone(),
+                                kernel_id: Some(container.kernel_id.clone()),
+                                session_id: container.session_id.clone(),
+                                age_secs: Some(age),
+                                resource_usage,
+                            });
+                        }
+                    }
+                    Err(e) => scan.skip(&host.name, format!("Cannot list kernel containers: {}", e)),
+                }
+
+                match client.list_krunner_volumes().await {
+                    Ok(volumes) => scan.orphans.extend(find_orphaned_krunner_volumes(&host.name, &volumes)),
+                    Err(e) => scan.skip(&host.name, format!("Cannot list krunner volumes: {}", e)),
+                }
+            }
+        }
+
+        let scratch_root = &self.config.orphans.scratch_root;
+        if scratch_root.is_dir() {
+            match find_orphaned_scratch(scratch_root, &known_kernel_ids, grace_period) {
+                Ok(orphans) => scan.orphans.extend(orphans),
+                Err(e) => scan.skip(
+                    crate::docker::LOCAL_DOCKER_HOST,
+                    format!("Cannot scan scratch root {}: {}", scratch_root.display(), e),
+                ),
+            }
+        } else {
+            warn!("Scratch root {} not found; skipping scratch detection", scratch_root.display());
+        }
+
+        Ok(scan)
+    }
+
+    pub fn orphan_results(found: &Result<OrphanScan>) -> Vec<HealthCheckResult> {
+        let scan = match found {
+            Ok(scan) => scan,
+            Err(e) => {
+                error!("Orphan detection failed: {:#}", e);
+                return vec![HealthCheckResult {
+                    service_name: "Orphaned Resources".to_string(),
+                    status: HealthStatus::Unknown,
+                    response_time_ms: 0,
+                    details: format!("{:#}", e),
+                    timestamp: Utc::now(),
+                    error_message: Some(e.to_string()),
+                }];
+            }
+        };
+
+        let skipped = scan.skipped.iter().map(|skipped| HealthCheckResult {
+            service_name: format!("Orphaned Resources ({})", skipped.host),
+            status: HealthStatus::Unknown,
+            response_time_ms: 0,
+            details: skipped.reason.clone(),
+            timestamp: Utc::now(),
+            error_message: Some(skipped.reason.clone()),
+        });
+
+        let orphans = &scan.orphans;
+        if orphans.is_empty() && scan.skipped.is_empty() {
+            return vec![HealthCheckResult {
+                service_name: "Orphaned Resources".to_string(),
+                status: HealthStatus::Healthy,
+                response_time_ms: 0,
+                details: "No orphaned kernel containers, scratch entries or krunner volumes".to_string(),
+                timestamp: Utc::now(),
+                error_message: None,
+            }];
+        }
+
+        orphans.iter().map(|orphan| {
+            let mut details = format!("{} {}", orphan.kind, orphan.display_name);
+            if let Some(kernel_id) = &orphan.kernel_id {
+                details.push_str(&format!(", kernel {}", kernel_id));
+            }
+            if let Some(session_id) = &orphan.session_id {
+                details.push_str(&format!(", session {}", session_id));
+            }
+            if let Some(age) = orphan.age_secs {
+                details.push_str(&format!(", age {}", format_duration(age)));
+            }
+            details.push_str(&format!(" - {}", orphan.resource_usage));
+
+            HealthCheckResult {
+                service_name: format!("Orphaned {} ({})", orphan.kind, orphan.host),
+                status: HealthStatus::Degraded,
+                response_time_ms: 0,
+                details,
+                timestamp: Utc::now(),
+                error_message: None,
+            }
+        }).chain(skipped).collect()
+    }
+
+    /// Remove orphaned resources, or only describe the removals when `dry_run` is set
+    pub async fn cleanup_orphans(&self, orphans: &[OrphanResource], dry_run: bool) -> Vec<String> {
+        let mut actions = Vec::new();
+
+        for orphan in orphans {
+            let description = format!("{} {} on {}", orphan.kind, orphan.target, orphan.host);
+            if dry_run {
+                actions.push(format!("Would remove {}", description));
+                continue;
+            }
+
+            let outcome = match orphan.kind {
+                OrphanKind::KernelContainer | OrphanKind::KrunnerVolume => {
+                    match self.docker_client_for(&orphan.host).await {
+                        Some(client) if orphan.kind == OrphanKind::KernelContainer => {
+                            client.remove_container(&orphan.target).await
+                        }
+                        Some(client) => client.remove_volume(&orphan.target).await,
+                        None => Err(anyhow::anyhow!("Docker engine {} is unavailable", orphan.host)),
+                    }
+                }
+                OrphanKind::ScratchDirectory => std::fs::remove_dir_all(PathBuf::from(&orphan.target)).map_err(Into::into),
+                OrphanKind::ScratchImage => std::fs::remove_file(PathBuf::from(&orphan.target)).map_err(Into::into),
+            };
+
+            match outcome {
+                Ok(()) => actions.push(format!("Removed {}", description)),
+                Err(e) => actions.push(format!("Failed to remove {}: {}", description, e)),
+            }
+        }
+
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HealthChecker;
+
+    #[test]
+    fn test_orphan_results_report_skipped_hosts() {
+        let scan = OrphanScan {
+            orphans: Vec::new(),
+            skipped: vec![SkippedScan {
+                host: "gpu-node-2".to_string(),
+                reason: "Cannot list kernel containers: connection reset".to_string(),
+            }],
+        };
+
+        let results = HealthChecker::orphan_results(&Ok(scan));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].service_name, "Orphaned Resources (gpu-node-2)");
+        assert_eq!(results[0].status, HealthStatus::Unknown);
+        assert!(results[0].details.contains("Cannot list kernel containers"));
+    }
+
+    #[test]
+    fn test_orphan_results_name_the_failed_step() {
+        let found = Err(anyhow::anyhow!("connection refused")
+            .context("Cannot determine alive kernels: manager database unreachable"));
+
+        let results = HealthChecker::orphan_results(&found);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, HealthStatus::Unknown);
+        assert_eq!(
+            results[0].details,
+            "Cannot determine alive kernels: manager database unreachable: connection refused"
+        );
+    }
+
+    #[test]
+    fn test_orphan_results_healthy_when_all_hosts_inspected() {
+        let results = HealthChecker::orphan_results(&Ok(OrphanScan::default()));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, HealthStatus::Healthy);
+    }
+
+    const DEAD_KERNEL: &str = "0f6c2b8e-5d3a-4e21-9c7b-1a2b3c4d5e6f";
+    const LIVE_KERNEL: &str = "8a1d4f27-3c9e-4b60-a5d2-7e8f9a0b1c2d";
+
+    fn scratch_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("health-checker-orphans-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Backdate `path` so it is past any grace period used in these tests
+    fn age(path: &Path) {
+        let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(7200);
+        std::fs::File::open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn test_looks_like_kernel_id() {
+        assert!(looks_like_kernel_id(DEAD_KERNEL));
+        assert!(!looks_like_kernel_id("lost+found"));
+        assert!(!looks_like_kernel_id("0f6c2b8e5d3a4e219c7b1a2b3c4d5e6f"));
+        assert!(!looks_like_kernel_id("0f6c2b8e-5d3a-4e21-9c7b-1a2b3c4d5e6g"));
+        assert!(!looks_like_kernel_id("0f6c2b8e-5d3a-4e21-9c7b1-a2b3c4d5e6f-"));
+    }
+
+    #[test]
+    fn test_find_orphaned_scratch() {
+        let root = scratch_root("scratch");
+        for name in [
+            DEAD_KERNEL.to_string(),
+            format!("{}_tmp", DEAD_KERNEL),
+            format!("{}.img", DEAD_KERNEL),
+            LIVE_KERNEL.to_string(),
+            "lost+found".to_string(),
+        ] {
+            let path = root.join(&name);
+            if name.ends_with(".img") {
+                std::fs::write(&path, vec![0u8; 1024]).unwrap();
+            } else {
+                std::fs::create_dir(&path).unwrap();
+            }
+            age(&path);
+        }
+        let known: HashSet<String> = [LIVE_KERNEL.to_string()].into_iter().collect();
+
+        let mut orphans = find_orphaned_scratch(&root, &known, 3600).unwrap();
+        orphans.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+
+        let found: Vec<(String, OrphanKind)> = orphans.iter()
+            .map(|orphan| (orphan.display_name.clone(), orphan.kind.clone()))
+            .collect();
+        assert_eq!(found, vec![
+            (DEAD_KERNEL.to_string(), OrphanKind::ScratchDirectory),
+            (format!("{}.img", DEAD_KERNEL), OrphanKind::ScratchImage),
+            (format!("{}_tmp", DEAD_KERNEL), OrphanKind::ScratchDirectory),
+        ]);
+        assert!(orphans.iter().all(|orphan| orphan.kernel_id.as_deref() == Some(DEAD_KERNEL)));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_find_orphaned_scratch_respects_grace_period() {
+        let root = scratch_root("grace");
+        std::fs::create_dir(root.join(DEAD_KERNEL)).unwrap();
+
+        assert!(find_orphaned_scratch(&root, &HashSet::new(), 3600).unwrap().is_empty());
+        assert_eq!(find_orphaned_scratch(&root, &HashSet::new(), 0).unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn volume(name: &str, in_use: bool) -> KrunnerVolume {
+        KrunnerVolume {
+            name: name.to_string(),
+            in_use,
+            size_bytes: Some(300 * 1024 * 1024),
+        }
+    }
+
+    #[test]
+    fn test_find_orphaned_krunner_volumes() {
+        let volumes = vec![
+            volume("backendai-krunner.v8.x86_64.ubuntu22.04", false),
+            volume("backendai-krunner.v9.x86_64.ubuntu22.04", false),
+            volume("backendai-krunner.v10.x86_64.ubuntu22.04", true),
+            // Still mounted by a kernel, so kept although superseded
+            volume("backendai-krunner.v7.x86_64.ubuntu22.04", true),
+            // Latest for its own platform
+            volume("backendai-krunner.v8.aarch64.ubuntu22.04", false),
+            volume("backendai-krunner.vnext.x86_64.ubuntu22.04", false),
+        ];
+
+        let orphans = find_orphaned_krunner_volumes("gpu-node-1", &volumes);
+        let names: Vec<&str> = orphans.iter().map(|orphan| orphan.target.as_str()).collect();
+        assert_eq!(names, vec![
+            "backendai-krunner.v8.x86_64.ubuntu22.04",
+            "backendai-krunner.v9.x86_64.ubuntu22.04",
+        ]);
+        assert!(orphans.iter().all(|orphan| orphan.host == "gpu-node-1" && orphan.resource_usage == "300MB"));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(750), "12m30s");
+        assert_eq!(format_duration(3 * 3600 + 125), "3h2m");
+        assert_eq!(format_duration(3 * 86400 + 4 * 3600 + 59), "3d4h");
+    }
+}
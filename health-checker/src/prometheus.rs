@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+/// One sample line of the Prometheus text exposition format
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+}
+
+impl Sample {
+    pub fn label(&self, name: &str) -> Option<&str> {
+        self.labels.get(name).map(String::as_str)
+    }
+}
+
+/// Parse Prometheus text exposition output; comments and malformed lines are skipped
+pub fn parse_exposition(text: &str) -> Vec<Sample> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(parse_sample)
+        .collect()
+}
+
+fn parse_sample(line: &str) -> Option<Sample> {
+    let name_end = line.find(|c: char| c == '{' || c.is_whitespace())?;
+    let name = &line[..name_end];
+    let mut rest = &line[name_end..];
+
+    let mut labels = BTreeMap::new();
+    if let Some(label_text) = rest.strip_prefix('{') {
+        let (parsed, remainder) = parse_labels(label_text)?;
+        labels = parsed;
+        rest = remainder;
+    }
+
+    // An optional timestamp follows the value
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+    Some(Sample { name: name.to_string(), labels, value })
+}
+
+/// Labels up to the closing brace, and the text after it
+fn parse_labels(text: &str) -> Option<(BTreeMap<String, String>, &str)> {
+    let mut labels = BTreeMap::new();
+    let mut rest = text;
+
+    loop {
+        rest = rest.trim_start_matches([',', ' ']);
+        if let Some(remainder) = rest.strip_prefix('}') {
+            return Some((labels, remainder));
+        }
+
+        let (name, after_name) = rest.split_once('=')?;
+        let quoted = after_name.strip_prefix('"')?;
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    escaped => value.push(escaped),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+
+        labels.insert(name.trim().to_string(), value);
+        rest = &quoted[end + 1..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_exposition_lines() {
+        let samples = parse_exposition(concat!(
+            "# HELP up Whether the target is up.\n",
+            "# TYPE up gauge\n",
+            "up 1\n",
+            "http_requests_total{method=\"post\",code=\"200\"} 1027 1395066363000\n",
+            "msdos_file_access_time_seconds{path=\"C:\\\\DIR\\\\FILE.TXT\",error=\"Cannot find file:\\n\\\"FILE.TXT\\\"\"} 1.458255915e9\n",
+            "trailing_comma{a=\"1\",} NaN\n",
+            "broken{a=\"1\" 5\n",
+        ));
+
+        assert_eq!(samples.len(), 4);
+        assert_eq!(samples[0], Sample { name: "up".to_string(), labels: BTreeMap::new(), value: 1.0 });
+        assert_eq!(samples[1].label("code"), Some("200"));
+        assert_eq!(samples[1].value, 1027.0);
+        assert_eq!(samples[2].label("path"), Some("C:\\DIR\\FILE.TXT"));
+        assert_eq!(samples[2].label("error"), Some("Cannot find file:\n\"FILE.TXT\""));
+        assert_eq!(samples[2].value, 1.458255915e9);
+        assert!(samples[3].value.is_nan());
+    }
+}
@@ -36,9 +36,7 @@ impl crate::HealthChecker {
     async fn check_postgresql_internal(&self) -> Result<(HealthStatus, String)> {
         use tokio_postgres::NoTls;
 
-        let connection_string = "host=127.0.0.1 port=8101 user=postgres dbname=backend";
-        
-        match tokio_postgres::connect(connection_string, NoTls).await {
+        match tokio_postgres::connect(&self.config.infrastructure.postgres_dsn, NoTls).await {
             Ok((client, connection)) => {
                 // Spawn connection handler
                 tokio::spawn(async move {
@@ -99,8 +97,6 @@ impl crate::HealthChecker {
     }
 
     async fn check_redis_internal(&self) -> Result<(HealthStatus, String)> {
-        use redis::AsyncCommands;
-
         let client = redis::Client::open("redis://127.0.0.1:8111/")?;
         let mut conn = client.get_tokio_connection().await?;
 
@@ -0,0 +1,59 @@
+use crate::config::Threshold;
+use crate::HealthStatus;
+
+/// Severity of `value` under `threshold`; the Unhealthy level is checked first so it always wins
+pub fn severity(value: f64, threshold: &Threshold) -> Option<HealthStatus> {
+    if threshold.unhealthy.is_some_and(|limit| value > limit) {
+        Some(HealthStatus::Unhealthy)
+    } else if threshold.degraded.is_some_and(|limit| value > limit) {
+        Some(HealthStatus::Degraded)
+    } else {
+        None
+    }
+}
+
+/// Raise `status` to `severity` unless it is already worse
+pub fn escalate(status: &mut HealthStatus, severity: HealthStatus) {
+    match severity {
+        HealthStatus::Unhealthy => *status = HealthStatus::Unhealthy,
+        HealthStatus::Degraded if *status == HealthStatus::Healthy => *status = HealthStatus::Degraded,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold(degraded: f64, unhealthy: f64) -> Threshold {
+        Threshold { degraded: Some(degraded), unhealthy: Some(unhealthy) }
+    }
+
+    #[test]
+    fn unhealthy_level_wins_over_degraded() {
+        let temperature = threshold(85.0, 95.0);
+        assert_eq!(severity(85.0, &temperature), None);
+        assert_eq!(severity(85.5, &temperature), Some(HealthStatus::Degraded));
+        assert_eq!(severity(95.0, &temperature), Some(HealthStatus::Degraded));
+        assert_eq!(severity(96.0, &temperature), Some(HealthStatus::Unhealthy));
+        assert_eq!(severity(200.0, &Threshold::default()), None);
+    }
+
+    #[test]
+    fn unset_degraded_level_still_reports_unhealthy() {
+        let memory = Threshold { degraded: None, unhealthy: Some(99.0) };
+        assert_eq!(severity(98.0, &memory), None);
+        assert_eq!(severity(99.1, &memory), Some(HealthStatus::Unhealthy));
+    }
+
+    #[test]
+    fn escalation_never_lowers_status() {
+        let mut status = HealthStatus::Healthy;
+        escalate(&mut status, HealthStatus::Degraded);
+        assert_eq!(status, HealthStatus::Degraded);
+        escalate(&mut status, HealthStatus::Unhealthy);
+        assert_eq!(status, HealthStatus::Unhealthy);
+        escalate(&mut status, HealthStatus::Degraded);
+        assert_eq!(status, HealthStatus::Unhealthy);
+    }
+}